@@ -0,0 +1,166 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use plastmem_shared::AppError;
+use uuid::Uuid;
+
+use crate::{AiClient, ChatRequest, ChatResponse, ChatTask, TokenUsage, UsageRecord};
+
+/// What an AI call was made for. Attached to every [`ChatCallRecord`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallContext {
+  /// Worker job or API operation making the call, e.g. `predict_calibrate`.
+  pub job: Option<&'static str>,
+  pub conversation_id: Option<Uuid>,
+  pub episode_id: Option<Uuid>,
+}
+
+/// One chat request made by `generate_object` / `generate_text` and its outcome.
+#[derive(Debug, Clone)]
+pub struct ChatCallRecord {
+  pub task: ChatTask,
+  pub context: CallContext,
+  pub model: String,
+  /// Request messages, serialized as sent to the backend.
+  pub messages: serde_json::Value,
+  /// Raw reply content; `None` when the request itself failed.
+  pub response: Option<String>,
+  pub reasoning: Option<String>,
+  /// Request error, or the validation error of a reply that could not be parsed.
+  pub error: Option<String>,
+  pub latency: Duration,
+  pub usage: Option<TokenUsage>,
+}
+
+/// Receives a [`ChatCallRecord`] for every chat call made through an [`AiClient`].
+///
+/// Recording must never fail the call: implementations log and drop their own errors.
+#[async_trait]
+pub trait ChatCallSink: Send + Sync {
+  async fn record(&self, record: ChatCallRecord);
+}
+
+/// A chat call in flight, recorded once its outcome is known.
+///
+/// Usage goes to the client's `UsageSink`; the call itself to its [`ChatCallSink`].
+pub struct PendingChatCall<'a> {
+  ai: &'a AiClient,
+  task: ChatTask,
+  messages: Option<serde_json::Value>,
+  started: Instant,
+}
+
+impl<'a> PendingChatCall<'a> {
+  pub fn start(request: &ChatRequest, ai: &'a AiClient) -> Self {
+    let messages = ai
+      .call_sink()
+      .map(|_| serde_json::to_value(&request.messages).unwrap_or_default());
+    Self {
+      ai,
+      task: request.task,
+      messages,
+      started: Instant::now(),
+    }
+  }
+
+  pub async fn failed(self, err: &AppError) {
+    self.finish(None, Some(err.to_string())).await;
+  }
+
+  /// Record a reply; `parse_error` is set when it failed validation.
+  pub async fn replied(self, response: &ChatResponse, parse_error: Option<String>) {
+    self.finish(Some(response), parse_error).await;
+  }
+
+  async fn finish(self, response: Option<&ChatResponse>, error: Option<String>) {
+    let model = self.ai.chat_for(self.task).model();
+    if let (Some(sink), Some(usage)) = (self.ai.usage_sink(), response.and_then(|r| r.usage)) {
+      sink
+        .record_usage(UsageRecord {
+          context: self.ai.call_context(),
+          stage: self.task.name(),
+          model: model.to_owned(),
+          usage,
+        })
+        .await;
+    }
+
+    let (Some(sink), Some(messages)) = (self.ai.call_sink(), self.messages) else {
+      return;
+    };
+    sink
+      .record(ChatCallRecord {
+        task: self.task,
+        context: self.ai.call_context(),
+        model: model.to_owned(),
+        messages,
+        response: response.map(|r| r.content.clone()),
+        reasoning: response.and_then(|r| r.reasoning.clone()),
+        error,
+        latency: self.started.elapsed(),
+        usage: response.and_then(|r| r.usage),
+      })
+      .await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use schemars::JsonSchema;
+  use serde::Deserialize;
+
+  use super::*;
+  use crate::{ChatCompletionRequestMessage, ScriptedChatProvider, generate_object};
+
+  #[derive(Default)]
+  struct CollectingSink(Mutex<Vec<ChatCallRecord>>);
+
+  #[async_trait]
+  impl ChatCallSink for CollectingSink {
+    async fn record(&self, record: ChatCallRecord) {
+      self.0.lock().unwrap().push(record);
+    }
+  }
+
+  #[derive(Debug, Deserialize, JsonSchema)]
+  struct Title {
+    #[allow(dead_code)]
+    title: String,
+  }
+
+  #[tokio::test]
+  async fn records_every_attempt_with_context() {
+    let scripted = ScriptedChatProvider::new();
+    scripted.push_raw("episode_title", "not json");
+    scripted.push_raw("episode_title", "{\"title\": \"Fixed\"}");
+    let sink = Arc::new(CollectingSink::default());
+    let context = CallContext {
+      job: Some("episode_creation"),
+      conversation_id: Some(Uuid::nil()),
+      episode_id: None,
+    };
+    let ai = AiClient::scripted(Arc::new(scripted))
+      .with_call_sink(sink.clone())
+      .with_call_context(context);
+
+    generate_object::<Title>(
+      ChatTask::EpisodeTitle,
+      vec![ChatCompletionRequestMessage::User("...".into())],
+      "episode_title".to_owned(),
+      None,
+      &ai,
+    )
+    .await
+    .unwrap();
+
+    let records = std::mem::take(&mut *sink.0.lock().unwrap());
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|r| r.context == context));
+    assert_eq!(records[0].response.as_deref(), Some("not json"));
+    assert!(records[0].error.is_some());
+    assert!(records[1].error.is_none());
+    assert_eq!(records[1].messages.as_array().map(Vec::len), Some(3));
+  }
+}
@@ -0,0 +1,54 @@
+use async_openai::types::chat::{ChatCompletionRequestMessage, ReasoningEffort};
+use async_trait::async_trait;
+use plastmem_shared::AppError;
+use serde::{Deserialize, Serialize};
+
+use crate::ChatTask;
+
+/// JSON schema the reply must conform to.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseSchema {
+  pub name: String,
+  pub description: Option<String>,
+  pub schema: serde_json::Value,
+}
+
+/// Backend-agnostic chat completion request.
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+  /// Pipeline stage the request is made for; used for per-task routing.
+  pub task: ChatTask,
+  pub messages: Vec<ChatCompletionRequestMessage>,
+  /// When set, the backend must constrain the reply to this schema.
+  pub response_schema: Option<ResponseSchema>,
+  /// Overrides the backend's configured reasoning effort.
+  pub reasoning_effort: Option<ReasoningEffort>,
+}
+
+/// Token counts reported by the backend for one request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+  pub prompt_tokens: u32,
+  pub completion_tokens: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+  /// Final message content returned by the model, without any reasoning trace.
+  pub content: String,
+  /// Reasoning trace, from `reasoning_content` or an inline `<think>` block.
+  pub reasoning: Option<String>,
+  /// Token usage, when the backend reports it.
+  pub usage: Option<TokenUsage>,
+}
+
+/// A chat completion backend.
+///
+/// Implementations own their transport, model selection and retry policy.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+  /// Model identifier used for this backend's replies.
+  fn model(&self) -> &str;
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError>;
+}
@@ -0,0 +1,57 @@
+/// Pipeline stage a chat request is made for.
+///
+/// `AiClient` routes each task to its own backend when one is registered, so
+/// cheap classification calls and heavy consolidation calls can use different
+/// models. Overrides are read from `AI_TASK_<NAME>_*` (see [`ChatTask::env_prefix`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChatTask {
+  /// Primitive segment classification in batch segmentation.
+  SegmentationClassify,
+  /// Splitting an over-long primitive segment.
+  SegmentationSplit,
+  /// Constrained re-segmentation of informative segment groups.
+  SegmentationResegment,
+  /// Episode title generation.
+  EpisodeTitle,
+  /// Time anchor insertion into episode lines.
+  EpisodeTimeAnchor,
+  /// FSRS relevance review of retrieved memories.
+  MemoryReview,
+  /// Predicting an episode from existing semantic facts.
+  Prediction,
+  /// Cold-start and prediction-calibrated semantic fact extraction.
+  Consolidation,
+}
+
+impl ChatTask {
+  pub const ALL: [Self; 8] = [
+    Self::SegmentationClassify,
+    Self::SegmentationSplit,
+    Self::SegmentationResegment,
+    Self::EpisodeTitle,
+    Self::EpisodeTimeAnchor,
+    Self::MemoryReview,
+    Self::Prediction,
+    Self::Consolidation,
+  ];
+
+  #[must_use]
+  pub const fn name(self) -> &'static str {
+    match self {
+      Self::SegmentationClassify => "segmentation_classify",
+      Self::SegmentationSplit => "segmentation_split",
+      Self::SegmentationResegment => "segmentation_resegment",
+      Self::EpisodeTitle => "episode_title",
+      Self::EpisodeTimeAnchor => "episode_time_anchor",
+      Self::MemoryReview => "memory_review",
+      Self::Prediction => "prediction",
+      Self::Consolidation => "consolidation",
+    }
+  }
+
+  /// Environment variable prefix for per-task overrides, e.g. `AI_TASK_EPISODE_TITLE_`.
+  #[must_use]
+  pub fn env_prefix(self) -> String {
+    format!("AI_TASK_{}_", self.name().to_ascii_uppercase())
+  }
+}
@@ -0,0 +1,353 @@
+use std::{
+  fmt,
+  sync::{Arc, Mutex, PoisonError},
+  time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use plastmem_shared::{APP_ENV, AppError};
+use reqwest::StatusCode;
+use serde::Serialize;
+use tokio::time::{Instant, sleep_until};
+use utoipa::ToSchema;
+
+use crate::{
+  ChatProvider, ChatRequest, ChatResponse, EmbeddingProvider, EmbeddingResponse, RetryableError,
+};
+
+/// When a [`CircuitBreaker`] opens and how long it stays open.
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerConfig {
+  /// Consecutive failed requests that open the breaker. `0` disables it.
+  pub failure_threshold: u32,
+  /// How long the breaker stays open before letting a request through again.
+  pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+  fn default() -> Self {
+    Self {
+      failure_threshold: 0,
+      cooldown: Duration::from_secs(30),
+    }
+  }
+}
+
+impl CircuitBreakerConfig {
+  /// Read `AI_BREAKER_FAILURE_THRESHOLD` and `AI_BREAKER_COOLDOWN_SECONDS`.
+  #[must_use]
+  pub fn from_env() -> Self {
+    Self {
+      failure_threshold: APP_ENV.ai_breaker_failure_threshold,
+      cooldown: Duration::from_secs(APP_ENV.ai_breaker_cooldown_seconds),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+  /// Requests go through.
+  Closed,
+  /// The backend is considered down; requests fail fast until the cooldown ends.
+  Open,
+  /// The cooldown ended; the next request decides whether the breaker closes or reopens.
+  HalfOpen,
+}
+
+/// Snapshot of a [`CircuitBreaker`], as reported by the status endpoint.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BreakerStatus {
+  pub state: BreakerState,
+  pub consecutive_failures: u32,
+  /// When the breaker last opened, while it is open or half-open.
+  pub opened_at: Option<DateTime<Utc>>,
+  /// When an open breaker lets a request through again.
+  pub retry_at: Option<DateTime<Utc>>,
+  pub last_error: Option<String>,
+}
+
+struct Opened {
+  at: Instant,
+  at_utc: DateTime<Utc>,
+}
+
+struct BreakerInner {
+  state: BreakerState,
+  consecutive_failures: u32,
+  opened: Option<Opened>,
+  last_error: Option<String>,
+}
+
+/// Error of a request refused by an open [`CircuitBreaker`], served as `503 Service Unavailable`.
+#[derive(Debug, Clone)]
+pub struct CircuitOpen {
+  breaker: &'static str,
+  retry_in: Duration,
+}
+
+impl fmt::Display for CircuitOpen {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "AI {} backend unavailable: circuit breaker open for another {}s",
+      self.breaker,
+      self.retry_in.as_secs()
+    )
+  }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// Stops calling a backend that keeps failing.
+///
+/// After `failure_threshold` consecutive failed requests (each already retried by
+/// the transport) the breaker opens: requests fail fast with [`CircuitOpen`] and
+/// workers wait in [`CircuitBreaker::wait_until_available`] instead of burning
+/// job attempts. Once `cooldown` has passed the breaker is half-open: requests
+/// go through again, the first success closes it and the first failure reopens it.
+///
+/// Only a [`RetryableError`] counts as a failure. Any other error, such as a
+/// `400 Bad Request` or an unparsable reply, means the backend answered, so it
+/// counts like a success.
+pub struct CircuitBreaker {
+  name: &'static str,
+  config: CircuitBreakerConfig,
+  inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+  #[must_use]
+  pub const fn new(name: &'static str, config: CircuitBreakerConfig) -> Self {
+    Self {
+      name,
+      config,
+      inner: Mutex::new(BreakerInner {
+        state: BreakerState::Closed,
+        consecutive_failures: 0,
+        opened: None,
+        last_error: None,
+      }),
+    }
+  }
+
+  /// Backend the breaker guards, e.g. `chat` or `embedding`.
+  #[must_use]
+  pub const fn name(&self) -> &'static str {
+    self.name
+  }
+
+  #[must_use]
+  pub fn status(&self) -> BreakerStatus {
+    let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+    let retry_at = (inner.state == BreakerState::Open)
+      .then(|| inner.opened.as_ref())
+      .flatten()
+      .and_then(|opened| {
+        chrono::Duration::from_std(self.config.cooldown)
+          .ok()
+          .map(|cooldown| opened.at_utc + cooldown)
+      });
+    BreakerStatus {
+      state: inner.state,
+      consecutive_failures: inner.consecutive_failures,
+      opened_at: inner.opened.as_ref().map(|opened| opened.at_utc),
+      retry_at,
+      last_error: inner.last_error.clone(),
+    }
+  }
+
+  /// Time at which an open breaker lets requests through again, or `None` if it already does.
+  fn blocked_until(&self) -> Option<Instant> {
+    let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+    if inner.state != BreakerState::Open {
+      return None;
+    }
+    let retry_at = inner
+      .opened
+      .as_ref()
+      .map_or_else(Instant::now, |opened| opened.at + self.config.cooldown);
+    if Instant::now() < retry_at {
+      return Some(retry_at);
+    }
+    inner.state = BreakerState::HalfOpen;
+    drop(inner);
+    tracing::info!(
+      breaker = self.name,
+      "AI circuit breaker half-open, probing backend"
+    );
+    None
+  }
+
+  /// Fail fast while the breaker is open.
+  fn check(&self) -> Result<(), AppError> {
+    let Some(retry_at) = self.blocked_until() else {
+      return Ok(());
+    };
+    Err(AppError::with_status(
+      StatusCode::SERVICE_UNAVAILABLE,
+      CircuitOpen {
+        breaker: self.name,
+        retry_in: retry_at.saturating_duration_since(Instant::now()),
+      },
+    ))
+  }
+
+  /// Wait until the breaker lets requests through.
+  pub async fn wait_until_available(&self) {
+    while let Some(retry_at) = self.blocked_until() {
+      tracing::info!(
+        breaker = self.name,
+        "AI circuit breaker open, pausing until the cooldown ends"
+      );
+      sleep_until(retry_at).await;
+    }
+  }
+
+  fn record<T>(&self, result: &Result<T, AppError>) {
+    if self.config.failure_threshold == 0 {
+      return;
+    }
+    let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+    match result {
+      Err(err) if err.downcast_ref::<RetryableError>().is_some() => {
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        inner.last_error = Some(err.to_string());
+        let trips = inner.state == BreakerState::HalfOpen
+          || (inner.state == BreakerState::Closed
+            && inner.consecutive_failures >= self.config.failure_threshold);
+        if trips {
+          inner.state = BreakerState::Open;
+          inner.opened = Some(Opened {
+            at: Instant::now(),
+            at_utc: Utc::now(),
+          });
+          tracing::error!(
+            breaker = self.name,
+            consecutive_failures = inner.consecutive_failures,
+            cooldown_secs = self.config.cooldown.as_secs(),
+            error = %err,
+            "AI circuit breaker opened"
+          );
+        }
+      }
+      _ => {
+        if inner.state != BreakerState::Closed {
+          tracing::info!(breaker = self.name, "AI circuit breaker closed");
+        }
+        inner.state = BreakerState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened = None;
+      }
+    }
+  }
+}
+
+/// Whether `err` came from an open [`CircuitBreaker`].
+#[must_use]
+pub fn is_circuit_open(err: &AppError) -> bool {
+  err.downcast_ref::<CircuitOpen>().is_some()
+}
+
+/// Chat backend guarded by a [`CircuitBreaker`].
+pub struct BreakerChatProvider {
+  pub inner: Arc<dyn ChatProvider>,
+  pub breaker: Arc<CircuitBreaker>,
+}
+
+#[async_trait]
+impl ChatProvider for BreakerChatProvider {
+  fn model(&self) -> &str {
+    self.inner.model()
+  }
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError> {
+    self.breaker.check()?;
+    let result = self.inner.chat(request).await;
+    self.breaker.record(&result);
+    result
+  }
+}
+
+/// Embedding backend guarded by a [`CircuitBreaker`].
+pub struct BreakerEmbeddingProvider {
+  pub inner: Arc<dyn EmbeddingProvider>,
+  pub breaker: Arc<CircuitBreaker>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for BreakerEmbeddingProvider {
+  fn model(&self) -> &str {
+    self.inner.model()
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    self.breaker.check()?;
+    let result = self.inner.embed(inputs).await;
+    self.breaker.record(&result);
+    result
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use anyhow::anyhow;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn opens_after_threshold_and_closes_after_probe() {
+    let breaker = CircuitBreaker::new(
+      "chat",
+      CircuitBreakerConfig {
+        failure_threshold: 2,
+        cooldown: Duration::from_millis(20),
+      },
+    );
+    let failure = || Err::<(), _>(AppError::new(RetryableError(anyhow!("connection refused"))));
+
+    breaker.record(&failure());
+    assert!(breaker.check().is_ok());
+    breaker.record(&failure());
+    let err = breaker.check().unwrap_err();
+    assert!(is_circuit_open(&err));
+    assert_eq!(breaker.status().state, BreakerState::Open);
+    assert!(breaker.status().retry_at.is_some());
+
+    // A failed probe reopens the breaker for another cooldown
+    breaker.wait_until_available().await;
+    assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+    breaker.record(&failure());
+    assert!(breaker.check().is_err());
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(breaker.check().is_ok());
+    breaker.record(&Ok(()));
+    let status = breaker.status();
+    assert_eq!(status.state, BreakerState::Closed);
+    assert_eq!(status.consecutive_failures, 0);
+  }
+
+  #[test]
+  fn ignores_errors_the_backend_answered_with() {
+    let breaker = CircuitBreaker::new(
+      "chat",
+      CircuitBreakerConfig {
+        failure_threshold: 1,
+        cooldown: Duration::from_secs(30),
+      },
+    );
+
+    breaker.record(&Err::<(), _>(AppError::with_status(
+      StatusCode::SERVICE_UNAVAILABLE,
+      anyhow!("400 Bad Request: context length exceeded"),
+    )));
+    assert_eq!(breaker.status().state, BreakerState::Closed);
+    assert_eq!(breaker.status().consecutive_failures, 0);
+
+    // A 503 that did not come from a breaker is not a breaker trip
+    let unrelated = AppError::with_status(StatusCode::SERVICE_UNAVAILABLE, anyhow!("overloaded"));
+    assert!(!is_circuit_open(&unrelated));
+  }
+}
@@ -0,0 +1,513 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::anyhow;
+use async_openai::types::chat::ReasoningEffort;
+use plastmem_shared::{APP_ENV, AppError, ChatModelEnv};
+
+#[cfg(feature = "tokenizer")]
+use crate::TokenizerCounter;
+use crate::{
+  CallContext, ChatCallSink, ChatProvider, ChatTask, CircuitBreaker, CircuitBreakerConfig,
+  DEFAULT_CONTEXT_TOKENS, DEFAULT_EMBEDDING_DIM, EmbeddingBatcher, EmbeddingKind,
+  EmbeddingProvider, EstimatedTokenCounter, HttpTransport, MessagesProvider, OllamaProvider,
+  OpenAiProvider, PromptRegistry, ReplayProvider, RequestLimits, TokenBudget, TokenCounter,
+  UsageSink,
+  circuit_breaker::{BreakerChatProvider, BreakerEmbeddingProvider},
+};
+#[cfg(feature = "local-embedding")]
+use crate::{LocalEmbeddingProvider, Pooling};
+
+/// Handle to the chat and embedding backends used by the memory pipeline.
+///
+/// Chat requests are routed by [`ChatTask`]: a task with a registered backend
+/// uses it, every other task uses the default chat backend.
+///
+/// Cheap to clone; injected into workers through apalis `Data` and into the
+/// HTTP server through `AppState`.
+#[derive(Clone)]
+pub struct AiClient {
+  chat: Arc<dyn ChatProvider>,
+  task_chat: HashMap<ChatTask, Arc<dyn ChatProvider>>,
+  embedding: Arc<dyn EmbeddingProvider>,
+  chat_breaker: Arc<CircuitBreaker>,
+  embedding_breaker: Arc<CircuitBreaker>,
+  context_tokens: usize,
+  task_context_tokens: HashMap<ChatTask, usize>,
+  token_counter: Arc<dyn TokenCounter>,
+  embedding_dim: usize,
+  query_template: Option<String>,
+  document_template: Option<String>,
+  prompts: Arc<PromptRegistry>,
+  reasoning_effort: Option<ReasoningEffort>,
+  log_reasoning: bool,
+  call_sink: Option<Arc<dyn ChatCallSink>>,
+  usage_sink: Option<Arc<dyn UsageSink>>,
+  call_context: CallContext,
+}
+
+impl AiClient {
+  #[must_use]
+  pub fn new(chat: Arc<dyn ChatProvider>, embedding: Arc<dyn EmbeddingProvider>) -> Self {
+    Self {
+      chat,
+      task_chat: HashMap::new(),
+      embedding,
+      chat_breaker: Arc::new(CircuitBreaker::new("chat", CircuitBreakerConfig::default())),
+      embedding_breaker: Arc::new(CircuitBreaker::new(
+        "embedding",
+        CircuitBreakerConfig::default(),
+      )),
+      context_tokens: DEFAULT_CONTEXT_TOKENS,
+      task_context_tokens: HashMap::new(),
+      token_counter: Arc::new(EstimatedTokenCounter),
+      embedding_dim: DEFAULT_EMBEDDING_DIM,
+      query_template: None,
+      document_template: None,
+      prompts: Arc::default(),
+      reasoning_effort: None,
+      log_reasoning: false,
+      call_sink: None,
+      usage_sink: None,
+      call_context: CallContext::default(),
+    }
+  }
+
+  /// Use one backend for both chat and embeddings.
+  #[must_use]
+  pub fn from_provider<P>(provider: P) -> Self
+  where
+    P: ChatProvider + EmbeddingProvider + 'static,
+  {
+    let provider = Arc::new(provider);
+    Self::new(provider.clone(), provider)
+  }
+
+  /// Route `task` to its own chat backend.
+  #[must_use]
+  pub fn with_task_chat(mut self, task: ChatTask, chat: Arc<dyn ChatProvider>) -> Self {
+    self.task_chat.insert(task, chat);
+    self
+  }
+
+  /// Wrap the embedding backend, e.g. with a cache.
+  #[must_use]
+  pub fn map_embedding(
+    mut self,
+    f: impl FnOnce(Arc<dyn EmbeddingProvider>) -> Arc<dyn EmbeddingProvider>,
+  ) -> Self {
+    self.embedding = f(self.embedding);
+    self
+  }
+
+  /// Guard the chat and embedding backends with circuit breakers (see [`CircuitBreaker`]).
+  ///
+  /// Every chat backend, per-task ones included, shares the chat breaker. Wrappers
+  /// added afterwards with [`AiClient::map_embedding`] sit outside the breaker, so
+  /// cached embeddings are still served while it is open.
+  #[must_use]
+  pub fn with_circuit_breakers(mut self, config: CircuitBreakerConfig) -> Self {
+    let chat_breaker = Arc::new(CircuitBreaker::new("chat", config));
+    let embedding_breaker = Arc::new(CircuitBreaker::new("embedding", config));
+    let guard_chat = |inner| -> Arc<dyn ChatProvider> {
+      Arc::new(BreakerChatProvider {
+        inner,
+        breaker: chat_breaker.clone(),
+      })
+    };
+    self.chat = guard_chat(self.chat);
+    self.task_chat = self
+      .task_chat
+      .into_iter()
+      .map(|(task, chat)| (task, guard_chat(chat)))
+      .collect();
+    self.embedding = Arc::new(BreakerEmbeddingProvider {
+      inner: self.embedding,
+      breaker: embedding_breaker.clone(),
+    });
+    self.chat_breaker = chat_breaker;
+    self.embedding_breaker = embedding_breaker;
+    self
+  }
+
+  /// Context window of the default chat model.
+  #[must_use]
+  pub const fn with_context_tokens(mut self, context_tokens: usize) -> Self {
+    self.context_tokens = context_tokens;
+    self
+  }
+
+  /// Context window of the model `task` is routed to.
+  #[must_use]
+  pub fn with_task_context_tokens(mut self, task: ChatTask, context_tokens: usize) -> Self {
+    self.task_context_tokens.insert(task, context_tokens);
+    self
+  }
+
+  /// Count prompt tokens with `counter` instead of the [`EstimatedTokenCounter`].
+  #[must_use]
+  pub fn with_token_counter(mut self, counter: Arc<dyn TokenCounter>) -> Self {
+    self.token_counter = counter;
+    self
+  }
+
+  /// Truncate and normalize embeddings to `embedding_dim` dimensions.
+  #[must_use]
+  pub const fn with_embedding_dim(mut self, embedding_dim: usize) -> Self {
+    self.embedding_dim = embedding_dim;
+    self
+  }
+
+  /// Instruction template applied to `kind` inputs before embedding (see [`EmbeddingKind`]).
+  #[must_use]
+  pub fn with_embedding_template(mut self, kind: EmbeddingKind, template: Option<String>) -> Self {
+    match kind {
+      EmbeddingKind::Query => self.query_template = template,
+      EmbeddingKind::Document => self.document_template = template,
+    }
+    self
+  }
+
+  /// Prompt templates that replace the built-in prompts of the same name.
+  #[must_use]
+  pub fn with_prompts(mut self, prompts: PromptRegistry) -> Self {
+    self.prompts = Arc::new(prompts);
+    self
+  }
+
+  /// Override the configured reasoning effort for requests made through this handle.
+  ///
+  /// ```rust,ignore
+  /// generate_object::<T>(task, messages, name, None, &ai.with_reasoning_effort(ReasoningEffort::High))
+  /// ```
+  #[must_use]
+  pub fn with_reasoning_effort(&self, reasoning_effort: ReasoningEffort) -> Self {
+    Self {
+      reasoning_effort: Some(reasoning_effort),
+      ..self.clone()
+    }
+  }
+
+  /// Log reasoning traces returned by the model (`plastmem_ai::reasoning` target).
+  #[must_use]
+  pub const fn with_reasoning_logging(mut self, log_reasoning: bool) -> Self {
+    self.log_reasoning = log_reasoning;
+    self
+  }
+
+  /// Record every chat call made through this client in `sink`.
+  #[must_use]
+  pub fn with_call_sink(mut self, sink: Arc<dyn ChatCallSink>) -> Self {
+    self.call_sink = Some(sink);
+    self
+  }
+
+  /// Report the token usage of every chat and embedding request to `sink`.
+  #[must_use]
+  pub fn with_usage_sink(mut self, sink: Arc<dyn UsageSink>) -> Self {
+    self.usage_sink = Some(sink);
+    self
+  }
+
+  /// Attribute calls made through this handle to a job, conversation or episode.
+  ///
+  /// ```rust,ignore
+  /// let ai = ai.with_call_context(CallContext { job: Some("memory_review"), ..Default::default() });
+  /// ```
+  #[must_use]
+  pub fn with_call_context(&self, call_context: CallContext) -> Self {
+    Self {
+      call_context,
+      ..self.clone()
+    }
+  }
+
+  /// Use the backend configured by `OPENAI_*` for both chat and embeddings, with
+  /// per-task chat overrides from `AI_TASK_<NAME>_*`.
+  ///
+  /// `OPENAI_CHAT_PROTOCOL` (or `AI_TASK_<NAME>_PROTOCOL`) selects the wire protocol of a
+  /// chat backend: `OpenAI` chat completions, native Ollama or Messages-style. Embeddings
+  /// use the `OpenAI` protocol, or Ollama's with `EMBEDDING_BACKEND=ollama`.
+  ///
+  /// When `AI_FIXTURES_DIR` is set, requests go through a [`ReplayProvider`] instead:
+  /// fixtures are recorded from the configured backends if `AI_FIXTURES_RECORD` is
+  /// enabled, and replayed without network access otherwise.
+  ///
+  /// `EMBEDDING_BACKEND=local` replaces the embedding backend with an in-process
+  /// model (requires the `local-embedding` feature).
+  ///
+  /// Concurrent embedding calls are coalesced by an [`EmbeddingBatcher`] unless
+  /// `EMBEDDING_BATCH_WINDOW_MS` is `0`, and backends are guarded by circuit breakers
+  /// configured by `AI_BREAKER_*`.
+  pub fn from_env() -> Result<Self, AppError> {
+    let client = Self::backends_from_env()?
+      .with_local_embedding_from_env()?
+      .with_circuit_breakers(CircuitBreakerConfig::from_env())
+      .with_token_budgets_from_env()?;
+    let client = if APP_ENV.embedding_batch_window_ms > 0 {
+      client.map_embedding(|inner| {
+        Arc::new(EmbeddingBatcher::new(
+          inner,
+          Duration::from_millis(APP_ENV.embedding_batch_window_ms),
+          APP_ENV.embedding_batch_max_inputs,
+        ))
+      })
+    } else {
+      client
+    };
+    Ok(
+      client
+        .with_embedding_dim(APP_ENV.embedding_dim)
+        .with_embedding_template(
+          EmbeddingKind::Query,
+          APP_ENV.embedding_query_template.clone(),
+        )
+        .with_embedding_template(
+          EmbeddingKind::Document,
+          APP_ENV.embedding_document_template.clone(),
+        )
+        .with_reasoning_logging(APP_ENV.log_reasoning_traces),
+    )
+  }
+
+  /// Context sizes from `OPENAI_CONTEXT_TOKENS` / `AI_TASK_<NAME>_CONTEXT_TOKENS`, and
+  /// the tokenizer from `PROMPT_TOKENIZER_PATH`.
+  fn with_token_budgets_from_env(self) -> Result<Self, AppError> {
+    let client = ChatTask::ALL.into_iter().fold(
+      self.with_context_tokens(APP_ENV.chat_model_env().context_tokens),
+      |client, task| match APP_ENV.chat_model_env_override(&task.env_prefix()) {
+        Some(env) => client.with_task_context_tokens(task, env.context_tokens),
+        None => client,
+      },
+    );
+    match &APP_ENV.prompt_tokenizer_path {
+      Some(path) => Ok(client.with_token_counter(Self::token_counter_from_path(path)?)),
+      None => Ok(client),
+    }
+  }
+
+  #[cfg(feature = "tokenizer")]
+  fn token_counter_from_path(path: &str) -> Result<Arc<dyn TokenCounter>, AppError> {
+    Ok(Arc::new(TokenizerCounter::load(path)?))
+  }
+
+  #[cfg(not(feature = "tokenizer"))]
+  fn token_counter_from_path(_path: &str) -> Result<Arc<dyn TokenCounter>, AppError> {
+    Err(anyhow!("PROMPT_TOKENIZER_PATH requires building with the `tokenizer` feature").into())
+  }
+
+  fn with_local_embedding_from_env(self) -> Result<Self, AppError> {
+    if !APP_ENV.embedding_backend.eq_ignore_ascii_case("local") {
+      return Ok(self);
+    }
+    let local = Self::local_embedding_from_env()?;
+    Ok(self.map_embedding(|_| local))
+  }
+
+  #[cfg(feature = "local-embedding")]
+  fn local_embedding_from_env() -> Result<Arc<dyn EmbeddingProvider>, AppError> {
+    let dir = APP_ENV
+      .local_embedding_model_dir
+      .as_deref()
+      .ok_or_else(|| anyhow!("EMBEDDING_BACKEND=local requires LOCAL_EMBEDDING_MODEL_DIR"))?;
+    let pooling = Pooling::parse(&APP_ENV.local_embedding_pooling).ok_or_else(|| {
+      anyhow!(
+        "unknown LOCAL_EMBEDDING_POOLING `{}`: expected mean or cls",
+        APP_ENV.local_embedding_pooling
+      )
+    })?;
+    Ok(Arc::new(LocalEmbeddingProvider::load(
+      dir,
+      pooling,
+      APP_ENV.local_embedding_max_tokens,
+    )?))
+  }
+
+  #[cfg(not(feature = "local-embedding"))]
+  fn local_embedding_from_env() -> Result<Arc<dyn EmbeddingProvider>, AppError> {
+    Err(
+      anyhow!("EMBEDDING_BACKEND=local requires building with the `local-embedding` feature")
+        .into(),
+    )
+  }
+
+  fn backends_from_env() -> Result<Self, AppError> {
+    let Some(fixtures_dir) = &APP_ENV.ai_fixtures_dir else {
+      return Self::remote_from_env();
+    };
+
+    if APP_ENV.ai_fixtures_record {
+      return Ok(Self::from_provider(ReplayProvider::record(
+        fixtures_dir,
+        Self::remote_from_env()?,
+      )));
+    }
+
+    let replay = ChatTask::ALL.into_iter().fold(
+      ReplayProvider::replay(
+        fixtures_dir,
+        APP_ENV.openai_chat_model.clone(),
+        APP_ENV.openai_embedding_model.clone(),
+      ),
+      |replay, task| match APP_ENV.chat_model_env_override(&task.env_prefix()) {
+        Some(env) => replay.with_task_model(task, env.model),
+        None => replay,
+      },
+    );
+    Ok(Self::from_provider(replay))
+  }
+
+  fn remote_from_env() -> Result<Self, AppError> {
+    // One transport for every backend, so request limits apply process-wide
+    let transport = HttpTransport::new(RequestLimits::from_env());
+    let chat = Self::chat_from_model_env(&APP_ENV.chat_model_env(), &transport)?;
+    let embedding: Arc<dyn EmbeddingProvider> =
+      match APP_ENV.embedding_backend.to_ascii_lowercase().as_str() {
+        // `local` is swapped in by `with_local_embedding_from_env`
+        "openai" | "local" => {
+          Arc::new(OpenAiProvider::from_env().with_transport(transport.clone()))
+        }
+        "ollama" => Arc::new(
+          OllamaProvider::from_model_env(
+            &APP_ENV.chat_model_env(),
+            APP_ENV.openai_embedding_model.clone(),
+          )
+          .with_transport(transport.clone()),
+        ),
+        other => {
+          return Err(
+            anyhow!("unknown EMBEDDING_BACKEND `{other}`: expected openai, ollama or local").into(),
+          );
+        }
+      };
+
+    ChatTask::ALL
+      .into_iter()
+      .try_fold(Self::new(chat, embedding), |client, task| {
+        let Some(env) = APP_ENV.chat_model_env_override(&task.env_prefix()) else {
+          return Ok(client);
+        };
+        tracing::info!(
+          task = task.name(),
+          protocol = %env.protocol,
+          model = %env.model,
+          "using per-task chat model"
+        );
+        Ok(client.with_task_chat(task, Self::chat_from_model_env(&env, &transport)?))
+      })
+  }
+
+  /// Chat backend speaking the protocol selected by `env`.
+  fn chat_from_model_env(
+    env: &ChatModelEnv,
+    transport: &HttpTransport,
+  ) -> Result<Arc<dyn ChatProvider>, AppError> {
+    let transport = transport.clone();
+    let embedding_model = APP_ENV.openai_embedding_model.clone();
+    match env.protocol.to_ascii_lowercase().as_str() {
+      "openai" => Ok(Arc::new(
+        OpenAiProvider::from_model_env(env, embedding_model).with_transport(transport),
+      )),
+      "ollama" => Ok(Arc::new(
+        OllamaProvider::from_model_env(env, embedding_model).with_transport(transport),
+      )),
+      "messages" => Ok(Arc::new(
+        MessagesProvider::from_model_env(env).with_transport(transport),
+      )),
+      other => {
+        Err(anyhow!("unknown chat protocol `{other}`: expected openai, ollama or messages").into())
+      }
+    }
+  }
+
+  /// Default chat backend.
+  #[must_use]
+  pub fn chat(&self) -> &dyn ChatProvider {
+    self.chat.as_ref()
+  }
+
+  /// Chat backend registered for `task`, or the default one.
+  #[must_use]
+  pub fn chat_for(&self, task: ChatTask) -> &dyn ChatProvider {
+    self.task_chat.get(&task).unwrap_or(&self.chat).as_ref()
+  }
+
+  /// Context window of the model `task` is routed to.
+  #[must_use]
+  pub fn context_tokens(&self, task: ChatTask) -> usize {
+    self
+      .task_context_tokens
+      .get(&task)
+      .copied()
+      .unwrap_or(self.context_tokens)
+  }
+
+  /// Prompt budget for one `task` request, before its system prompt is reserved.
+  #[must_use]
+  pub fn token_budget(&self, task: ChatTask) -> TokenBudget {
+    TokenBudget::new(self.context_tokens(task), self.token_counter.clone())
+  }
+
+  /// Budget of exactly `max_tokens` for text handed to another model, e.g. retrieval output.
+  #[must_use]
+  pub fn output_budget(&self, max_tokens: usize) -> TokenBudget {
+    TokenBudget::exact(max_tokens, self.token_counter.clone())
+  }
+
+  #[must_use]
+  pub const fn reasoning_effort(&self) -> Option<&ReasoningEffort> {
+    self.reasoning_effort.as_ref()
+  }
+
+  #[must_use]
+  pub const fn log_reasoning(&self) -> bool {
+    self.log_reasoning
+  }
+
+  #[must_use]
+  pub const fn call_context(&self) -> CallContext {
+    self.call_context
+  }
+
+  pub(crate) fn call_sink(&self) -> Option<&dyn ChatCallSink> {
+    self.call_sink.as_deref()
+  }
+
+  pub(crate) fn usage_sink(&self) -> Option<&dyn UsageSink> {
+    self.usage_sink.as_deref()
+  }
+
+  /// Dimension of the vectors returned by `embed` / `embed_many`.
+  #[must_use]
+  pub const fn embedding_dim(&self) -> usize {
+    self.embedding_dim
+  }
+
+  #[must_use]
+  pub fn embedding_template(&self, kind: EmbeddingKind) -> Option<&str> {
+    match kind {
+      EmbeddingKind::Query => self.query_template.as_deref(),
+      EmbeddingKind::Document => self.document_template.as_deref(),
+    }
+  }
+
+  #[must_use]
+  pub fn prompts(&self) -> &PromptRegistry {
+    &self.prompts
+  }
+
+  #[must_use]
+  pub fn embedding(&self) -> &dyn EmbeddingProvider {
+    self.embedding.as_ref()
+  }
+
+  /// Breaker shared by every chat backend.
+  #[must_use]
+  pub fn chat_breaker(&self) -> &CircuitBreaker {
+    &self.chat_breaker
+  }
+
+  #[must_use]
+  pub fn embedding_breaker(&self) -> &CircuitBreaker {
+    &self.embedding_breaker
+  }
+}
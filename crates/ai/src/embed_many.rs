@@ -1,52 +1,34 @@
-use anyhow::anyhow;
-use async_openai::{Client, config::OpenAIConfig, types::embeddings::CreateEmbeddingRequestArgs};
-use plastmem_shared::{APP_ENV, AppError};
+use plastmem_shared::AppError;
 use sea_orm::prelude::PgVector;
 
-use crate::embed_shared::{EMBEDDING_DIM, process_embedding, request_embedding_with_retry};
+use crate::{
+  AiClient, EmbeddingKind, embed_shared::process_embedding,
+  embedding_kind::render_embedding_template, usage::record_embedding_usage,
+};
 
 /// Embed multiple texts in a single API call.
 ///
 /// Returns one `PgVector` per input, in the same order.
-pub async fn embed_many(inputs: &[String]) -> Result<Vec<PgVector>, AppError> {
+pub async fn embed_many(
+  kind: EmbeddingKind,
+  inputs: &[String],
+  ai: &AiClient,
+) -> Result<Vec<PgVector>, AppError> {
   if inputs.is_empty() {
     return Ok(vec![]);
   }
 
-  let embedding_dim =
-    u32::try_from(EMBEDDING_DIM).map_err(|_| anyhow!("EMBEDDING_DIM must fit in u32"))?;
-  let config = OpenAIConfig::new()
-    .with_api_key(&APP_ENV.openai_api_key)
-    .with_api_base(&APP_ENV.openai_base_url);
+  let template = ai.embedding_template(kind);
+  let inputs = inputs
+    .iter()
+    .map(|input| render_embedding_template(template, input))
+    .collect();
 
-  let client = Client::with_config(config);
-
-  let request = CreateEmbeddingRequestArgs::default()
-    .model(&APP_ENV.openai_embedding_model)
-    .input(inputs.to_vec())
-    .dimensions(embedding_dim)
-    .build()?;
-  let embeddings = client.embeddings();
-
-  let response = request_embedding_with_retry(|| embeddings.create(request.clone())).await?;
-
-  // Sort by index to ensure ordering matches input
-  let mut data = response.data;
-  data.sort_by_key(|e| e.index);
-
-  if data.len() != inputs.len() {
-    return Err(
-      anyhow!(
-        "embedding count mismatch: expected {}, got {}",
-        inputs.len(),
-        data.len()
-      )
-      .into(),
-    );
-  }
-
-  data
+  let response = ai.embedding().embed(inputs).await?;
+  record_embedding_usage(response.usage, ai).await;
+  response
+    .embeddings
     .into_iter()
-    .map(|e| process_embedding(e.embedding).map(PgVector::from))
+    .map(|embedding| process_embedding(embedding, ai.embedding_dim()).map(PgVector::from))
     .collect::<Result<Vec<_>, _>>()
 }
@@ -0,0 +1,338 @@
+use std::{
+  mem,
+  sync::{Arc, Mutex, PoisonError},
+  time::Duration,
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use plastmem_shared::AppError;
+use tokio::{sync::oneshot, time::sleep};
+
+use crate::{CircuitOpen, EmbeddingProvider, EmbeddingResponse, RetryableError, TokenUsage};
+
+type EmbeddingReply = oneshot::Sender<Result<EmbeddingResponse, AppError>>;
+
+/// Coalesces concurrent embedding calls into one backend request.
+///
+/// The first call to arrive opens a batch that is sent after `window`; calls
+/// arriving meanwhile join it. A batch is sent early once it holds `max_inputs`
+/// inputs, and calls that large on their own bypass batching. Each caller gets
+/// back exactly the vectors for its own inputs, in order, and a share of the
+/// batch's token usage proportional to the length of its inputs.
+pub struct EmbeddingBatcher {
+  inner: Arc<dyn EmbeddingProvider>,
+  window: Duration,
+  max_inputs: usize,
+  pending: Arc<Mutex<PendingBatch>>,
+}
+
+#[derive(Default)]
+struct PendingBatch {
+  /// Bumped whenever a batch is taken, so a stale timer leaves the next batch alone.
+  generation: u64,
+  inputs: Vec<String>,
+  /// Callers in input order.
+  callers: Vec<PendingCaller>,
+}
+
+struct PendingCaller {
+  inputs: usize,
+  /// Total input length, used to split the batch's token usage.
+  chars: usize,
+  reply: EmbeddingReply,
+}
+
+struct Batch {
+  inputs: Vec<String>,
+  callers: Vec<PendingCaller>,
+}
+
+impl PendingBatch {
+  fn take(&mut self) -> Batch {
+    self.generation += 1;
+    Batch {
+      inputs: mem::take(&mut self.inputs),
+      callers: mem::take(&mut self.callers),
+    }
+  }
+}
+
+impl EmbeddingBatcher {
+  #[must_use]
+  pub fn new(inner: Arc<dyn EmbeddingProvider>, window: Duration, max_inputs: usize) -> Self {
+    Self {
+      inner,
+      window,
+      max_inputs: max_inputs.max(1),
+      pending: Arc::default(),
+    }
+  }
+
+  fn spawn_send(&self, batch: Batch) {
+    tokio::spawn(send_batch(self.inner.clone(), batch));
+  }
+
+  fn spawn_timer(&self, generation: u64) {
+    let inner = self.inner.clone();
+    let pending = self.pending.clone();
+    let window = self.window;
+    tokio::spawn(async move {
+      sleep(window).await;
+      let batch = {
+        let mut pending = pending.lock().unwrap_or_else(PoisonError::into_inner);
+        (pending.generation == generation && !pending.callers.is_empty()).then(|| pending.take())
+      };
+      if let Some(batch) = batch {
+        send_batch(inner, batch).await;
+      }
+    });
+  }
+}
+
+async fn send_batch(inner: Arc<dyn EmbeddingProvider>, batch: Batch) {
+  let total = batch.inputs.len();
+  tracing::debug!(
+    inputs = total,
+    callers = batch.callers.len(),
+    "sending embedding batch"
+  );
+
+  let result = inner.embed(batch.inputs).await.and_then(|response| {
+    if response.embeddings.len() == total {
+      Ok(response)
+    } else {
+      Err(AppError::new(anyhow!(
+        "embedding backend returned {} vectors for {total} inputs",
+        response.embeddings.len()
+      )))
+    }
+  });
+
+  // A dropped receiver means that caller was cancelled; its vectors are discarded
+  let mut callers = batch.callers;
+  match result {
+    Ok(response) => {
+      let weights: Vec<usize> = callers.iter().map(|caller| caller.chars).collect();
+      let usages = split_usage(response.usage, &weights);
+      let mut embeddings = response.embeddings.into_iter();
+      for (caller, usage) in callers.into_iter().zip(usages) {
+        let _ = caller.reply.send(Ok(EmbeddingResponse {
+          embeddings: embeddings.by_ref().take(caller.inputs).collect(),
+          usage,
+        }));
+      }
+    }
+    Err(err) if callers.len() == 1 => {
+      if let Some(caller) = callers.pop() {
+        let _ = caller.reply.send(Err(err));
+      }
+    }
+    Err(err) => {
+      for caller in callers {
+        let _ = caller.reply.send(Err(share_error(&err)));
+      }
+    }
+  }
+}
+
+/// Copy of a failed batch's error for one of its callers.
+///
+/// Keeps the status code and the [`CircuitOpen`] and [`RetryableError`] types, so
+/// callers still see a failure as the breaker or the worker's retry logic would.
+fn share_error(err: &AppError) -> AppError {
+  let status = err.status_code();
+  if let Some(open) = err.downcast_ref::<CircuitOpen>() {
+    return AppError::with_status(status, open.clone());
+  }
+  let shared = anyhow!("batched embedding request failed: {err}");
+  if err.downcast_ref::<RetryableError>().is_some() {
+    AppError::with_status(status, RetryableError(shared))
+  } else {
+    AppError::with_status(status, shared)
+  }
+}
+
+/// Split `usage` across callers in proportion to `weights`; rounding leftovers go to the last one.
+fn split_usage(usage: Option<TokenUsage>, weights: &[usize]) -> Vec<Option<TokenUsage>> {
+  let Some(usage) = usage else {
+    return vec![None; weights.len()];
+  };
+  let total = weights.iter().sum::<usize>().max(1) as u64;
+  let mut remaining = usage.prompt_tokens;
+  weights
+    .iter()
+    .enumerate()
+    .map(|(i, &weight)| {
+      let prompt_tokens = if i + 1 == weights.len() {
+        remaining
+      } else {
+        u32::try_from(u64::from(usage.prompt_tokens) * weight as u64 / total)
+          .unwrap_or(u32::MAX)
+          .min(remaining)
+      };
+      remaining -= prompt_tokens;
+      Some(TokenUsage {
+        prompt_tokens,
+        completion_tokens: 0,
+      })
+    })
+    .collect()
+}
+
+#[async_trait]
+impl EmbeddingProvider for EmbeddingBatcher {
+  fn model(&self) -> &str {
+    self.inner.model()
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    if inputs.is_empty() || inputs.len() >= self.max_inputs {
+      return self.inner.embed(inputs).await;
+    }
+
+    let (reply, receiver) = oneshot::channel();
+    {
+      let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+      if pending.inputs.len() + inputs.len() > self.max_inputs {
+        self.spawn_send(pending.take());
+      }
+      if pending.callers.is_empty() {
+        self.spawn_timer(pending.generation);
+      }
+      pending.callers.push(PendingCaller {
+        inputs: inputs.len(),
+        chars: inputs.iter().map(String::len).sum(),
+        reply,
+      });
+      pending.inputs.extend(inputs);
+      if pending.inputs.len() >= self.max_inputs {
+        self.spawn_send(pending.take());
+      }
+    }
+
+    receiver
+      .await
+      .map_err(|_| AppError::new(anyhow!("embedding batch was dropped before completing")))?
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    CircuitBreaker, CircuitBreakerConfig, circuit_breaker::BreakerEmbeddingProvider,
+    is_circuit_open,
+  };
+
+  /// Embeds each input as `[len]`, bills one token per byte and records the size of every request.
+  #[derive(Default)]
+  struct RecordingEmbedding {
+    requests: Mutex<Vec<usize>>,
+  }
+
+  #[async_trait]
+  impl EmbeddingProvider for RecordingEmbedding {
+    fn model(&self) -> &'static str {
+      "recording"
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+      self.requests.lock().unwrap().push(inputs.len());
+      #[allow(clippy::cast_precision_loss)]
+      let embeddings = inputs.iter().map(|i| vec![i.len() as f32]).collect();
+      let prompt_tokens = u32::try_from(inputs.iter().map(String::len).sum::<usize>()).unwrap();
+      Ok(EmbeddingResponse {
+        embeddings,
+        usage: Some(TokenUsage {
+          prompt_tokens,
+          completion_tokens: 0,
+        }),
+      })
+    }
+  }
+
+  fn texts(texts: &[&str]) -> Vec<String> {
+    texts.iter().map(|&t| t.to_owned()).collect()
+  }
+
+  #[tokio::test]
+  async fn coalesces_concurrent_calls_and_routes_results() {
+    let recording = Arc::new(RecordingEmbedding::default());
+    let batcher = EmbeddingBatcher::new(recording.clone(), Duration::from_millis(20), 64);
+
+    let (a, b, c) = tokio::join!(
+      batcher.embed(texts(&["a"])),
+      batcher.embed(texts(&["bb", "ccc"])),
+      batcher.embed(texts(&["dddd"])),
+    );
+
+    let (a, b, c) = (a.unwrap(), b.unwrap(), c.unwrap());
+    assert_eq!(a.embeddings, vec![vec![1.0]]);
+    assert_eq!(b.embeddings, vec![vec![2.0], vec![3.0]]);
+    assert_eq!(c.embeddings, vec![vec![4.0]]);
+    let prompt_tokens = |r: &EmbeddingResponse| r.usage.map(|u| u.prompt_tokens);
+    assert_eq!(
+      [prompt_tokens(&a), prompt_tokens(&b), prompt_tokens(&c)],
+      [Some(1), Some(5), Some(4)]
+    );
+    assert_eq!(*recording.requests.lock().unwrap(), vec![4]);
+  }
+
+  #[tokio::test]
+  async fn full_batches_are_sent_without_waiting() {
+    let recording = Arc::new(RecordingEmbedding::default());
+    let batcher = EmbeddingBatcher::new(recording.clone(), Duration::from_mins(1), 2);
+
+    let (a, b) = tokio::join!(batcher.embed(texts(&["a"])), batcher.embed(texts(&["bb"])));
+    assert_eq!(a.unwrap().embeddings, vec![vec![1.0]]);
+    assert_eq!(b.unwrap().embeddings, vec![vec![2.0]]);
+
+    let large = batcher.embed(texts(&["a", "bb", "ccc"])).await.unwrap();
+    assert_eq!(large.embeddings.len(), 3);
+    assert_eq!(*recording.requests.lock().unwrap(), vec![2, 3]);
+  }
+
+  /// Fails every request as unreachable, counting them.
+  #[derive(Default)]
+  struct DownEmbedding {
+    requests: Mutex<usize>,
+  }
+
+  #[async_trait]
+  impl EmbeddingProvider for DownEmbedding {
+    fn model(&self) -> &'static str {
+      "down"
+    }
+
+    async fn embed(&self, _inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+      *self.requests.lock().unwrap() += 1;
+      Err(AppError::new(RetryableError(anyhow!("connection refused"))))
+    }
+  }
+
+  #[tokio::test]
+  async fn every_caller_of_a_batch_sees_an_open_breaker() {
+    let down = Arc::new(DownEmbedding::default());
+    let breaker = Arc::new(CircuitBreaker::new(
+      "embedding",
+      CircuitBreakerConfig {
+        failure_threshold: 1,
+        cooldown: Duration::from_mins(1),
+      },
+    ));
+    let guarded = Arc::new(BreakerEmbeddingProvider {
+      inner: down.clone(),
+      breaker,
+    });
+    // The first failure opens the breaker
+    assert!(guarded.embed(texts(&["a"])).await.is_err());
+
+    let batcher = EmbeddingBatcher::new(guarded, Duration::from_millis(20), 64);
+    let (a, b) = tokio::join!(batcher.embed(texts(&["a"])), batcher.embed(texts(&["bb"])));
+
+    assert!(is_circuit_open(&a.unwrap_err()));
+    assert!(is_circuit_open(&b.unwrap_err()));
+    assert_eq!(*down.requests.lock().unwrap(), 1);
+  }
+}
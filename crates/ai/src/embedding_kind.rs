@@ -0,0 +1,47 @@
+/// Role of a text being embedded.
+///
+/// Instruction-tuned embedding models (e.g. Qwen3-Embedding) expect an
+/// instruction prefix on search queries but not on the documents they are
+/// matched against. `AiClient` holds one template per kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmbeddingKind {
+  /// A retrieval query, compared against stored documents.
+  Query,
+  /// Stored content: episode text or semantic facts.
+  Document,
+}
+
+/// Placeholder replaced by the input text in embedding instruction templates.
+const TEXT_PLACEHOLDER: &str = "{text}";
+
+/// Apply an instruction template to `text`.
+///
+/// `{text}` in the template is replaced by the input; a template without the
+/// placeholder is used as a prefix.
+pub fn render_embedding_template(template: Option<&str>, text: &str) -> String {
+  match template {
+    Some(template) if template.contains(TEXT_PLACEHOLDER) => {
+      template.replace(TEXT_PLACEHOLDER, text)
+    }
+    Some(template) => format!("{template}{text}"),
+    None => text.to_owned(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn render_embedding_template_substitutes_or_prefixes() {
+    assert_eq!(render_embedding_template(None, "hi"), "hi");
+    assert_eq!(
+      render_embedding_template(Some("Instruct: find memories\nQuery: {text}"), "hi"),
+      "Instruct: find memories\nQuery: hi"
+    );
+    assert_eq!(
+      render_embedding_template(Some("query: "), "hi"),
+      "query: hi"
+    );
+  }
+}
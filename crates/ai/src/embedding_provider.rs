@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use plastmem_shared::AppError;
+
+use crate::TokenUsage;
+
+/// Raw vectors returned by an [`EmbeddingProvider`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmbeddingResponse {
+  /// One vector per input, in input order.
+  pub embeddings: Vec<Vec<f32>>,
+  /// Tokens billed for the request, when the backend reports them.
+  pub usage: Option<TokenUsage>,
+}
+
+impl EmbeddingResponse {
+  /// Vectors without usage information.
+  #[must_use]
+  pub const fn new(embeddings: Vec<Vec<f32>>) -> Self {
+    Self {
+      embeddings,
+      usage: None,
+    }
+  }
+}
+
+/// An embedding backend.
+///
+/// Returns one raw vector per input, in input order. Normalization and
+/// truncation to the configured dimension are applied by `embed` / `embed_many`.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+  /// Model identifier the vectors are produced by.
+  fn model(&self) -> &str;
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError>;
+}
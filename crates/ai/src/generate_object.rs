@@ -1,48 +1,16 @@
 use anyhow::anyhow;
-use async_openai::{
-  Client,
-  config::OpenAIConfig,
-  types::chat::{
-    ChatCompletionRequestMessage, CreateChatCompletionRequestArgs, ReasoningEffort, ResponseFormat,
-    ResponseFormatJsonSchema,
-  },
-};
-use plastmem_shared::{APP_ENV, AppError};
+use async_openai::types::chat::ChatCompletionRequestMessage;
+use plastmem_shared::AppError;
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
 
-use crate::embed_shared::request_chat_completion_with_retry;
+use crate::{
+  AiClient, ChatRequest, ChatTask, ResponseSchema, call_log::PendingChatCall, extract_json,
+};
+
+/// How many times an invalid reply is sent back to the model with its validation error.
+const VALIDATION_RETRIES: usize = 2;
 
-/// Generates a structured object
-///
-/// # Type Parameters
-///
-/// * `T` - The output type that implements `DeserializeOwned` and `JsonSchema`
-///
-/// # Arguments
-///
-/// * `messages` - The chat completion messages
-/// * `schema_name` - A name for the schema
-/// * `schema_description` - A description for the schema
-///
-/// # Example
-///
-/// ```rust
-/// use schemars::JsonSchema;
-/// use serde::Deserialize;
-///
-/// #[derive(Deserialize, JsonSchema)]
-/// struct SurpriseScore {
-///     score: f32,
-///     reason: String,
-/// }
-///
-/// let result = generate_object::<SurpriseScore>(
-///     messages,
-///     "surprise_score".to_owned(),
-///     None,
-/// ).await?;
-/// ```
 /// Recursively fix a JSON schema for `OpenAI` strict mode:
 /// - additionalProperties: false on all objects
 /// - required must include all property keys
@@ -131,55 +99,128 @@ fn fix_schema_for_strict(schema: &mut serde_json::Value) {
   }
 }
 
+/// Generates a structured object
+///
+/// # Type Parameters
+///
+/// * `T` - The output type that implements `DeserializeOwned` and `JsonSchema`
+///
+/// # Arguments
+///
+/// * `task` - The pipeline stage, used to pick the backend
+/// * `messages` - The chat completion messages
+/// * `schema_name` - A name for the schema
+/// * `schema_description` - A description for the schema
+/// * `ai` - The backend to send the request to
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// struct SurpriseScore {
+///     score: f32,
+///     reason: String,
+/// }
+///
+/// let result = generate_object::<SurpriseScore>(
+///     ChatTask::Consolidation,
+///     messages,
+///     "surprise_score".to_owned(),
+///     None,
+///     &ai,
+/// ).await?;
+/// ```
 pub async fn generate_object<T>(
+  task: ChatTask,
   messages: Vec<ChatCompletionRequestMessage>,
   schema_name: String,
   schema_description: Option<String>,
+  ai: &AiClient,
 ) -> Result<T, AppError>
 where
   T: DeserializeOwned + JsonSchema,
 {
-  let config = OpenAIConfig::new()
-    .with_api_key(&APP_ENV.openai_api_key)
-    .with_api_base(&APP_ENV.openai_base_url);
-
-  let client = Client::with_config(config);
-
   // Generate JSON schema from type
   let schema = schemars::schema_for!(T);
   let mut schema = serde_json::to_value(&schema)?;
   // OpenAI strict mode requires additionalProperties: false and all properties in required
   fix_schema_for_strict(&mut schema);
 
-  #[allow(deprecated)]
-  let mut request_builder = CreateChatCompletionRequestArgs::default();
-  request_builder
-    .model(&APP_ENV.openai_chat_model)
-    .messages(messages)
-    .reasoning_effort(ReasoningEffort::None)
-    .response_format(ResponseFormat::JsonSchema {
-      json_schema: ResponseFormatJsonSchema {
-        description: schema_description,
-        name: schema_name,
-        schema: Some(schema),
-        strict: Some(true),
-      },
-    });
-
-  if let Some(seed) = APP_ENV.openai_chat_seed {
-    request_builder.seed(seed);
-  }
-
-  let request = request_builder.build()?;
+  let response_schema = ResponseSchema {
+    name: schema_name,
+    description: schema_description,
+    schema,
+  };
+  let mut messages = messages;
+  let mut attempt = 0;
+
+  loop {
+    let request = ChatRequest {
+      task,
+      messages: messages.clone(),
+      response_schema: Some(response_schema.clone()),
+      reasoning_effort: ai.reasoning_effort().cloned(),
+    };
+    let call = PendingChatCall::start(&request, ai);
+    let response = match ai.chat_for(task).chat(request).await {
+      Ok(response) => response,
+      Err(err) => {
+        call.failed(&err).await;
+        return Err(err);
+      }
+    };
 
-  let chat = client.chat();
-  let response = request_chat_completion_with_retry(|| chat.create(request.clone()))
-    .await
-    .map(|r| r.choices.into_iter())?
-    .find_map(|c| c.message.content)
-    .ok_or_else(|| anyhow!("empty message content"))?;
+    if ai.log_reasoning()
+      && let Some(reasoning) = &response.reasoning
+    {
+      tracing::info!(
+        target: "plastmem_ai::reasoning",
+        task = task.name(),
+        schema = response_schema.name,
+        attempt = attempt + 1,
+        reasoning
+      );
+    }
 
-  let result: T = serde_json::from_str(&response)?;
+    let parsed = serde_json::from_str::<T>(&extract_json(&response.content));
+    call
+      .replied(&response, parsed.as_ref().err().map(ToString::to_string))
+      .await;
+    let err = match parsed {
+      Ok(result) => return Ok(result),
+      Err(err) => err,
+    };
+
+    if attempt >= VALIDATION_RETRIES {
+      return Err(
+        anyhow!(
+          "`{}` reply failed validation after {} attempts: {err}",
+          response_schema.name,
+          attempt + 1
+        )
+        .into(),
+      );
+    }
 
-  Ok(result)
+    tracing::warn!(
+      schema = response_schema.name,
+      attempt = attempt + 1,
+      error = %err,
+      "structured output failed validation, retrying with error feedback"
+    );
+    messages.push(ChatCompletionRequestMessage::Assistant(
+      response.content.into(),
+    ));
+    messages.push(ChatCompletionRequestMessage::User(
+      format!(
+        "Your previous reply failed validation: {err}\nReply again with only the corrected JSON for `{}`.",
+        response_schema.name
+      )
+      .into(),
+    ));
+    attempt += 1;
+  }
 }
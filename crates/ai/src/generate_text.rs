@@ -1,41 +1,34 @@
-use anyhow::anyhow;
-use async_openai::{
-  Client,
-  config::OpenAIConfig,
-  types::chat::{ChatCompletionRequestMessage, CreateChatCompletionRequestArgs, ReasoningEffort},
-};
-use plastmem_shared::{APP_ENV, AppError};
+use async_openai::types::chat::ChatCompletionRequestMessage;
+use plastmem_shared::AppError;
 
-use crate::embed_shared::request_chat_completion_with_retry;
+use crate::{AiClient, ChatRequest, ChatTask, call_log::PendingChatCall};
 
 pub async fn generate_text(
+  task: ChatTask,
   messages: Vec<ChatCompletionRequestMessage>,
+  ai: &AiClient,
 ) -> Result<String, AppError> {
-  let config = OpenAIConfig::new()
-    .with_api_key(&APP_ENV.openai_api_key)
-    .with_api_base(&APP_ENV.openai_base_url);
-
-  let client = Client::with_config(config);
-
-  #[allow(deprecated)]
-  let mut request_builder = CreateChatCompletionRequestArgs::default();
-  request_builder
-    .model(&APP_ENV.openai_chat_model)
-    .messages(messages)
-    .reasoning_effort(ReasoningEffort::None);
-
-  if let Some(seed) = APP_ENV.openai_chat_seed {
-    request_builder.seed(seed);
+  let request = ChatRequest {
+    task,
+    messages,
+    response_schema: None,
+    reasoning_effort: ai.reasoning_effort().cloned(),
+  };
+  let call = PendingChatCall::start(&request, ai);
+  let response = match ai.chat_for(task).chat(request).await {
+    Ok(response) => response,
+    Err(err) => {
+      call.failed(&err).await;
+      return Err(err);
+    }
+  };
+  call.replied(&response, None).await;
+
+  if ai.log_reasoning()
+    && let Some(reasoning) = &response.reasoning
+  {
+    tracing::info!(target: "plastmem_ai::reasoning", task = task.name(), reasoning);
   }
 
-  let request = request_builder.build()?;
-
-  let chat = client.chat();
-
-  request_chat_completion_with_retry(|| chat.create(request.clone()))
-    .await
-    .map(|r| r.choices.into_iter())?
-    .filter_map(|c| c.message.content)
-    .next_back()
-    .ok_or_else(|| anyhow!("empty message content").into())
+  Ok(response.content)
 }
@@ -1,13 +1,82 @@
 // Re-export async_openai types for consumers
 pub use async_openai::types::chat::{
   ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
-  ChatCompletionRequestUserMessage,
+  ChatCompletionRequestUserMessage, ReasoningEffort,
 };
 
+mod chat_task;
+pub use chat_task::ChatTask;
+
+mod chat_provider;
+pub use chat_provider::{ChatProvider, ChatRequest, ChatResponse, ResponseSchema, TokenUsage};
+
+mod call_log;
+pub use call_log::{CallContext, ChatCallRecord, ChatCallSink};
+
+mod usage;
+pub use usage::{EMBEDDING_STAGE, UsageRecord, UsageSink};
+
+mod prompts;
+pub use prompts::{BuiltinPrompt, Prompt, PromptRegistry, PromptTemplate, PromptVersions};
+
+mod reasoning;
+pub use reasoning::split_reasoning;
+
+mod structured_output;
+pub use structured_output::{StructuredOutputMode, extract_json};
+
+mod token_budget;
+#[cfg(feature = "tokenizer")]
+pub use token_budget::TokenizerCounter;
+pub use token_budget::{DEFAULT_CONTEXT_TOKENS, EstimatedTokenCounter, TokenBudget, TokenCounter};
+
+mod embedding_kind;
+pub use embedding_kind::EmbeddingKind;
+
+mod embedding_provider;
+pub use embedding_provider::{EmbeddingProvider, EmbeddingResponse};
+
+mod embedding_batcher;
+pub use embedding_batcher::EmbeddingBatcher;
+
+mod circuit_breaker;
+pub use circuit_breaker::{
+  BreakerState, BreakerStatus, CircuitBreaker, CircuitBreakerConfig, CircuitOpen, is_circuit_open,
+};
+
+mod transport;
+pub use transport::{HttpTransport, RequestLimits, RetryableError};
+
+#[cfg(feature = "local-embedding")]
+mod local_embedding;
+#[cfg(feature = "local-embedding")]
+pub use local_embedding::{LocalEmbeddingProvider, Pooling};
+
+mod plain_message;
+
+mod openai;
+pub use openai::OpenAiProvider;
+
+mod ollama;
+pub use ollama::OllamaProvider;
+
+mod messages;
+pub use messages::MessagesProvider;
+
+mod replay;
+pub use replay::ReplayProvider;
+
+mod scripted;
+pub use scripted::ScriptedChatProvider;
+
+mod client;
+pub use client::AiClient;
+
 mod cosine_similarity;
 pub use cosine_similarity::cosine_similarity;
 
 mod embed_shared;
+pub use embed_shared::DEFAULT_EMBEDDING_DIM;
 
 mod embed;
 pub use embed::embed;
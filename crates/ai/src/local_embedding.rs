@@ -0,0 +1,185 @@
+use std::{fs, path::Path, sync::Arc};
+
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use candle_core::{DType, Device, IndexOp, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use plastmem_shared::AppError;
+use tokenizers::{Encoding, PaddingParams, Tokenizer, TruncationParams};
+
+use crate::{EmbeddingProvider, EmbeddingResponse, TokenUsage};
+
+/// How token states are reduced to one vector per input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pooling {
+  /// Average of the non-padding tokens (E5, GTE, MiniLM sentence-transformers).
+  Mean,
+  /// The `[CLS]` token (BGE).
+  Cls,
+}
+
+impl Pooling {
+  #[must_use]
+  pub fn parse(value: &str) -> Option<Self> {
+    match value.trim().to_ascii_lowercase().as_str() {
+      "mean" => Some(Self::Mean),
+      "cls" => Some(Self::Cls),
+      _ => None,
+    }
+  }
+}
+
+struct LocalModel {
+  model: BertModel,
+  tokenizer: Tokenizer,
+  pooling: Pooling,
+  device: Device,
+}
+
+/// In-process CPU embedding backend for BERT-architecture models.
+///
+/// Loads `config.json`, `tokenizer.json` and `model.safetensors` from a local
+/// model directory (e.g. a Hugging Face snapshot of `bge-base-en-v1.5` or
+/// `all-MiniLM-L6-v2`); XLM-RoBERTa models are not supported. Returns raw
+/// pooled vectors; normalization and truncation to the configured dimension
+/// happen in `embed` / `embed_many` as for every other backend. Inference runs
+/// on the blocking thread pool.
+#[derive(Clone)]
+pub struct LocalEmbeddingProvider {
+  model_name: String,
+  inner: Arc<LocalModel>,
+}
+
+impl LocalEmbeddingProvider {
+  /// Load the model in `dir`, truncating inputs to `max_tokens` tokens.
+  ///
+  /// The model is named after the directory, which is what gets recorded as
+  /// the `embedding_model` of stored rows.
+  pub fn load(
+    dir: impl AsRef<Path>,
+    pooling: Pooling,
+    max_tokens: usize,
+  ) -> Result<Self, AppError> {
+    let dir = dir.as_ref();
+    let config: Config = serde_json::from_str(
+      &fs::read_to_string(dir.join("config.json"))
+        .with_context(|| format!("failed to read {}", dir.join("config.json").display()))?,
+    )
+    .context("invalid BERT config.json")?;
+
+    let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+      .map_err(|err| anyhow!("failed to load tokenizer from {}: {err}", dir.display()))?;
+    tokenizer.with_padding(Some(PaddingParams::default()));
+    tokenizer
+      .with_truncation(Some(TruncationParams {
+        max_length: max_tokens,
+        ..TruncationParams::default()
+      }))
+      .map_err(|err| anyhow!("invalid tokenizer truncation: {err}"))?;
+
+    let device = Device::Cpu;
+    let weights = fs::read(dir.join("model.safetensors"))
+      .with_context(|| format!("failed to read model weights from {}", dir.display()))?;
+    let vb = VarBuilder::from_buffered_safetensors(weights, DType::F32, &device)?;
+    let model = BertModel::load(vb, &config)?;
+
+    let model_name = dir
+      .file_name()
+      .and_then(|name| name.to_str())
+      .unwrap_or("local")
+      .to_owned();
+    tracing::info!(model = %model_name, ?pooling, "loaded local embedding model");
+
+    Ok(Self {
+      model_name,
+      inner: Arc::new(LocalModel {
+        model,
+        tokenizer,
+        pooling,
+        device,
+      }),
+    })
+  }
+}
+
+impl LocalModel {
+  fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    let encodings = self
+      .tokenizer
+      .encode_batch(inputs, true)
+      .map_err(|err| anyhow!("tokenization failed: {err}"))?;
+
+    let stack = |field: fn(&Encoding) -> &[u32]| {
+      let rows = encodings
+        .iter()
+        .map(|encoding| Tensor::new(field(encoding), &self.device))
+        .collect::<Result<Vec<_>, _>>()?;
+      Tensor::stack(&rows, 0)
+    };
+    let input_ids = stack(Encoding::get_ids)?;
+    let token_type_ids = stack(Encoding::get_type_ids)?;
+    let attention_mask = stack(Encoding::get_attention_mask)?;
+
+    let hidden = self
+      .model
+      .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+    let embeddings = pool(&hidden, &attention_mask, self.pooling)?.to_vec2::<f32>()?;
+
+    let tokens = encodings
+      .iter()
+      .map(|encoding| encoding.get_attention_mask().iter().sum::<u32>())
+      .sum();
+    Ok(EmbeddingResponse {
+      embeddings,
+      usage: Some(TokenUsage {
+        prompt_tokens: tokens,
+        completion_tokens: 0,
+      }),
+    })
+  }
+}
+
+/// Reduce `(batch, tokens, hidden)` states to `(batch, hidden)`.
+fn pool(hidden: &Tensor, attention_mask: &Tensor, pooling: Pooling) -> candle_core::Result<Tensor> {
+  match pooling {
+    Pooling::Cls => hidden.i((.., 0)),
+    Pooling::Mean => {
+      let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+      let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+      // Never zero: every encoding keeps at least its special tokens
+      let counts = mask.sum(1)?;
+      summed.broadcast_div(&counts)
+    }
+  }
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbeddingProvider {
+  fn model(&self) -> &str {
+    &self.model_name
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    let inner = self.inner.clone();
+    tokio::task::spawn_blocking(move || inner.embed(inputs))
+      .await
+      .context("local embedding task panicked")?
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mean_pooling_skips_padding() {
+    let hidden = Tensor::new(&[[[1.0f32, 2.0], [3.0, 4.0], [100.0, 100.0]]], &Device::Cpu).unwrap();
+    let mask = Tensor::new(&[[1u32, 1, 0]], &Device::Cpu).unwrap();
+
+    let mean = pool(&hidden, &mask, Pooling::Mean).unwrap();
+    assert_eq!(mean.to_vec2::<f32>().unwrap(), vec![vec![2.0, 3.0]]);
+    let cls = pool(&hidden, &mask, Pooling::Cls).unwrap();
+    assert_eq!(cls.to_vec2::<f32>().unwrap(), vec![vec![1.0, 2.0]]);
+  }
+}
@@ -0,0 +1,374 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_openai::types::chat::ReasoningEffort;
+use async_trait::async_trait;
+use plastmem_shared::{AppError, ChatModelEnv};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+  ChatProvider, ChatRequest, ChatResponse, HttpTransport, StructuredOutputMode, TokenUsage,
+  plain_message::{PlainMessage, PlainRole},
+  reasoning::{merge_reasoning, parse_reasoning_effort, split_reasoning},
+  structured_output::insert_schema_instruction,
+  transport::header_value,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_mins(1);
+const API_VERSION: &str = "2023-06-01";
+/// Output tokens allowed for the answer, on top of any thinking budget.
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// Chat backend for Messages-style APIs (`POST {base_url}/messages`), as served
+/// by Anthropic and compatible gateways; the base URL includes the version
+/// path, e.g. `https://api.anthropic.com/v1`.
+///
+/// System messages become the top-level `system` prompt. Response schemas are
+/// enforced through a forced call of one tool whose input schema is the
+/// response schema. Reasoning efforts other than `none` enable extended
+/// thinking with a token budget per level; since thinking cannot be combined
+/// with a forced tool call, the tool is then offered and the schema is also
+/// described in the prompt. Seeds are not supported and are ignored.
+#[derive(Clone)]
+pub struct MessagesProvider {
+  transport: HttpTransport,
+  base_url: String,
+  api_key: String,
+  model: String,
+  reasoning_effort: ReasoningEffort,
+  structured_output: StructuredOutputMode,
+  timeout: Duration,
+  max_tokens: u32,
+}
+
+impl MessagesProvider {
+  #[must_use]
+  pub fn new(base_url: &str, api_key: &str, model: String) -> Self {
+    Self {
+      transport: HttpTransport::default(),
+      base_url: base_url.trim_end_matches('/').to_owned(),
+      api_key: api_key.to_owned(),
+      model,
+      reasoning_effort: ReasoningEffort::None,
+      structured_output: StructuredOutputMode::StrictSchema,
+      timeout: DEFAULT_TIMEOUT,
+      max_tokens: DEFAULT_MAX_TOKENS,
+    }
+  }
+
+  /// Send requests through `transport`, sharing its connection pool and limits.
+  #[must_use]
+  pub fn with_transport(mut self, transport: HttpTransport) -> Self {
+    self.transport = transport;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_reasoning_effort(mut self, reasoning_effort: ReasoningEffort) -> Self {
+    self.reasoning_effort = reasoning_effort;
+    self
+  }
+
+  /// `strict_schema` and `tool_call` both use a forced tool call.
+  #[must_use]
+  pub const fn with_structured_output(mut self, structured_output: StructuredOutputMode) -> Self {
+    self.structured_output = structured_output;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Output tokens allowed for the answer, on top of any thinking budget.
+  #[must_use]
+  pub const fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+    self.max_tokens = max_tokens;
+    self
+  }
+
+  /// Build the provider from one chat backend's settings.
+  #[must_use]
+  pub fn from_model_env(env: &ChatModelEnv) -> Self {
+    Self::new(&env.base_url, &env.api_key, env.model.clone())
+      .with_reasoning_effort(parse_reasoning_effort(&env.reasoning_effort))
+      .with_structured_output(StructuredOutputMode::parse_or_default(
+        &env.structured_output,
+      ))
+      .with_timeout(Duration::from_secs(env.timeout_seconds))
+  }
+
+  fn headers(&self) -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-api-key", header_value(&self.api_key)?);
+    headers.insert("anthropic-version", HeaderValue::from_static(API_VERSION));
+    Ok(headers)
+  }
+}
+
+/// Thinking tokens granted per reasoning effort; `None` disables thinking.
+const fn thinking_budget(effort: &ReasoningEffort) -> Option<u32> {
+  match effort {
+    ReasoningEffort::None => None,
+    // 1024 is the smallest budget the API accepts
+    ReasoningEffort::Minimal | ReasoningEffort::Low => Some(1024),
+    ReasoningEffort::Medium => Some(4096),
+    ReasoningEffort::High => Some(16_384),
+    ReasoningEffort::Xhigh => Some(32_768),
+  }
+}
+
+#[derive(Serialize)]
+struct MessagesTurn {
+  role: &'static str,
+  content: String,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+  model: &'a str,
+  max_tokens: u32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  system: Option<String>,
+  messages: Vec<MessagesTurn>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  thinking: Option<serde_json::Value>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  tools: Vec<serde_json::Value>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  tool_choice: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct MessagesBody {
+  content: Vec<ContentBlock>,
+  usage: Option<MessagesUsage>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+  Text {
+    text: String,
+  },
+  Thinking {
+    thinking: String,
+  },
+  ToolUse {
+    input: serde_json::Value,
+  },
+  #[serde(other)]
+  Other,
+}
+
+#[derive(Deserialize)]
+struct MessagesUsage {
+  input_tokens: u32,
+  output_tokens: u32,
+}
+
+/// Split messages into the `system` prompt and alternating user / assistant turns.
+///
+/// Consecutive messages with the same role are merged, as the API requires turns to alternate.
+fn split_system(messages: &[PlainMessage]) -> (Option<String>, Vec<MessagesTurn>) {
+  let mut system = Vec::new();
+  let mut turns: Vec<MessagesTurn> = Vec::new();
+  for message in messages {
+    let role = match message.role {
+      PlainRole::System => {
+        system.push(message.content.as_str());
+        continue;
+      }
+      PlainRole::User => "user",
+      PlainRole::Assistant => "assistant",
+    };
+    match turns.last_mut() {
+      Some(last) if last.role == role => {
+        last.content.push_str("\n\n");
+        last.content.push_str(&message.content);
+      }
+      _ => turns.push(MessagesTurn {
+        role,
+        content: message.content.clone(),
+      }),
+    }
+  }
+  ((!system.is_empty()).then(|| system.join("\n\n")), turns)
+}
+
+#[async_trait]
+impl ChatProvider for MessagesProvider {
+  fn model(&self) -> &str {
+    &self.model
+  }
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError> {
+    let reasoning_effort = request
+      .reasoning_effort
+      .unwrap_or_else(|| self.reasoning_effort.clone());
+    let thinking_budget = thinking_budget(&reasoning_effort);
+    let uses_tool = matches!(
+      self.structured_output,
+      StructuredOutputMode::StrictSchema | StructuredOutputMode::ToolCall
+    );
+
+    let mut messages = request.messages;
+    if let Some(response_schema) = &request.response_schema
+      && (self.structured_output.needs_prompt_schema() || (uses_tool && thinking_budget.is_some()))
+    {
+      insert_schema_instruction(&mut messages, response_schema);
+    }
+    let messages = messages
+      .iter()
+      .map(PlainMessage::from_request)
+      .collect::<Vec<_>>();
+    let (system, turns) = split_system(&messages);
+    if turns.is_empty() {
+      return Err(anyhow!("Messages API requests need at least one user message").into());
+    }
+
+    let mut body = MessagesRequest {
+      model: &self.model,
+      max_tokens: self.max_tokens + thinking_budget.unwrap_or_default(),
+      system,
+      messages: turns,
+      thinking: thinking_budget.map(|budget| json!({ "type": "enabled", "budget_tokens": budget })),
+      tools: Vec::new(),
+      tool_choice: None,
+    };
+    if let Some(response_schema) = request.response_schema
+      && uses_tool
+    {
+      body.tool_choice = Some(if thinking_budget.is_some() {
+        json!({ "type": "auto" })
+      } else {
+        json!({ "type": "tool", "name": response_schema.name })
+      });
+      body.tools = vec![json!({
+        "name": response_schema.name,
+        "description": response_schema.description,
+        "input_schema": response_schema.schema,
+      })];
+    }
+
+    let response: MessagesBody = self
+      .transport
+      .post_json_with_headers(
+        &format!("{}/messages", self.base_url),
+        &self.headers()?,
+        &body,
+        self.timeout,
+      )
+      .await?;
+
+    let mut text = Vec::new();
+    let mut thinking = Vec::new();
+    let mut tool_input = None;
+    for block in response.content {
+      match block {
+        ContentBlock::Text { text: part } => text.push(part),
+        ContentBlock::Thinking { thinking: part } => thinking.push(part),
+        ContentBlock::ToolUse { input } => tool_input = tool_input.or(Some(input)),
+        ContentBlock::Other => {}
+      }
+    }
+
+    let raw = tool_input
+      .map(|input| input.to_string())
+      .or_else(|| {
+        let text = text.concat();
+        (!text.trim().is_empty()).then_some(text)
+      })
+      .ok_or_else(|| anyhow!("empty message content"))?;
+    let (content, inline_reasoning) = split_reasoning(&raw);
+
+    Ok(ChatResponse {
+      content,
+      reasoning: merge_reasoning(
+        (!thinking.is_empty()).then(|| thinking.join("\n")),
+        inline_reasoning,
+      ),
+      usage: response.usage.map(|usage| TokenUsage {
+        prompt_tokens: usage.input_tokens,
+        completion_tokens: usage.output_tokens,
+      }),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use async_openai::types::chat::{
+    ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+  };
+
+  use super::*;
+  use crate::{
+    ChatTask, ResponseSchema,
+    transport::test_server::{json_response, stub_server},
+  };
+
+  #[tokio::test]
+  async fn forces_schema_tool_and_reads_its_input() {
+    let (base_url, mut requests) = stub_server(vec![json_response(&json!({
+      "type": "message",
+      "role": "assistant",
+      "content": [
+        { "type": "text", "text": "Here is the title." },
+        { "type": "tool_use", "id": "toolu_1", "name": "title", "input": { "title": "Trip" } },
+      ],
+      "stop_reason": "tool_use",
+      "usage": { "input_tokens": 20, "output_tokens": 9 },
+    }))])
+    .await;
+    let provider = MessagesProvider::new(&format!("{base_url}/v1"), "secret", "claude".to_owned());
+
+    let response = provider
+      .chat(ChatRequest {
+        task: ChatTask::EpisodeTitle,
+        messages: vec![
+          ChatCompletionRequestSystemMessage::from("Name the episode.").into(),
+          ChatCompletionRequestUserMessage::from("We planned").into(),
+          ChatCompletionRequestUserMessage::from("a trip.").into(),
+        ],
+        response_schema: Some(ResponseSchema {
+          name: "title".to_owned(),
+          description: Some("Episode title".to_owned()),
+          schema: json!({ "type": "object", "properties": { "title": { "type": "string" } } }),
+        }),
+        reasoning_effort: None,
+      })
+      .await
+      .unwrap();
+    assert_eq!(response.content, "{\"title\":\"Trip\"}");
+    assert_eq!(
+      response.usage,
+      Some(TokenUsage {
+        prompt_tokens: 20,
+        completion_tokens: 9
+      })
+    );
+
+    let request = requests.recv().await.unwrap();
+    assert!(request.starts_with("POST /v1/messages "));
+    assert!(request.contains("x-api-key: secret"));
+    assert!(request.contains("anthropic-version: 2023-06-01"));
+    let body: serde_json::Value =
+      serde_json::from_str(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+    assert_eq!(body["system"], "Name the episode.");
+    assert_eq!(
+      body["messages"],
+      json!([{ "role": "user", "content": "We planned\n\na trip." }])
+    );
+    assert_eq!(
+      body["tool_choice"],
+      json!({ "type": "tool", "name": "title" })
+    );
+    assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+    assert!(body.get("thinking").is_none());
+  }
+}
@@ -0,0 +1,378 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_openai::types::chat::ReasoningEffort;
+use async_trait::async_trait;
+use plastmem_shared::{AppError, ChatModelEnv};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+  ChatProvider, ChatRequest, ChatResponse, EmbeddingProvider, EmbeddingResponse, HttpTransport,
+  StructuredOutputMode, TokenUsage,
+  plain_message::PlainMessage,
+  reasoning::{merge_reasoning, parse_reasoning_effort, split_reasoning},
+  structured_output::insert_schema_instruction,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_mins(1);
+
+/// Native Ollama chat and embedding backend.
+///
+/// Speaks `/api/chat` and `/api/embed` instead of Ollama's OpenAI-compatible
+/// endpoints, so response schemas go through `format` (enforced by Ollama's
+/// grammar sampler) and thinking models return their trace in `message.thinking`.
+/// A trailing `/v1` on the base URL is dropped, so the URL configured for the
+/// OpenAI-compatible endpoints works unchanged.
+#[derive(Clone)]
+pub struct OllamaProvider {
+  transport: HttpTransport,
+  base_url: String,
+  api_key: String,
+  chat_model: String,
+  chat_seed: Option<i64>,
+  reasoning_effort: ReasoningEffort,
+  structured_output: StructuredOutputMode,
+  timeout: Duration,
+  embedding_model: String,
+}
+
+impl OllamaProvider {
+  #[must_use]
+  pub fn new(
+    base_url: &str,
+    api_key: &str,
+    chat_model: String,
+    chat_seed: Option<i64>,
+    embedding_model: String,
+  ) -> Self {
+    let base_url = base_url.trim_end_matches('/');
+    Self {
+      transport: HttpTransport::default(),
+      base_url: base_url.strip_suffix("/v1").unwrap_or(base_url).to_owned(),
+      api_key: api_key.to_owned(),
+      chat_model,
+      chat_seed,
+      reasoning_effort: ReasoningEffort::None,
+      structured_output: StructuredOutputMode::StrictSchema,
+      timeout: DEFAULT_TIMEOUT,
+      embedding_model,
+    }
+  }
+
+  /// Send requests through `transport`, sharing its connection pool and limits.
+  #[must_use]
+  pub fn with_transport(mut self, transport: HttpTransport) -> Self {
+    self.transport = transport;
+    self
+  }
+
+  /// Any effort other than `none` turns on `think`; Ollama has no effort levels for most models.
+  #[must_use]
+  pub const fn with_reasoning_effort(mut self, reasoning_effort: ReasoningEffort) -> Self {
+    self.reasoning_effort = reasoning_effort;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_structured_output(mut self, structured_output: StructuredOutputMode) -> Self {
+    self.structured_output = structured_output;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Build the provider from one chat backend's settings and the shared embedding model.
+  #[must_use]
+  pub fn from_model_env(env: &ChatModelEnv, embedding_model: String) -> Self {
+    Self::new(
+      &env.base_url,
+      &env.api_key,
+      env.model.clone(),
+      env.seed,
+      embedding_model,
+    )
+    .with_reasoning_effort(parse_reasoning_effort(&env.reasoning_effort))
+    .with_structured_output(StructuredOutputMode::parse_or_default(
+      &env.structured_output,
+    ))
+    .with_timeout(Duration::from_secs(env.timeout_seconds))
+  }
+}
+
+#[derive(Serialize)]
+struct OllamaMessage {
+  role: &'static str,
+  content: String,
+}
+
+#[derive(Serialize)]
+struct OllamaChatRequest<'a> {
+  model: &'a str,
+  messages: Vec<OllamaMessage>,
+  stream: bool,
+  think: bool,
+  /// A JSON schema, or `"json"` for any JSON value.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  format: Option<serde_json::Value>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  tools: Vec<serde_json::Value>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  options: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct OllamaChatBody {
+  message: OllamaMessageBody,
+  prompt_eval_count: Option<u32>,
+  eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct OllamaMessageBody {
+  #[serde(default)]
+  content: String,
+  thinking: Option<String>,
+  #[serde(default)]
+  tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Deserialize)]
+struct OllamaToolCall {
+  function: OllamaFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct OllamaFunctionCall {
+  /// Ollama returns arguments as a JSON object, not a string.
+  arguments: serde_json::Value,
+}
+
+fn usage(prompt_tokens: Option<u32>, completion_tokens: Option<u32>) -> Option<TokenUsage> {
+  (prompt_tokens.is_some() || completion_tokens.is_some()).then(|| TokenUsage {
+    prompt_tokens: prompt_tokens.unwrap_or_default(),
+    completion_tokens: completion_tokens.unwrap_or_default(),
+  })
+}
+
+#[async_trait]
+impl ChatProvider for OllamaProvider {
+  fn model(&self) -> &str {
+    &self.chat_model
+  }
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError> {
+    let mut messages = request.messages;
+    if let Some(response_schema) = &request.response_schema
+      && self.structured_output.needs_prompt_schema()
+    {
+      insert_schema_instruction(&mut messages, response_schema);
+    }
+
+    let reasoning_effort = request
+      .reasoning_effort
+      .unwrap_or_else(|| self.reasoning_effort.clone());
+    let mut body = OllamaChatRequest {
+      model: &self.chat_model,
+      messages: messages
+        .iter()
+        .map(|message| {
+          let message = PlainMessage::from_request(message);
+          OllamaMessage {
+            role: message.role.as_str(),
+            content: message.content,
+          }
+        })
+        .collect(),
+      stream: false,
+      think: reasoning_effort != ReasoningEffort::None,
+      format: None,
+      tools: Vec::new(),
+      options: self.chat_seed.map(|seed| json!({ "seed": seed })),
+    };
+
+    if let Some(response_schema) = request.response_schema {
+      match self.structured_output {
+        StructuredOutputMode::StrictSchema => body.format = Some(response_schema.schema),
+        StructuredOutputMode::JsonObject => body.format = Some(json!("json")),
+        // Ollama cannot force a tool call; replies without one are read from content
+        StructuredOutputMode::ToolCall => {
+          body.tools = vec![json!({
+            "type": "function",
+            "function": {
+              "name": response_schema.name,
+              "description": response_schema.description,
+              "parameters": response_schema.schema,
+            },
+          })];
+        }
+        StructuredOutputMode::PromptSchema => {}
+      }
+    }
+
+    let response: OllamaChatBody = self
+      .transport
+      .post_json(
+        &format!("{}/api/chat", self.base_url),
+        &self.api_key,
+        &body,
+        self.timeout,
+      )
+      .await?;
+    let message = response.message;
+
+    let raw = message
+      .tool_calls
+      .into_iter()
+      .next()
+      .map(|call| call.function.arguments.to_string())
+      .or_else(|| (!message.content.trim().is_empty()).then_some(message.content))
+      .ok_or_else(|| anyhow!("empty message content"))?;
+    let (content, inline_reasoning) = split_reasoning(&raw);
+
+    Ok(ChatResponse {
+      content,
+      reasoning: merge_reasoning(message.thinking, inline_reasoning),
+      usage: usage(response.prompt_eval_count, response.eval_count),
+    })
+  }
+}
+
+#[derive(Serialize)]
+struct OllamaEmbedRequest<'a> {
+  model: &'a str,
+  input: Vec<String>,
+  truncate: bool,
+}
+
+#[derive(Deserialize)]
+struct OllamaEmbedBody {
+  embeddings: Vec<Vec<f32>>,
+  prompt_eval_count: Option<u32>,
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+  fn model(&self) -> &str {
+    &self.embedding_model
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    let expected = inputs.len();
+    let response: OllamaEmbedBody = self
+      .transport
+      .post_json(
+        &format!("{}/api/embed", self.base_url),
+        &self.api_key,
+        &OllamaEmbedRequest {
+          model: &self.embedding_model,
+          input: inputs,
+          truncate: true,
+        },
+        self.timeout,
+      )
+      .await?;
+
+    if response.embeddings.len() != expected {
+      return Err(
+        anyhow!(
+          "embedding count mismatch: expected {expected}, got {}",
+          response.embeddings.len()
+        )
+        .into(),
+      );
+    }
+
+    Ok(EmbeddingResponse {
+      embeddings: response.embeddings,
+      usage: usage(response.prompt_eval_count, Some(0)),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use async_openai::types::chat::{
+    ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage,
+  };
+
+  use super::*;
+  use crate::{
+    ChatTask, ResponseSchema,
+    transport::test_server::{json_response, stub_server},
+  };
+
+  #[tokio::test]
+  async fn maps_schema_to_format_and_reads_thinking() {
+    let (base_url, mut requests) = stub_server(vec![
+      json_response(&json!({
+        "model": "qwen3",
+        "message": { "role": "assistant", "content": "{\"title\":\"Trip\"}", "thinking": "plan" },
+        "done": true,
+        "prompt_eval_count": 12,
+        "eval_count": 5,
+      })),
+      json_response(&json!({ "embeddings": [[0.5, 0.5]], "prompt_eval_count": 3 })),
+    ])
+    .await;
+    let provider = OllamaProvider::new(
+      &format!("{base_url}/v1/"),
+      "",
+      "qwen3".to_owned(),
+      Some(7),
+      "bge-m3".to_owned(),
+    )
+    .with_reasoning_effort(ReasoningEffort::Low);
+
+    let response = provider
+      .chat(ChatRequest {
+        task: ChatTask::EpisodeTitle,
+        messages: vec![
+          ChatCompletionRequestSystemMessage::from("Name the episode.").into(),
+          ChatCompletionRequestUserMessage::from("We planned a trip.").into(),
+        ],
+        response_schema: Some(ResponseSchema {
+          name: "title".to_owned(),
+          description: None,
+          schema: json!({ "type": "object", "properties": { "title": { "type": "string" } } }),
+        }),
+        reasoning_effort: None,
+      })
+      .await
+      .unwrap();
+    assert_eq!(response.content, "{\"title\":\"Trip\"}");
+    assert_eq!(response.reasoning.as_deref(), Some("plan"));
+    assert_eq!(
+      response.usage,
+      Some(TokenUsage {
+        prompt_tokens: 12,
+        completion_tokens: 5
+      })
+    );
+
+    let request = requests.recv().await.unwrap();
+    assert!(request.starts_with("POST /api/chat "));
+    let body: serde_json::Value =
+      serde_json::from_str(request.split_once("\r\n\r\n").unwrap().1).unwrap();
+    assert_eq!(body["format"]["properties"]["title"]["type"], "string");
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["content"], "We planned a trip.");
+    assert_eq!(body["options"]["seed"], 7);
+    assert_eq!(body["think"], true);
+    assert_eq!(body["stream"], false);
+
+    let embedded = provider.embed(vec!["trip".to_owned()]).await.unwrap();
+    assert_eq!(embedded.embeddings, vec![vec![0.5, 0.5]]);
+    assert!(
+      requests
+        .recv()
+        .await
+        .unwrap()
+        .starts_with("POST /api/embed ")
+    );
+  }
+}
@@ -0,0 +1,300 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_openai::types::{
+  chat::{
+    ChatCompletionMessageToolCalls, ChatCompletionNamedToolChoice, ChatCompletionTool,
+    ChatCompletionToolChoiceOption, ChatCompletionTools, CreateChatCompletionRequestArgs,
+    FunctionName, FunctionObject, ReasoningEffort, ResponseFormat, ResponseFormatJsonSchema,
+  },
+  embeddings::{CreateEmbeddingRequestArgs, CreateEmbeddingResponse},
+};
+use async_trait::async_trait;
+use plastmem_shared::{APP_ENV, AppError, ChatModelEnv};
+use serde::Deserialize;
+
+use crate::{
+  ChatProvider, ChatRequest, ChatResponse, DEFAULT_EMBEDDING_DIM, EmbeddingProvider,
+  EmbeddingResponse, HttpTransport, StructuredOutputMode, TokenUsage,
+  reasoning::{merge_reasoning, parse_reasoning_effort, split_reasoning},
+  structured_output::insert_schema_instruction,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_mins(1);
+
+/// OpenAI-compatible chat and embedding backend.
+///
+/// Works with any server exposing `/chat/completions` and `/embeddings`
+/// (`OpenAI`, llama.cpp, Ollama, vLLM, ...).
+#[derive(Clone)]
+pub struct OpenAiProvider {
+  transport: HttpTransport,
+  base_url: String,
+  api_key: String,
+  chat_model: String,
+  chat_seed: Option<i64>,
+  reasoning_effort: ReasoningEffort,
+  structured_output: StructuredOutputMode,
+  timeout: Duration,
+  embedding_model: String,
+  embedding_dim: usize,
+}
+
+impl OpenAiProvider {
+  #[must_use]
+  pub fn new(
+    base_url: &str,
+    api_key: &str,
+    chat_model: String,
+    chat_seed: Option<i64>,
+    embedding_model: String,
+  ) -> Self {
+    Self {
+      transport: HttpTransport::default(),
+      base_url: base_url.trim_end_matches('/').to_owned(),
+      api_key: api_key.to_owned(),
+      chat_model,
+      chat_seed,
+      reasoning_effort: ReasoningEffort::None,
+      structured_output: StructuredOutputMode::StrictSchema,
+      timeout: DEFAULT_TIMEOUT,
+      embedding_model,
+      embedding_dim: DEFAULT_EMBEDDING_DIM,
+    }
+  }
+
+  /// Send requests through `transport`, sharing its connection pool and limits.
+  #[must_use]
+  pub fn with_transport(mut self, transport: HttpTransport) -> Self {
+    self.transport = transport;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_reasoning_effort(mut self, reasoning_effort: ReasoningEffort) -> Self {
+    self.reasoning_effort = reasoning_effort;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_structured_output(mut self, structured_output: StructuredOutputMode) -> Self {
+    self.structured_output = structured_output;
+    self
+  }
+
+  #[must_use]
+  pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+    self.timeout = timeout;
+    self
+  }
+
+  /// Dimension requested from embedding models that support shortening (`dimensions`).
+  #[must_use]
+  pub const fn with_embedding_dim(mut self, embedding_dim: usize) -> Self {
+    self.embedding_dim = embedding_dim;
+    self
+  }
+
+  /// Build the provider from one chat backend's settings and the shared embedding model.
+  #[must_use]
+  pub fn from_model_env(env: &ChatModelEnv, embedding_model: String) -> Self {
+    Self::new(
+      &env.base_url,
+      &env.api_key,
+      env.model.clone(),
+      env.seed,
+      embedding_model,
+    )
+    .with_reasoning_effort(parse_reasoning_effort(&env.reasoning_effort))
+    .with_structured_output(StructuredOutputMode::parse_or_default(
+      &env.structured_output,
+    ))
+    .with_timeout(Duration::from_secs(env.timeout_seconds))
+  }
+
+  /// Build the provider from `OPENAI_*` environment variables.
+  #[must_use]
+  pub fn from_env() -> Self {
+    Self::from_model_env(
+      &APP_ENV.chat_model_env(),
+      APP_ENV.openai_embedding_model.clone(),
+    )
+    .with_embedding_dim(APP_ENV.embedding_dim)
+  }
+}
+
+/// Chat completion response, keeping the non-standard reasoning fields that
+/// llama.cpp, vLLM, Ollama and `DeepSeek` add next to `content`.
+#[derive(Deserialize)]
+struct ChatCompletionBody {
+  choices: Vec<ChatCompletionChoiceBody>,
+  #[serde(default)]
+  usage: Option<TokenUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChoiceBody {
+  message: ChatCompletionMessageBody,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionMessageBody {
+  content: Option<String>,
+  #[serde(default, alias = "reasoning")]
+  reasoning_content: Option<String>,
+  tool_calls: Option<Vec<ChatCompletionMessageToolCalls>>,
+}
+
+#[async_trait]
+impl ChatProvider for OpenAiProvider {
+  fn model(&self) -> &str {
+    &self.chat_model
+  }
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError> {
+    let mut messages = request.messages;
+    if let Some(response_schema) = &request.response_schema
+      && self.structured_output.needs_prompt_schema()
+    {
+      insert_schema_instruction(&mut messages, response_schema);
+    }
+
+    #[allow(deprecated)]
+    let mut request_builder = CreateChatCompletionRequestArgs::default();
+    request_builder
+      .model(&self.chat_model)
+      .messages(messages)
+      .reasoning_effort(
+        request
+          .reasoning_effort
+          .unwrap_or_else(|| self.reasoning_effort.clone()),
+      );
+
+    if let Some(response_schema) = request.response_schema {
+      match self.structured_output {
+        StructuredOutputMode::StrictSchema => {
+          request_builder.response_format(ResponseFormat::JsonSchema {
+            json_schema: ResponseFormatJsonSchema {
+              description: response_schema.description,
+              name: response_schema.name,
+              schema: Some(response_schema.schema),
+              strict: Some(true),
+            },
+          });
+        }
+        StructuredOutputMode::JsonObject => {
+          request_builder.response_format(ResponseFormat::JsonObject);
+        }
+        StructuredOutputMode::ToolCall => {
+          request_builder
+            .tools(vec![ChatCompletionTools::Function(ChatCompletionTool {
+              function: FunctionObject {
+                name: response_schema.name.clone(),
+                description: response_schema.description,
+                parameters: Some(response_schema.schema),
+                strict: Some(true),
+              },
+            })])
+            .tool_choice(ChatCompletionToolChoiceOption::Function(
+              ChatCompletionNamedToolChoice {
+                function: FunctionName {
+                  name: response_schema.name,
+                },
+              },
+            ));
+        }
+        StructuredOutputMode::PromptSchema => {}
+      }
+    }
+
+    if let Some(seed) = self.chat_seed {
+      request_builder.seed(seed);
+    }
+
+    let request = request_builder.build()?;
+
+    let body: ChatCompletionBody = self
+      .transport
+      .post_json(
+        &format!("{}/chat/completions", self.base_url),
+        &self.api_key,
+        &request,
+        self.timeout,
+      )
+      .await?;
+    let message = body
+      .choices
+      .into_iter()
+      .map(|c| c.message)
+      .rfind(|m| m.content.is_some() || m.tool_calls.is_some())
+      .ok_or_else(|| anyhow!("empty message content"))?;
+
+    // Servers that ignore the forced tool choice still answer in content
+    let arguments = message.tool_calls.and_then(|calls| {
+      calls.into_iter().find_map(|call| match call {
+        ChatCompletionMessageToolCalls::Function(call) => Some(call.function.arguments),
+        ChatCompletionMessageToolCalls::Custom(_) => None,
+      })
+    });
+    let raw = arguments
+      .or(message.content)
+      .ok_or_else(|| anyhow!("empty message content"))?;
+    let (content, inline_reasoning) = split_reasoning(&raw);
+
+    Ok(ChatResponse {
+      content,
+      reasoning: merge_reasoning(message.reasoning_content, inline_reasoning),
+      usage: body.usage,
+    })
+  }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiProvider {
+  fn model(&self) -> &str {
+    &self.embedding_model
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    let embedding_dim =
+      u32::try_from(self.embedding_dim).map_err(|_| anyhow!("EMBEDDING_DIM must fit in u32"))?;
+    let expected = inputs.len();
+
+    let request = CreateEmbeddingRequestArgs::default()
+      .model(&self.embedding_model)
+      .input(inputs)
+      .dimensions(embedding_dim)
+      .build()?;
+    let response: CreateEmbeddingResponse = self
+      .transport
+      .post_json(
+        &format!("{}/embeddings", self.base_url),
+        &self.api_key,
+        &request,
+        self.timeout,
+      )
+      .await?;
+
+    // Sort by index to ensure ordering matches input
+    let mut data = response.data;
+    data.sort_by_key(|e| e.index);
+
+    if data.len() != expected {
+      return Err(
+        anyhow!(
+          "embedding count mismatch: expected {expected}, got {}",
+          data.len()
+        )
+        .into(),
+      );
+    }
+
+    Ok(EmbeddingResponse {
+      embeddings: data.into_iter().map(|e| e.embedding).collect(),
+      usage: Some(TokenUsage {
+        prompt_tokens: response.usage.prompt_tokens,
+        completion_tokens: 0,
+      }),
+    })
+  }
+}
@@ -0,0 +1,58 @@
+use async_openai::types::chat::ChatCompletionRequestMessage;
+
+/// Author of a [`PlainMessage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlainRole {
+  System,
+  User,
+  Assistant,
+}
+
+impl PlainRole {
+  #[must_use]
+  pub const fn as_str(self) -> &'static str {
+    match self {
+      Self::System => "system",
+      Self::User => "user",
+      Self::Assistant => "assistant",
+    }
+  }
+}
+
+/// A chat message reduced to its role and text, for backends with their own message format.
+///
+/// Developer messages count as system messages; tool and function results as user messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlainMessage {
+  pub role: PlainRole,
+  pub content: String,
+}
+
+impl PlainMessage {
+  #[must_use]
+  pub fn from_request(message: &ChatCompletionRequestMessage) -> Self {
+    let role = match message {
+      ChatCompletionRequestMessage::Developer(_) | ChatCompletionRequestMessage::System(_) => {
+        PlainRole::System
+      }
+      ChatCompletionRequestMessage::User(_)
+      | ChatCompletionRequestMessage::Tool(_)
+      | ChatCompletionRequestMessage::Function(_) => PlainRole::User,
+      ChatCompletionRequestMessage::Assistant(_) => PlainRole::Assistant,
+    };
+
+    // Content is either a string or an array of parts; only text parts are kept
+    let value = serde_json::to_value(message).unwrap_or_default();
+    let content = match &value["content"] {
+      serde_json::Value::String(text) => text.clone(),
+      serde_json::Value::Array(parts) => parts
+        .iter()
+        .filter_map(|part| part["text"].as_str())
+        .collect::<Vec<_>>()
+        .join("\n"),
+      _ => String::new(),
+    };
+
+    Self { role, content }
+  }
+}
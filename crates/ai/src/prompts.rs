@@ -0,0 +1,290 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  fs,
+  path::Path,
+};
+
+use anyhow::Context;
+use plastmem_shared::{AppError, Language, TemplateEscape, fill_template};
+use sha2::{Digest, Sha256};
+
+/// Prompt name to template version, recorded with the artifacts the prompts produced.
+pub type PromptVersions = BTreeMap<String, String>;
+
+/// A prompt template compiled into the binary.
+///
+/// Used unless the [`PromptRegistry`] holds an override with the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinPrompt {
+  pub name: &'static str,
+  /// Bumped whenever the template text changes.
+  pub version: &'static str,
+  pub template: &'static str,
+}
+
+impl BuiltinPrompt {
+  #[must_use]
+  pub const fn new(name: &'static str, version: &'static str, template: &'static str) -> Self {
+    Self {
+      name,
+      version,
+      template,
+    }
+  }
+}
+
+/// A template replacing the built-in prompt with the same name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+  pub name: String,
+  pub version: String,
+  pub template: String,
+}
+
+impl PromptTemplate {
+  /// Parse a template file.
+  ///
+  /// The file may start with a front matter block holding its version:
+  ///
+  /// ```text
+  /// ---
+  /// version: persona-2
+  /// ---
+  /// You are naming one conversation segment...
+  /// ```
+  ///
+  /// Without one, the version is derived from the template content.
+  #[must_use]
+  pub fn parse(name: impl Into<String>, source: &str) -> Self {
+    let (version, template) = split_front_matter(source);
+    let template = template.trim().to_owned();
+    let version = version.unwrap_or_else(|| content_version(&template));
+    Self {
+      name: name.into(),
+      version,
+      template,
+    }
+  }
+}
+
+fn split_front_matter(source: &str) -> (Option<String>, &str) {
+  let Some(rest) = source.strip_prefix("---\n") else {
+    return (None, source);
+  };
+  let Some((front_matter, template)) = rest.split_once("\n---\n") else {
+    return (None, source);
+  };
+  let version = front_matter.lines().find_map(|line| {
+    let (key, value) = line.split_once(':')?;
+    (key.trim() == "version")
+      .then(|| value.trim().to_owned())
+      .filter(|value| !value.is_empty())
+  });
+  (version, template)
+}
+
+fn content_version(template: &str) -> String {
+  let digest = format!("{:x}", Sha256::digest(template.as_bytes()));
+  format!("sha256-{}", &digest[..12])
+}
+
+/// Appended to built-in prompts rendered for a language other than English.
+const OUTPUT_LANGUAGE_INSTRUCTION: &str = "Language:\n\
+  - The conversation is in {language}. Write every natural-language value you return (titles, facts, predictions, anchor text) in {language}.\n\
+  - Keep JSON keys, enum values and category names exactly as specified above.\n\
+  - Write dates and times with Arabic numerals.";
+
+/// A rendered prompt and the template version it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+  pub name: &'static str,
+  pub version: String,
+  pub text: String,
+}
+
+impl Prompt {
+  /// Record the version of this prompt in `versions`.
+  pub fn record(&self, versions: &mut PromptVersions) {
+    versions.insert(self.name.to_owned(), self.version.clone());
+  }
+}
+
+/// Prompt overrides, looked up by name when a [`BuiltinPrompt`] is rendered.
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+  overrides: HashMap<String, PromptTemplate>,
+}
+
+impl PromptRegistry {
+  /// Replace the built-in prompt named `template.name`. A later template with the same name wins.
+  #[must_use]
+  pub fn with_template(mut self, template: PromptTemplate) -> Self {
+    self.overrides.insert(template.name.clone(), template);
+    self
+  }
+
+  /// Load every `<name>.md` or `<name>.txt` file in `dir` as the template for prompt `<name>`.
+  pub fn load_dir(self, dir: impl AsRef<Path>) -> Result<Self, AppError> {
+    let dir = dir.as_ref();
+    let mut registry = self;
+    let entries = fs::read_dir(dir)
+      .with_context(|| format!("failed to read prompt templates from {}", dir.display()))?;
+    for entry in entries {
+      let path = entry?.path();
+      let is_template = path
+        .extension()
+        .is_some_and(|extension| extension == "md" || extension == "txt");
+      let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        continue;
+      };
+      if !is_template {
+        continue;
+      }
+      let source = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read prompt template {}", path.display()))?;
+      registry = registry.with_template(PromptTemplate::parse(name, &source));
+    }
+    Ok(registry)
+  }
+
+  /// Loaded overrides, in no particular order.
+  pub fn templates(&self) -> impl Iterator<Item = &PromptTemplate> {
+    self.overrides.values()
+  }
+
+  /// Version of the template that [`PromptRegistry::render`] would use for `builtin`.
+  #[must_use]
+  pub fn version(&self, builtin: &BuiltinPrompt) -> &str {
+    self
+      .overrides
+      .get(builtin.name)
+      .map_or(builtin.version, |template| template.version.as_str())
+  }
+
+  /// Render `builtin`, or its override, replacing each `{name}` in `vars` with its value.
+  ///
+  /// Placeholders without a value are left as they are.
+  #[must_use]
+  pub fn render(&self, builtin: &BuiltinPrompt, vars: &[(&str, &str)]) -> Prompt {
+    let (version, template) = self.overrides.get(builtin.name).map_or_else(
+      || (builtin.version.to_owned(), builtin.template),
+      |template| (template.version.clone(), template.template.as_str()),
+    );
+    Prompt {
+      name: builtin.name,
+      version,
+      text: fill_template(template, vars, TemplateEscape::None, &[]),
+    }
+  }
+
+  /// Render `builtin` for a conversation held in `language`.
+  ///
+  /// English renders as [`PromptRegistry::render`]. Other languages use the override named
+  /// `<name>.<code>` (e.g. `episode_title.ja`) when one is loaded; otherwise the prompt
+  /// [`PromptRegistry::render`] would use is extended with an instruction to answer in
+  /// `language`, and its version gets a `+<code>` suffix.
+  #[must_use]
+  pub fn render_in(
+    &self,
+    builtin: &BuiltinPrompt,
+    language: Language,
+    vars: &[(&str, &str)],
+  ) -> Prompt {
+    if language == Language::English {
+      return self.render(builtin, vars);
+    }
+
+    if let Some(template) = self
+      .overrides
+      .get(&format!("{}.{}", builtin.name, language.code()))
+    {
+      return Prompt {
+        name: builtin.name,
+        version: template.version.clone(),
+        text: fill_template(&template.template, vars, TemplateEscape::None, &[]),
+      };
+    }
+
+    let prompt = self.render(builtin, vars);
+    let instruction = fill_template(
+      OUTPUT_LANGUAGE_INSTRUCTION,
+      &[("language", language.name())],
+      TemplateEscape::None,
+      &[],
+    );
+    Prompt {
+      name: prompt.name,
+      version: format!("{}+{}", prompt.version, language.code()),
+      text: format!("{}\n\n{instruction}", prompt.text.trim_end()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const GREETING: BuiltinPrompt =
+    BuiltinPrompt::new("greeting", "builtin-1", "Hello {name}, {unknown}!");
+
+  #[test]
+  fn renders_builtin_or_override_with_version() {
+    let registry = PromptRegistry::default();
+    let prompt = registry.render(&GREETING, &[("name", "{Ada}")]);
+    assert_eq!(prompt.text, "Hello {Ada}, {unknown}!");
+    assert_eq!(prompt.version, "builtin-1");
+
+    let registry = registry.with_template(PromptTemplate::parse(
+      "greeting",
+      "---\nversion: persona-2\n---\nHi {name}.\n",
+    ));
+    let prompt = registry.render(&GREETING, &[("name", "Ada")]);
+    assert_eq!(prompt.text, "Hi Ada.");
+    assert_eq!(registry.version(&GREETING), "persona-2");
+
+    let mut versions = PromptVersions::new();
+    prompt.record(&mut versions);
+    assert_eq!(
+      versions.get("greeting").map(String::as_str),
+      Some("persona-2")
+    );
+  }
+
+  #[test]
+  fn renders_localized_variant_or_language_instruction() {
+    let registry = PromptRegistry::default();
+    let prompt = registry.render_in(&GREETING, Language::Japanese, &[("name", "Ada")]);
+    assert!(
+      prompt
+        .text
+        .starts_with("Hello Ada, {unknown}!\n\nLanguage:")
+    );
+    assert!(prompt.text.contains("The conversation is in Japanese."));
+    assert_eq!(prompt.version, "builtin-1+ja");
+    assert_eq!(
+      registry.render_in(&GREETING, Language::English, &[]),
+      registry.render(&GREETING, &[])
+    );
+
+    let registry = registry.with_template(PromptTemplate::parse(
+      "greeting.ja",
+      "---\nversion: ja-1\n---\nこんにちは {name}",
+    ));
+    let prompt = registry.render_in(&GREETING, Language::Japanese, &[("name", "Ada")]);
+    assert_eq!(prompt.text, "こんにちは Ada");
+    assert_eq!(prompt.version, "ja-1");
+  }
+
+  #[test]
+  fn loads_templates_from_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("greeting.md"), "Hey {name}").unwrap();
+    fs::write(dir.path().join("notes.json"), "{}").unwrap();
+
+    let registry = PromptRegistry::default().load_dir(dir.path()).unwrap();
+    assert_eq!(registry.templates().count(), 1);
+    let prompt = registry.render(&GREETING, &[("name", "Ada")]);
+    assert_eq!(prompt.text, "Hey Ada");
+    assert!(prompt.version.starts_with("sha256-"));
+  }
+}
@@ -0,0 +1,80 @@
+use async_openai::types::chat::ReasoningEffort;
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+/// Separate a `<think>...</think>` reasoning trace from the final answer.
+///
+/// Handles replies whose chat template already opened the block (only
+/// `</think>` present) and replies truncated inside the block (no `</think>`),
+/// which have no final answer at all.
+#[must_use]
+pub fn split_reasoning(content: &str) -> (String, Option<String>) {
+  let (before, after_open) = match content.find(THINK_OPEN) {
+    Some(start) => (&content[..start], &content[start + THINK_OPEN.len()..]),
+    None if content.contains(THINK_CLOSE) => ("", content),
+    None => return (content.to_owned(), None),
+  };
+
+  let (reasoning, answer) = after_open
+    .find(THINK_CLOSE)
+    .map_or((after_open, ""), |end| {
+      (&after_open[..end], &after_open[end + THINK_CLOSE.len()..])
+    });
+
+  let answer = format!("{}{}", before.trim(), answer.trim())
+    .trim()
+    .to_owned();
+  let reasoning = reasoning.trim();
+  (
+    answer,
+    (!reasoning.is_empty()).then(|| reasoning.to_owned()),
+  )
+}
+
+/// Merge a server-reported reasoning field with a trace found inline in the content.
+#[must_use]
+pub fn merge_reasoning(reported: Option<String>, inline: Option<String>) -> Option<String> {
+  match (reported.filter(|r| !r.trim().is_empty()), inline) {
+    (Some(reported), Some(inline)) => Some(format!("{reported}\n{inline}")),
+    (reported, inline) => reported.or(inline),
+  }
+}
+
+/// Parse a reasoning effort name (`none`, `minimal`, `low`, `medium`, `high`, `xhigh`).
+///
+/// Unknown values fall back to `none` so a typo never enables expensive reasoning.
+#[must_use]
+pub fn parse_reasoning_effort(value: &str) -> ReasoningEffort {
+  serde_json::from_value(serde_json::Value::String(value.trim().to_ascii_lowercase()))
+    .unwrap_or_else(|_| {
+      tracing::warn!(value, "unknown reasoning effort, using none");
+      ReasoningEffort::None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn split_reasoning_handles_think_block_variants() {
+    assert_eq!(
+      split_reasoning("<think>\nplan\n</think>\n\n{\"a\": 1}"),
+      ("{\"a\": 1}".to_owned(), Some("plan".to_owned()))
+    );
+    assert_eq!(
+      split_reasoning("plan</think>answer"),
+      ("answer".to_owned(), Some("plan".to_owned()))
+    );
+    assert_eq!(
+      split_reasoning("<think>cut off mid-thought"),
+      (String::new(), Some("cut off mid-thought".to_owned()))
+    );
+    assert_eq!(
+      split_reasoning("<think></think>answer"),
+      ("answer".to_owned(), None)
+    );
+    assert_eq!(split_reasoning("answer"), ("answer".to_owned(), None));
+  }
+}
@@ -0,0 +1,282 @@
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use plastmem_shared::AppError;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+
+use crate::{
+  AiClient, ChatProvider, ChatRequest, ChatResponse, ChatTask, EmbeddingProvider,
+  EmbeddingResponse, TokenUsage,
+};
+
+/// Record/replay backend for offline pipeline tests.
+///
+/// Fixtures are stored as one JSON file per request under
+/// `<fixtures_dir>/chat/<sha256>.json` and `<fixtures_dir>/embedding/<sha256>.json`.
+/// Chat requests are keyed by model, messages and response schema; embeddings
+/// are keyed per input by model and text, so batching does not affect replay.
+pub struct ReplayProvider {
+  fixtures_dir: PathBuf,
+  chat_model: String,
+  task_chat_models: HashMap<ChatTask, String>,
+  embedding_model: String,
+  /// Set in record mode. Requests are forwarded here and their results written as fixtures.
+  upstream: Option<AiClient>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChatFixture {
+  model: String,
+  messages: serde_json::Value,
+  response_schema: serde_json::Value,
+  content: String,
+  #[serde(default)]
+  reasoning: Option<String>,
+  #[serde(default)]
+  usage: Option<TokenUsage>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmbeddingFixture {
+  model: String,
+  input: String,
+  embedding: Vec<f32>,
+}
+
+impl ReplayProvider {
+  /// Serve every request from `fixtures_dir`. A missing fixture is an error.
+  ///
+  /// `chat_model` and `embedding_model` must match the models the fixtures were recorded with.
+  #[must_use]
+  pub fn replay(
+    fixtures_dir: impl Into<PathBuf>,
+    chat_model: String,
+    embedding_model: String,
+  ) -> Self {
+    Self {
+      fixtures_dir: fixtures_dir.into(),
+      chat_model,
+      task_chat_models: HashMap::new(),
+      embedding_model,
+      upstream: None,
+    }
+  }
+
+  /// Key replayed `task` requests by `model` instead of the default chat model.
+  #[must_use]
+  pub fn with_task_model(mut self, task: ChatTask, model: String) -> Self {
+    self.task_chat_models.insert(task, model);
+    self
+  }
+
+  /// Forward every request to `upstream` and (over)write its fixture.
+  #[must_use]
+  pub fn record(fixtures_dir: impl Into<PathBuf>, upstream: AiClient) -> Self {
+    Self {
+      fixtures_dir: fixtures_dir.into(),
+      chat_model: upstream.chat().model().to_owned(),
+      task_chat_models: HashMap::new(),
+      embedding_model: upstream.embedding().model().to_owned(),
+      upstream: Some(upstream),
+    }
+  }
+
+  fn chat_model_for(&self, task: ChatTask) -> &str {
+    match &self.upstream {
+      Some(upstream) => upstream.chat_for(task).model(),
+      None => self.task_chat_models.get(&task).unwrap_or(&self.chat_model),
+    }
+  }
+
+  fn fixture_path(&self, kind: &str, key: &str) -> PathBuf {
+    self.fixtures_dir.join(kind).join(format!("{key}.json"))
+  }
+}
+
+fn sha256_hex(value: &serde_json::Value) -> Result<String, AppError> {
+  let bytes = serde_json::to_vec(value)?;
+  Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+fn chat_key(model: &str, request: &ChatRequest) -> Result<String, AppError> {
+  sha256_hex(&serde_json::json!({
+    "model": model,
+    "messages": request.messages,
+    "response_schema": request.response_schema,
+    "reasoning_effort": request.reasoning_effort,
+  }))
+}
+
+fn embedding_key(model: &str, input: &str) -> Result<String, AppError> {
+  sha256_hex(&serde_json::json!({
+    "model": model,
+    "input": input,
+  }))
+}
+
+async fn read_fixture<T: DeserializeOwned>(path: &Path) -> Result<T, AppError> {
+  let bytes = tokio::fs::read(path).await.map_err(|err| {
+    anyhow!(
+      "missing replay fixture {}: {err}; record it with AI_FIXTURES_RECORD=true",
+      path.display()
+    )
+  })?;
+  let fixture = serde_json::from_slice(&bytes)
+    .with_context(|| format!("invalid replay fixture {}", path.display()))?;
+  Ok(fixture)
+}
+
+async fn write_fixture<T: Serialize + Sync>(path: &Path, fixture: &T) -> Result<(), AppError> {
+  if let Some(parent) = path.parent() {
+    tokio::fs::create_dir_all(parent).await?;
+  }
+  tokio::fs::write(path, serde_json::to_vec_pretty(fixture)?).await?;
+  Ok(())
+}
+
+#[async_trait]
+impl ChatProvider for ReplayProvider {
+  fn model(&self) -> &str {
+    &self.chat_model
+  }
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError> {
+    let model = self.chat_model_for(request.task).to_owned();
+    let path = self.fixture_path("chat", &chat_key(&model, &request)?);
+
+    let Some(upstream) = &self.upstream else {
+      let fixture: ChatFixture = read_fixture(&path).await?;
+      return Ok(ChatResponse {
+        content: fixture.content,
+        reasoning: fixture.reasoning,
+        usage: fixture.usage,
+      });
+    };
+
+    let messages = serde_json::to_value(&request.messages)?;
+    let response_schema = serde_json::to_value(&request.response_schema)?;
+    let response = upstream.chat_for(request.task).chat(request).await?;
+    write_fixture(
+      &path,
+      &ChatFixture {
+        model,
+        messages,
+        response_schema,
+        content: response.content.clone(),
+        reasoning: response.reasoning.clone(),
+        usage: response.usage,
+      },
+    )
+    .await?;
+
+    Ok(response)
+  }
+}
+
+#[async_trait]
+impl EmbeddingProvider for ReplayProvider {
+  fn model(&self) -> &str {
+    &self.embedding_model
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    let mut paths = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+      paths.push(self.fixture_path("embedding", &embedding_key(&self.embedding_model, input)?));
+    }
+
+    let Some(upstream) = &self.upstream else {
+      let mut embeddings = Vec::with_capacity(paths.len());
+      for path in &paths {
+        let fixture: EmbeddingFixture = read_fixture(path).await?;
+        embeddings.push(fixture.embedding);
+      }
+      return Ok(EmbeddingResponse::new(embeddings));
+    };
+
+    let response = upstream.embedding().embed(inputs.clone()).await?;
+    for ((path, input), embedding) in paths.iter().zip(inputs).zip(&response.embeddings) {
+      write_fixture(
+        path,
+        &EmbeddingFixture {
+          model: self.embedding_model.clone(),
+          input,
+          embedding: embedding.clone(),
+        },
+      )
+      .await?;
+    }
+
+    Ok(response)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::{ChatCompletionRequestMessage, ScriptedChatProvider};
+
+  struct LengthEmbedding;
+
+  #[async_trait]
+  impl EmbeddingProvider for LengthEmbedding {
+    fn model(&self) -> &'static str {
+      "length"
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+      #[allow(clippy::cast_precision_loss)]
+      Ok(EmbeddingResponse::new(
+        inputs.iter().map(|i| vec![i.len() as f32, 1.0]).collect(),
+      ))
+    }
+  }
+
+  fn chat_request(text: &str) -> ChatRequest {
+    ChatRequest {
+      task: ChatTask::Prediction,
+      messages: vec![ChatCompletionRequestMessage::User(text.into())],
+      response_schema: None,
+      reasoning_effort: None,
+    }
+  }
+
+  #[tokio::test]
+  async fn replays_recorded_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let scripted = ScriptedChatProvider::new();
+    scripted.push_text("hello back");
+    let upstream = AiClient::new(Arc::new(scripted), Arc::new(LengthEmbedding));
+
+    let recorder = ReplayProvider::record(dir.path(), upstream);
+    recorder.chat(chat_request("hello")).await.unwrap();
+    recorder
+      .embed(vec!["a".to_owned(), "abc".to_owned()])
+      .await
+      .unwrap();
+
+    let replayer = ReplayProvider::replay(dir.path(), "scripted".to_owned(), "length".to_owned());
+    let response = replayer.chat(chat_request("hello")).await.unwrap();
+    assert_eq!(response.content, "hello back");
+    let embeddings = replayer
+      .embed(vec!["abc".to_owned(), "a".to_owned()])
+      .await
+      .unwrap();
+    assert_eq!(embeddings.embeddings, vec![vec![3.0, 1.0], vec![1.0, 1.0]]);
+  }
+
+  #[tokio::test]
+  async fn replay_miss_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let replayer = ReplayProvider::replay(dir.path(), "scripted".to_owned(), "length".to_owned());
+    assert!(replayer.chat(chat_request("unseen")).await.is_err());
+    assert!(replayer.embed(vec!["unseen".to_owned()]).await.is_err());
+  }
+}
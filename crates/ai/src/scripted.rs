@@ -0,0 +1,169 @@
+use std::{
+  collections::{HashMap, VecDeque},
+  sync::{Arc, Mutex},
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use plastmem_shared::AppError;
+use serde::Serialize;
+
+use crate::{AiClient, ChatProvider, ChatRequest, ChatResponse, ReplayProvider};
+
+/// Chat backend that returns queued replies, for tests.
+///
+/// Replies for structured requests are queued per schema name, so the order in
+/// which different `generate_object` call sites run does not matter. Replies for
+/// plain `generate_text` requests share a single queue.
+#[derive(Default)]
+pub struct ScriptedChatProvider {
+  replies: Mutex<HashMap<Option<String>, VecDeque<String>>>,
+}
+
+impl ScriptedChatProvider {
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Queue the reply for the next `generate_object` call using `schema_name`.
+  pub fn push_object<T: Serialize>(&self, schema_name: &str, value: &T) -> Result<(), AppError> {
+    let content = serde_json::to_string(value)?;
+    self.push(Some(schema_name.to_owned()), content);
+    Ok(())
+  }
+
+  /// Queue raw reply text for the next `generate_object` call using `schema_name`,
+  /// e.g. to exercise extraction and validation retries.
+  pub fn push_raw(&self, schema_name: &str, content: impl Into<String>) {
+    self.push(Some(schema_name.to_owned()), content.into());
+  }
+
+  /// Queue the reply for the next request without a response schema.
+  pub fn push_text(&self, text: impl Into<String>) {
+    self.push(None, text.into());
+  }
+
+  fn push(&self, key: Option<String>, content: String) {
+    self
+      .replies
+      .lock()
+      .unwrap_or_else(std::sync::PoisonError::into_inner)
+      .entry(key)
+      .or_default()
+      .push_back(content);
+  }
+}
+
+impl AiClient {
+  /// Client answering chat requests from `chat`, for tests; it has no embedding fixtures.
+  #[must_use]
+  pub fn scripted(chat: Arc<ScriptedChatProvider>) -> Self {
+    Self::new(
+      chat,
+      Arc::new(ReplayProvider::replay(
+        "unused",
+        String::new(),
+        String::new(),
+      )),
+    )
+  }
+}
+
+#[async_trait]
+impl ChatProvider for ScriptedChatProvider {
+  fn model(&self) -> &'static str {
+    "scripted"
+  }
+
+  async fn chat(&self, request: ChatRequest) -> Result<ChatResponse, AppError> {
+    let key = request.response_schema.map(|schema| schema.name);
+    let content = self
+      .replies
+      .lock()
+      .unwrap_or_else(std::sync::PoisonError::into_inner)
+      .get_mut(&key)
+      .and_then(VecDeque::pop_front)
+      .ok_or_else(|| {
+        key.as_ref().map_or_else(
+          || anyhow!("no scripted reply left for text generation"),
+          |name| anyhow!("no scripted reply left for schema `{name}`"),
+        )
+      })?;
+
+    Ok(ChatResponse {
+      content,
+      reasoning: None,
+      usage: None,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use schemars::JsonSchema;
+  use serde::Deserialize;
+
+  use super::*;
+  use crate::{ChatCompletionRequestMessage, ChatTask, generate_object};
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+  struct Title {
+    title: String,
+  }
+
+  #[tokio::test]
+  async fn generate_object_returns_scripted_reply() {
+    let scripted = ScriptedChatProvider::new();
+    scripted
+      .push_object(
+        "episode_title",
+        &Title {
+          title: "Trip planning".to_owned(),
+        },
+      )
+      .unwrap();
+    let ai = AiClient::scripted(Arc::new(scripted));
+
+    let messages = vec![ChatCompletionRequestMessage::User("...".into())];
+    let title: Title = generate_object(
+      ChatTask::EpisodeTitle,
+      messages.clone(),
+      "episode_title".to_owned(),
+      None,
+      &ai,
+    )
+    .await
+    .unwrap();
+    assert_eq!(title.title, "Trip planning");
+
+    let exhausted = generate_object::<Title>(
+      ChatTask::EpisodeTitle,
+      messages,
+      "episode_title".to_owned(),
+      None,
+      &ai,
+    )
+    .await;
+    assert!(exhausted.is_err());
+  }
+
+  #[tokio::test]
+  async fn generate_object_retries_after_validation_error() {
+    let scripted = ScriptedChatProvider::new();
+    scripted.push_raw("episode_title", "Sure! {\"name\": \"wrong field\"}");
+    scripted.push_raw("episode_title", "```json\n{\"title\": \"Fixed\",}\n```");
+    let ai = AiClient::scripted(Arc::new(scripted));
+
+    let title: Title = generate_object(
+      ChatTask::EpisodeTitle,
+      vec![ChatCompletionRequestMessage::User("...".into())],
+      "episode_title".to_owned(),
+      None,
+      &ai,
+    )
+    .await
+    .unwrap();
+    assert_eq!(title.title, "Fixed");
+  }
+}
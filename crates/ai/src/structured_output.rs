@@ -0,0 +1,215 @@
+use async_openai::types::chat::ChatCompletionRequestMessage;
+
+use crate::ResponseSchema;
+
+/// How a backend is asked for schema-conforming JSON.
+///
+/// Only `StrictSchema` is enforced by the server. The other modes exist for
+/// llama.cpp / Ollama builds and smaller models that ignore strict mode; their
+/// replies go through [`extract_json`] before deserialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StructuredOutputMode {
+  /// `response_format: json_schema` with `strict: true`.
+  #[default]
+  StrictSchema,
+  /// `response_format: json_object`, with the schema described in the prompt.
+  JsonObject,
+  /// A forced call of one function whose parameters are the schema.
+  ToolCall,
+  /// No server-side constraint; the schema is described in the prompt.
+  PromptSchema,
+}
+
+impl StructuredOutputMode {
+  /// Parse `strict_schema`, `json_object`, `tool_call` or `prompt_schema`.
+  #[must_use]
+  pub fn parse(value: &str) -> Option<Self> {
+    match value.trim().to_ascii_lowercase().as_str() {
+      "strict_schema" => Some(Self::StrictSchema),
+      "json_object" => Some(Self::JsonObject),
+      "tool_call" => Some(Self::ToolCall),
+      "prompt_schema" => Some(Self::PromptSchema),
+      _ => None,
+    }
+  }
+
+  /// Parse a configured mode, falling back to `strict_schema` on unknown values.
+  #[must_use]
+  pub fn parse_or_default(value: &str) -> Self {
+    Self::parse(value).unwrap_or_else(|| {
+      tracing::warn!(value, "unknown structured output mode, using strict_schema");
+      Self::StrictSchema
+    })
+  }
+
+  /// Whether the schema has to be spelled out in the prompt.
+  #[must_use]
+  pub const fn needs_prompt_schema(self) -> bool {
+    matches!(self, Self::JsonObject | Self::PromptSchema)
+  }
+}
+
+/// Insert a system message describing `schema` after the leading system messages.
+pub fn insert_schema_instruction(
+  messages: &mut Vec<ChatCompletionRequestMessage>,
+  schema: &ResponseSchema,
+) {
+  let description = schema
+    .description
+    .as_deref()
+    .map(|description| format!("Purpose: {description}\n"))
+    .unwrap_or_default();
+  let instruction = format!(
+    "Respond with a single JSON value named `{}` and nothing else: no prose, no code fences.\n{description}It must validate against this JSON schema:\n{}",
+    schema.name, schema.schema,
+  );
+
+  let position = messages
+    .iter()
+    .position(|message| !matches!(message, ChatCompletionRequestMessage::System(_)))
+    .unwrap_or(messages.len());
+  messages.insert(
+    position,
+    ChatCompletionRequestMessage::System(instruction.into()),
+  );
+}
+
+/// Pull the JSON value out of a model reply.
+///
+/// Handles replies wrapped in code fences or prose, trailing commas, and
+/// objects truncated before their closing brackets. The result is not
+/// guaranteed to be valid JSON; deserialization reports what is still wrong.
+#[must_use]
+pub fn extract_json(content: &str) -> String {
+  let trimmed = content.trim();
+  if serde_json::from_str::<serde_json::Value>(trimmed).is_ok() {
+    return trimmed.to_owned();
+  }
+
+  let unfenced = strip_code_fence(trimmed).unwrap_or(trimmed);
+  let Some(start) = unfenced.find(['{', '[']) else {
+    return unfenced.to_owned();
+  };
+
+  repair_json(&unfenced[start..])
+}
+
+fn strip_code_fence(content: &str) -> Option<&str> {
+  let start = content.find("```")?;
+  let after_fence = &content[start + 3..];
+  // Skip the language tag on the opening fence line
+  let body_start = after_fence.find('\n').map_or(0, |i| i + 1);
+  let body = &after_fence[body_start..];
+  Some(body.find("```").map_or(body, |end| &body[..end]).trim())
+}
+
+/// Cut `value` at the end of its first top-level JSON value, dropping trailing
+/// commas and closing brackets left open by truncation.
+fn repair_json(value: &str) -> String {
+  let mut out = String::with_capacity(value.len());
+  let mut closers = Vec::new();
+  let mut in_string = false;
+  let mut escaped = false;
+
+  for c in value.chars() {
+    if in_string {
+      out.push(c);
+      if escaped {
+        escaped = false;
+      } else if c == '\\' {
+        escaped = true;
+      } else if c == '"' {
+        in_string = false;
+      }
+      continue;
+    }
+
+    match c {
+      '"' => {
+        in_string = true;
+        out.push(c);
+      }
+      '{' => {
+        closers.push('}');
+        out.push(c);
+      }
+      '[' => {
+        closers.push(']');
+        out.push(c);
+      }
+      '}' | ']' => {
+        trim_trailing_comma(&mut out);
+        out.push(c);
+        closers.pop();
+        if closers.is_empty() {
+          return out;
+        }
+      }
+      _ => out.push(c),
+    }
+  }
+
+  if in_string {
+    out.push('"');
+  }
+  trim_trailing_comma(&mut out);
+  while let Some(closer) = closers.pop() {
+    out.push(closer);
+  }
+  out
+}
+
+fn trim_trailing_comma(out: &mut String) {
+  let trimmed_len = out.trim_end().len();
+  if out[..trimmed_len].ends_with(',') {
+    out.truncate(trimmed_len - 1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parses(content: &str) -> serde_json::Value {
+    serde_json::from_str(&extract_json(content)).unwrap()
+  }
+
+  #[test]
+  fn extract_json_handles_fences_prose_and_repairs() {
+    let expected = serde_json::json!({ "title": "a, b", "tags": ["x"] });
+
+    assert_eq!(parses(r#"{"title":"a, b","tags":["x"]}"#), expected);
+    assert_eq!(
+      parses("```json\n{\"title\": \"a, b\", \"tags\": [\"x\"]}\n```"),
+      expected
+    );
+    assert_eq!(
+      parses("Sure! Here it is: {\"title\": \"a, b\", \"tags\": [\"x\",],} Hope that helps {ok}"),
+      expected
+    );
+    assert_eq!(parses("{\"title\": \"a, b\", \"tags\": [\"x\""), expected);
+  }
+
+  #[test]
+  fn schema_instruction_goes_after_leading_system_messages() {
+    let mut messages = vec![
+      ChatCompletionRequestMessage::System("rules".into()),
+      ChatCompletionRequestMessage::User("input".into()),
+    ];
+    insert_schema_instruction(
+      &mut messages,
+      &ResponseSchema {
+        name: "out".to_owned(),
+        description: None,
+        schema: serde_json::json!({ "type": "object" }),
+      },
+    );
+
+    assert_eq!(messages.len(), 3);
+    assert!(matches!(
+      messages[1],
+      ChatCompletionRequestMessage::System(_)
+    ));
+    assert!(matches!(messages[2], ChatCompletionRequestMessage::User(_)));
+  }
+}
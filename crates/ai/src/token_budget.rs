@@ -0,0 +1,212 @@
+use std::{borrow::Cow, ops::Range, sync::Arc};
+
+/// Counts the tokens a chat model sees for a piece of text.
+pub trait TokenCounter: Send + Sync {
+  fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Tokenizer-free estimate that errs high: three ASCII bytes per token, and one
+/// token per other character (CJK text tokenizes to about one token per character).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EstimatedTokenCounter;
+
+const ASCII_BYTES_PER_TOKEN: usize = 3;
+
+impl TokenCounter for EstimatedTokenCounter {
+  fn count_tokens(&self, text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(ascii, other), c| {
+      if c.is_ascii() {
+        (ascii + 1, other)
+      } else {
+        (ascii, other + 1)
+      }
+    });
+    ascii.div_ceil(ASCII_BYTES_PER_TOKEN) + other
+  }
+}
+
+/// Counts tokens with a Hugging Face `tokenizer.json`, e.g. the chat model's own.
+#[cfg(feature = "tokenizer")]
+pub struct TokenizerCounter {
+  tokenizer: tokenizers::Tokenizer,
+}
+
+#[cfg(feature = "tokenizer")]
+impl TokenizerCounter {
+  pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, plastmem_shared::AppError> {
+    let path = path.as_ref();
+    let tokenizer = tokenizers::Tokenizer::from_file(path)
+      .map_err(|err| anyhow::anyhow!("failed to load tokenizer from {}: {err}", path.display()))?;
+    Ok(Self { tokenizer })
+  }
+}
+
+#[cfg(feature = "tokenizer")]
+impl TokenCounter for TokenizerCounter {
+  fn count_tokens(&self, text: &str) -> usize {
+    self.tokenizer.encode(text, false).map_or_else(
+      |_| EstimatedTokenCounter.count_tokens(text),
+      |encoding| encoding.len(),
+    )
+  }
+}
+
+/// Context window assumed for models without a configured size.
+pub const DEFAULT_CONTEXT_TOKENS: usize = 32_768;
+/// Most tokens held back from the context window for the model's reply.
+const RESPONSE_RESERVE_TOKENS: usize = 4096;
+/// Appended to truncated text.
+const TRUNCATION_MARKER: &str = " [...]";
+
+/// Tokens a prompt may still use for one chat task.
+///
+/// Starts from the task's context size minus a reserve for the reply
+/// (`min(4096, context / 4)`); [`TokenBudget::reserve`] takes the fixed parts
+/// of the prompt off, and the rest is shared by the variable inputs, which are
+/// truncated or chunked deterministically to fit.
+#[derive(Clone)]
+pub struct TokenBudget {
+  context_tokens: usize,
+  available: usize,
+  counter: Arc<dyn TokenCounter>,
+}
+
+impl TokenBudget {
+  #[must_use]
+  pub fn new(context_tokens: usize, counter: Arc<dyn TokenCounter>) -> Self {
+    let reserve = (context_tokens / 4).min(RESPONSE_RESERVE_TOKENS);
+    Self {
+      context_tokens,
+      available: context_tokens - reserve,
+      counter,
+    }
+  }
+
+  /// A budget of exactly `tokens`, with nothing held back for a reply.
+  #[must_use]
+  pub fn exact(tokens: usize, counter: Arc<dyn TokenCounter>) -> Self {
+    Self {
+      context_tokens: tokens,
+      available: tokens,
+      counter,
+    }
+  }
+
+  /// Context window of the task's model.
+  #[must_use]
+  pub const fn context_tokens(&self) -> usize {
+    self.context_tokens
+  }
+
+  /// Tokens left for the prompt.
+  #[must_use]
+  pub const fn available(&self) -> usize {
+    self.available
+  }
+
+  #[must_use]
+  pub fn count(&self, text: &str) -> usize {
+    self.counter.count_tokens(text)
+  }
+
+  /// Take the tokens of a fixed prompt part (system prompt, headers) off the budget.
+  #[must_use]
+  pub fn reserve(mut self, text: &str) -> Self {
+    self.available = self.available.saturating_sub(self.count(text));
+    self
+  }
+
+  /// Take `tokens` off the budget, e.g. for a part not rendered yet.
+  #[must_use]
+  pub const fn reserve_tokens(mut self, tokens: usize) -> Self {
+    self.available = self.available.saturating_sub(tokens);
+    self
+  }
+
+  #[must_use]
+  pub fn fits(&self, text: &str) -> bool {
+    self.count(text) <= self.available
+  }
+
+  /// Cut `text` to at most `max_tokens`, marking the cut. Text that fits is returned as is.
+  #[must_use]
+  pub fn truncate<'a>(&self, text: &'a str, max_tokens: usize) -> Cow<'a, str> {
+    if self.count(text) <= max_tokens {
+      return Cow::Borrowed(text);
+    }
+    let max_tokens = max_tokens.saturating_sub(self.count(TRUNCATION_MARKER));
+
+    // Longest prefix, in characters, that still fits
+    let boundaries = text
+      .char_indices()
+      .map(|(index, _)| index)
+      .chain([text.len()])
+      .collect::<Vec<_>>();
+    let fitting = boundaries.partition_point(|&end| self.count(&text[..end]) <= max_tokens);
+    let end = boundaries[fitting.saturating_sub(1)];
+    Cow::Owned(format!("{}{TRUNCATION_MARKER}", text[..end].trim_end()))
+  }
+
+  /// Group consecutive items of the given token `costs` into ranges of at most
+  /// [`TokenBudget::available`] tokens.
+  ///
+  /// Every range holds at least one item, so an item larger than the budget gets
+  /// a range of its own; callers truncate such items first.
+  #[must_use]
+  pub fn chunks(&self, costs: &[usize]) -> Vec<Range<usize>> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut used = 0;
+    for (index, &cost) in costs.iter().enumerate() {
+      if index > start && used + cost > self.available {
+        chunks.push(start..index);
+        start = index;
+        used = 0;
+      }
+      used += cost;
+    }
+    if start < costs.len() {
+      chunks.push(start..costs.len());
+    }
+    chunks
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn budget(context_tokens: usize) -> TokenBudget {
+    TokenBudget::new(context_tokens, Arc::new(EstimatedTokenCounter))
+  }
+
+  #[test]
+  fn estimates_and_reserves_tokens() {
+    assert_eq!(EstimatedTokenCounter.count_tokens("abcdefg"), 3);
+    assert_eq!(EstimatedTokenCounter.count_tokens("東京へ"), 3);
+
+    let small = budget(400);
+    assert_eq!(small.available(), 300);
+    let small = small.reserve(&"x".repeat(30));
+    assert_eq!(small.available(), 290);
+    assert_eq!(small.context_tokens(), 400);
+    assert_eq!(budget(100_000).available(), 100_000 - 4096);
+  }
+
+  #[test]
+  fn truncates_and_chunks_deterministically() {
+    let budget = budget(40);
+    assert_eq!(budget.truncate("short", 10), "short");
+    let cut = budget.truncate("one two three four five six seven", 6);
+    assert_eq!(cut, "one two thre [...]");
+    assert!(budget.count(&cut) <= 6);
+    assert_eq!(budget.truncate("日本語のテキスト", 4), "日本 [...]");
+
+    // 30 tokens available
+    assert_eq!(
+      budget.chunks(&[10, 15, 10, 40, 5]),
+      vec![0..2, 2..3, 3..4, 4..5]
+    );
+    assert!(budget.chunks(&[]).is_empty());
+  }
+}
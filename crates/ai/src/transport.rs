@@ -0,0 +1,554 @@
+use std::{
+  fmt,
+  sync::{Arc, Mutex, PoisonError},
+  time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+use chrono::{DateTime, Utc};
+use plastmem_shared::{APP_ENV, AppError};
+use reqwest::{
+  StatusCode,
+  header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue, RETRY_AFTER},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::{
+  sync::{Semaphore, SemaphorePermit},
+  time::{Instant, sleep, sleep_until},
+};
+
+const REQUEST_MAX_ATTEMPTS: usize = 4;
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// Longest `Retry-After` honored; servers asking for more get this instead.
+const RETRY_AFTER_MAX: Duration = Duration::from_mins(2);
+/// Rough bytes-per-token ratio used to charge requests against `tokens_per_minute`.
+const BYTES_PER_TOKEN: usize = 4;
+
+/// Process-wide limits for outgoing AI requests. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestLimits {
+  pub max_in_flight: Option<usize>,
+  pub requests_per_minute: Option<u32>,
+  pub tokens_per_minute: Option<u32>,
+}
+
+impl RequestLimits {
+  /// Read `AI_MAX_IN_FLIGHT`, `AI_REQUESTS_PER_MINUTE` and `AI_TOKENS_PER_MINUTE`.
+  #[must_use]
+  pub fn from_env() -> Self {
+    Self {
+      max_in_flight: (APP_ENV.ai_max_in_flight > 0).then_some(APP_ENV.ai_max_in_flight),
+      requests_per_minute: (APP_ENV.ai_requests_per_minute > 0)
+        .then_some(APP_ENV.ai_requests_per_minute),
+      tokens_per_minute: (APP_ENV.ai_tokens_per_minute > 0).then_some(APP_ENV.ai_tokens_per_minute),
+    }
+  }
+}
+
+/// HTTP client and request limiter shared by every OpenAI-compatible backend.
+///
+/// Clones share one connection pool and one limiter, so all per-task backends
+/// built by `AiClient::from_env` draw from the same budget. Rate-limited and
+/// failed requests are retried with jittered exponential backoff, or after the
+/// server's `Retry-After`, which also holds back every other request.
+#[derive(Clone)]
+pub struct HttpTransport {
+  client: reqwest::Client,
+  limiter: Arc<RequestLimiter>,
+}
+
+impl Default for HttpTransport {
+  fn default() -> Self {
+    Self::new(RequestLimits::default())
+  }
+}
+
+impl HttpTransport {
+  #[must_use]
+  pub fn new(limits: RequestLimits) -> Self {
+    Self {
+      client: reqwest::Client::new(),
+      limiter: Arc::new(RequestLimiter::new(limits)),
+    }
+  }
+
+  /// POST `body` as JSON to `url` with bearer auth and decode the JSON response.
+  pub(crate) async fn post_json<B, R>(
+    &self,
+    url: &str,
+    api_key: &str,
+    body: &B,
+    timeout: Duration,
+  ) -> Result<R, AppError>
+  where
+    B: Serialize + Sync,
+    R: DeserializeOwned,
+  {
+    let mut headers = HeaderMap::new();
+    headers.insert(AUTHORIZATION, header_value(&format!("Bearer {api_key}"))?);
+    self
+      .post_json_with_headers(url, &headers, body, timeout)
+      .await
+  }
+
+  /// POST `body` as JSON to `url` with extra `headers` (e.g. auth) and decode the JSON response.
+  pub(crate) async fn post_json_with_headers<B, R>(
+    &self,
+    url: &str,
+    headers: &HeaderMap,
+    body: &B,
+    timeout: Duration,
+  ) -> Result<R, AppError>
+  where
+    B: Serialize + Sync,
+    R: DeserializeOwned,
+  {
+    let body = serde_json::to_vec(body)?;
+    let estimated_tokens = u32::try_from(body.len() / BYTES_PER_TOKEN).unwrap_or(u32::MAX);
+    let mut attempt = 0;
+
+    loop {
+      let result = {
+        let _permit = self.limiter.acquire(estimated_tokens).await;
+        self.send(url, headers, body.clone(), timeout).await
+      };
+
+      let err = match result {
+        Ok(bytes) => {
+          return serde_json::from_slice(&bytes)
+            .with_context(|| {
+              format!(
+                "invalid response from {url}: {}",
+                String::from_utf8_lossy(&bytes)
+              )
+            })
+            .map_err(AppError::new);
+        }
+        Err(err) => err,
+      };
+
+      if attempt + 1 >= REQUEST_MAX_ATTEMPTS || !err.retryable {
+        tracing::error!(url, attempt, error = %err.error, "AI request failed");
+        return Err(if err.retryable {
+          AppError::new(RetryableError(err.error))
+        } else {
+          AppError::new(err.error)
+        });
+      }
+
+      // The server's Retry-After holds back every caller, not just this one
+      if let Some(retry_after) = err.retry_after {
+        self.limiter.pause_for(retry_after);
+      }
+      let delay = err
+        .retry_after
+        .unwrap_or_else(|| backoff_delay(attempt, rand::random()));
+      tracing::warn!(
+        url,
+        attempt,
+        delay_ms = delay.as_millis(),
+        error = %err.error,
+        "AI request failed, retrying"
+      );
+      sleep(delay).await;
+      attempt += 1;
+    }
+  }
+
+  async fn send(
+    &self,
+    url: &str,
+    headers: &HeaderMap,
+    body: Vec<u8>,
+    timeout: Duration,
+  ) -> Result<Vec<u8>, RequestError> {
+    let response = self
+      .client
+      .post(url)
+      .headers(headers.clone())
+      .header(CONTENT_TYPE, "application/json")
+      .body(body)
+      .timeout(timeout)
+      .send()
+      .await
+      .map_err(RequestError::from_reqwest)?;
+
+    let status = response.status();
+    if status.is_success() {
+      let bytes = response.bytes().await.map_err(RequestError::from_reqwest)?;
+      return Ok(bytes.to_vec());
+    }
+
+    let retry_after = parse_retry_after(response.headers(), Utc::now());
+    let text = response.text().await.unwrap_or_default();
+    Err(RequestError {
+      error: anyhow!("{url} returned {status}: {}", error_message(&text)),
+      retryable: status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+      retry_after,
+    })
+  }
+}
+
+/// Header value from configuration, e.g. an API key.
+pub fn header_value(value: &str) -> Result<HeaderValue, AppError> {
+  let mut value =
+    HeaderValue::from_str(value).map_err(|_| anyhow!("invalid characters in AI request header"))?;
+  value.set_sensitive(true);
+  Ok(value)
+}
+
+/// A request that failed in a way a retry may fix: a transport error, a timeout,
+/// `429 Too Many Requests` or a `5xx` response, still failing after the
+/// transport's own retries.
+///
+/// Only these failures count towards opening a [`CircuitBreaker`](crate::CircuitBreaker).
+/// Custom providers can wrap their own transient failures in it.
+#[derive(Debug)]
+pub struct RetryableError(pub anyhow::Error);
+
+impl fmt::Display for RetryableError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+impl std::error::Error for RetryableError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    self.0.source()
+  }
+}
+
+struct RequestError {
+  error: anyhow::Error,
+  retryable: bool,
+  retry_after: Option<Duration>,
+}
+
+impl RequestError {
+  fn from_reqwest(err: reqwest::Error) -> Self {
+    Self {
+      retryable: err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+      error: err.into(),
+      retry_after: None,
+    }
+  }
+}
+
+/// Pull `error.message` (`OpenAI`, Messages) or a string `error` (Ollama) out of an
+/// error body, or return the body as is.
+fn error_message(body: &str) -> String {
+  serde_json::from_str::<serde_json::Value>(body)
+    .ok()
+    .and_then(|value| {
+      value["error"]["message"]
+        .as_str()
+        .or_else(|| value["error"].as_str())
+        .map(str::to_owned)
+    })
+    .unwrap_or_else(|| body.trim().to_owned())
+}
+
+/// Read `retry-after-ms` or `Retry-After` (seconds or HTTP date), capped at `RETRY_AFTER_MAX`.
+fn parse_retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+  let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+  let delay = if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok())
+  {
+    Duration::try_from_secs_f64(ms / 1000.0).ok()?
+  } else {
+    let value = header(RETRY_AFTER.as_str())?.trim();
+    match value.parse::<f64>() {
+      Ok(seconds) => Duration::try_from_secs_f64(seconds).ok()?,
+      Err(_) => (DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc)
+        - now)
+        .to_std()
+        .unwrap_or(Duration::ZERO),
+    }
+  };
+
+  Some(delay.min(RETRY_AFTER_MAX))
+}
+
+/// Exponential backoff with equal jitter: half the step is fixed, half is scaled by `jitter` (0..1).
+fn backoff_delay(attempt: usize, jitter: f64) -> Duration {
+  let step = BACKOFF_BASE
+    .saturating_mul(1 << attempt.min(16))
+    .min(BACKOFF_MAX);
+  step / 2 + (step / 2).mul_f64(jitter.clamp(0.0, 1.0))
+}
+
+struct RequestLimiter {
+  in_flight: Option<Semaphore>,
+  requests: Option<Mutex<TokenBucket>>,
+  tokens: Option<Mutex<TokenBucket>>,
+  paused_until: Mutex<Option<Instant>>,
+}
+
+impl RequestLimiter {
+  fn new(limits: RequestLimits) -> Self {
+    let now = Instant::now();
+    Self {
+      in_flight: limits.max_in_flight.map(Semaphore::new),
+      requests: limits
+        .requests_per_minute
+        .map(|limit| Mutex::new(TokenBucket::per_minute(limit, now))),
+      tokens: limits
+        .tokens_per_minute
+        .map(|limit| Mutex::new(TokenBucket::per_minute(limit, now))),
+      paused_until: Mutex::new(None),
+    }
+  }
+
+  /// Wait until a request costing `estimated_tokens` may start.
+  ///
+  /// The returned permit counts the request as in flight until dropped.
+  async fn acquire(&self, estimated_tokens: u32) -> Option<SemaphorePermit<'_>> {
+    let paused_until = *self
+      .paused_until
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    if let Some(until) = paused_until {
+      sleep_until(until).await;
+    }
+    if let Some(bucket) = &self.requests {
+      take(bucket, 1.0).await;
+    }
+    if let Some(bucket) = &self.tokens {
+      take(bucket, f64::from(estimated_tokens)).await;
+    }
+    self.in_flight.as_ref()?.acquire().await.ok()
+  }
+
+  /// Hold back every request for `delay`, e.g. after a `Retry-After`.
+  fn pause_for(&self, delay: Duration) {
+    let until = Instant::now() + delay;
+    let mut paused_until = self
+      .paused_until
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    if paused_until.is_none_or(|current| current < until) {
+      *paused_until = Some(until);
+    }
+  }
+}
+
+async fn take(bucket: &Mutex<TokenBucket>, cost: f64) {
+  loop {
+    let wait = bucket
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .try_take(cost, Instant::now());
+    match wait {
+      Some(wait) => sleep(wait).await,
+      None => return,
+    }
+  }
+}
+
+/// Token bucket refilled continuously at `capacity` per minute.
+struct TokenBucket {
+  capacity: f64,
+  available: f64,
+  refill_per_sec: f64,
+  updated_at: Instant,
+}
+
+impl TokenBucket {
+  fn per_minute(limit: u32, now: Instant) -> Self {
+    let capacity = f64::from(limit.max(1));
+    Self {
+      capacity,
+      available: capacity,
+      refill_per_sec: capacity / 60.0,
+      updated_at: now,
+    }
+  }
+
+  /// Take `cost` units, or return how long to wait before trying again.
+  ///
+  /// Costs above the capacity are clamped so oversized requests still run.
+  fn try_take(&mut self, cost: f64, now: Instant) -> Option<Duration> {
+    let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+    self.available = elapsed
+      .mul_add(self.refill_per_sec, self.available)
+      .min(self.capacity);
+    self.updated_at = now;
+
+    let cost = cost.min(self.capacity);
+    if self.available >= cost {
+      self.available -= cost;
+      None
+    } else {
+      Some(Duration::from_secs_f64(
+        (cost - self.available) / self.refill_per_sec,
+      ))
+    }
+  }
+}
+
+/// Local HTTP stub for backend tests.
+#[cfg(test)]
+pub mod test_server {
+  use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+  };
+
+  /// Serve raw HTTP `responses` in order, one per connection.
+  ///
+  /// Returns the base URL and a channel yielding each raw request as received.
+  pub async fn stub_server(responses: Vec<String>) -> (String, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (requests_tx, requests_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+      for response in responses {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let _ = requests_tx.send(read_request(&mut socket).await);
+        socket.write_all(response.as_bytes()).await.unwrap();
+        socket.shutdown().await.unwrap();
+      }
+    });
+    (format!("http://{addr}"), requests_rx)
+  }
+
+  /// Read one request, headers and body.
+  async fn read_request(socket: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+      let read = socket.read(&mut buf).await.unwrap();
+      request.extend_from_slice(&buf[..read]);
+      let text = String::from_utf8_lossy(&request);
+      let complete = text.split_once("\r\n\r\n").is_some_and(|(head, body)| {
+        let content_length = head
+          .lines()
+          .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name
+              .eq_ignore_ascii_case("content-length")
+              .then(|| value.trim().parse::<usize>().ok())
+              .flatten()
+          })
+          .unwrap_or(0);
+        body.len() >= content_length
+      });
+      if read == 0 || complete {
+        return text.into_owned();
+      }
+    }
+  }
+
+  /// A `200 OK` response carrying `body` as JSON.
+  pub fn json_response(body: &serde_json::Value) -> String {
+    let body = body.to_string();
+    format!(
+      "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+      body.len()
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{test_server::stub_server, *};
+
+  #[test]
+  fn token_bucket_waits_for_refill() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::per_minute(60, start);
+
+    assert_eq!(bucket.try_take(60.0, start), None);
+    assert_eq!(bucket.try_take(2.0, start), Some(Duration::from_secs(2)));
+    assert_eq!(bucket.try_take(2.0, start + Duration::from_secs(2)), None);
+    // Oversized costs wait for a full bucket instead of forever
+    assert_eq!(
+      bucket.try_take(1000.0, start + Duration::from_secs(2)),
+      Some(Duration::from_mins(1))
+    );
+  }
+
+  #[test]
+  fn parses_retry_after_headers() {
+    let now = Utc::now();
+    let headers = |name: &'static str, value: &str| {
+      let mut headers = HeaderMap::new();
+      headers.insert(name, HeaderValue::from_str(value).unwrap());
+      headers
+    };
+
+    assert_eq!(
+      parse_retry_after(&headers("retry-after", "3"), now),
+      Some(Duration::from_secs(3))
+    );
+    assert_eq!(
+      parse_retry_after(&headers("retry-after-ms", "250"), now),
+      Some(Duration::from_millis(250))
+    );
+    assert_eq!(
+      parse_retry_after(&headers("retry-after", "86400"), now),
+      Some(RETRY_AFTER_MAX)
+    );
+    let date = (now + chrono::Duration::seconds(10)).to_rfc2822();
+    let parsed = parse_retry_after(&headers("retry-after", &date), now).unwrap();
+    assert!(parsed > Duration::from_secs(8) && parsed <= Duration::from_secs(10));
+    assert_eq!(parse_retry_after(&HeaderMap::new(), now), None);
+  }
+
+  #[test]
+  fn backoff_is_jittered_and_capped() {
+    assert_eq!(backoff_delay(0, 0.0), Duration::from_millis(250));
+    assert_eq!(backoff_delay(0, 1.0), Duration::from_millis(500));
+    assert_eq!(backoff_delay(2, 0.5), Duration::from_millis(1500));
+    assert_eq!(backoff_delay(20, 1.0), BACKOFF_MAX);
+  }
+
+  async fn serve(responses: Vec<&'static str>) -> String {
+    let (base_url, _requests) =
+      stub_server(responses.into_iter().map(str::to_owned).collect()).await;
+    format!("{base_url}/v1/embeddings")
+  }
+
+  #[tokio::test]
+  async fn retries_rate_limited_requests() {
+    let url = serve(vec![
+      "HTTP/1.1 429 Too Many Requests\r\nretry-after-ms: 10\r\ncontent-length: 40\r\nconnection: close\r\n\r\n{\"error\":{\"message\":\"slow down please\"}}",
+      "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 11\r\nconnection: close\r\n\r\n{\"ok\":true}",
+    ])
+    .await;
+
+    let transport = HttpTransport::new(RequestLimits {
+      max_in_flight: Some(1),
+      ..RequestLimits::default()
+    });
+    let response: serde_json::Value = transport
+      .post_json(&url, "key", &serde_json::json!({}), Duration::from_secs(5))
+      .await
+      .unwrap();
+    assert_eq!(response, serde_json::json!({ "ok": true }));
+  }
+
+  #[tokio::test]
+  async fn does_not_retry_client_errors() {
+    let url = serve(vec![
+      "HTTP/1.1 400 Bad Request\r\ncontent-length: 33\r\nconnection: close\r\n\r\n{\"error\":{\"message\":\"bad input\"}}",
+    ])
+    .await;
+
+    let err = HttpTransport::default()
+      .post_json::<_, serde_json::Value>(
+        &url,
+        "key",
+        &serde_json::json!({}),
+        Duration::from_secs(5),
+      )
+      .await
+      .unwrap_err();
+    assert!(err.to_string().contains("bad input"));
+    assert!(err.downcast_ref::<RetryableError>().is_none());
+  }
+}
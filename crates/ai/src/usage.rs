@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+
+use crate::{AiClient, CallContext, TokenUsage};
+
+/// Stage name recorded for embedding requests.
+pub const EMBEDDING_STAGE: &str = "embedding";
+
+/// Tokens spent by one AI request, attributed through its [`CallContext`].
+#[derive(Debug, Clone)]
+pub struct UsageRecord {
+  pub context: CallContext,
+  /// Chat task name (see [`crate::ChatTask::name`]), or [`EMBEDDING_STAGE`].
+  pub stage: &'static str,
+  pub model: String,
+  pub usage: TokenUsage,
+}
+
+/// Receives the token usage of every chat and embedding request made through an [`AiClient`].
+///
+/// Recording must never fail the request: implementations log and drop their own errors.
+#[async_trait]
+pub trait UsageSink: Send + Sync {
+  async fn record_usage(&self, record: UsageRecord);
+}
+
+/// Record billed embedding tokens with the client's [`UsageSink`], if any.
+pub async fn record_embedding_usage(usage: Option<TokenUsage>, ai: &AiClient) {
+  let (Some(sink), Some(usage)) = (ai.usage_sink(), usage) else {
+    return;
+  };
+  sink
+    .record_usage(UsageRecord {
+      context: ai.call_context(),
+      stage: EMBEDDING_STAGE,
+      model: ai.embedding().model().to_owned(),
+      usage,
+    })
+    .await;
+}
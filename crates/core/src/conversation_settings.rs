@@ -0,0 +1,66 @@
+use chrono::Utc;
+use plastmem_entities::conversation_settings;
+use plastmem_shared::{AppError, Language};
+use sea_orm::{DatabaseConnection, EntityTrait, Set, sea_query::OnConflict};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Per-conversation settings. Conversations without stored settings use the server defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ConversationSettings {
+  pub conversation_id: Uuid,
+  /// Language that episode titles, time anchors and facts are written in.
+  pub language: Language,
+  /// Output template that retrieval results are rendered with; the default when unset.
+  #[serde(default)]
+  pub output_template: Option<String>,
+}
+
+impl ConversationSettings {
+  pub fn from_model(model: conversation_settings::Model) -> Result<Self, AppError> {
+    Ok(Self {
+      conversation_id: model.conversation_id,
+      language: model.language.parse()?,
+      output_template: model.output_template,
+    })
+  }
+}
+
+pub async fn get_conversation_settings(
+  conversation_id: Uuid,
+  db: &DatabaseConnection,
+) -> Result<Option<ConversationSettings>, AppError> {
+  conversation_settings::Entity::find_by_id(conversation_id)
+    .one(db)
+    .await?
+    .map(ConversationSettings::from_model)
+    .transpose()
+}
+
+/// Store `settings`, replacing any previous settings of the conversation.
+///
+/// Only memories created afterwards follow the new settings.
+pub async fn set_conversation_settings(
+  settings: &ConversationSettings,
+  db: &DatabaseConnection,
+) -> Result<(), AppError> {
+  conversation_settings::Entity::insert(conversation_settings::ActiveModel {
+    conversation_id: Set(settings.conversation_id),
+    language: Set(settings.language.code().to_owned()),
+    output_template: Set(settings.output_template.clone()),
+    updated_at: Set(Utc::now().into()),
+  })
+  .on_conflict(
+    OnConflict::column(conversation_settings::Column::ConversationId)
+      .update_columns([
+        conversation_settings::Column::Language,
+        conversation_settings::Column::OutputTemplate,
+        conversation_settings::Column::UpdatedAt,
+      ])
+      .to_owned(),
+  )
+  .exec_without_returning(db)
+  .await?;
+  Ok(())
+}
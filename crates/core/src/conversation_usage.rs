@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Months, NaiveDate, NaiveTime, Utc};
+use plastmem_ai::{TokenUsage, UsageRecord, UsageSink};
+use plastmem_entities::conversation_usage;
+use plastmem_shared::AppError;
+use sea_orm::{
+  ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult,
+  QueryFilter, QueryOrder, Statement,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Price of one model in USD per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelPrice {
+  pub prompt: f64,
+  pub completion: f64,
+}
+
+/// Per-model prices used to turn token usage into cost. Unlisted models cost nothing.
+#[derive(Debug, Clone, Default)]
+pub struct ModelPrices(HashMap<String, ModelPrice>);
+
+impl ModelPrices {
+  /// Parse `model=prompt[/completion],...`, prices in USD per million tokens.
+  ///
+  /// A single price applies to both prompt and completion tokens.
+  pub fn parse(spec: &str) -> Result<Self, AppError> {
+    let mut prices = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+      let (model, price) = entry
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("invalid model price `{entry}`: expected model=price"))?;
+      let parse = |value: &str| {
+        value
+          .trim()
+          .parse::<f64>()
+          .ok()
+          .filter(|price| price.is_finite() && *price >= 0.0)
+          .ok_or_else(|| anyhow!("invalid price `{value}` for model `{model}`"))
+      };
+      let price = match price.split_once('/') {
+        Some((prompt, completion)) => ModelPrice {
+          prompt: parse(prompt)?,
+          completion: parse(completion)?,
+        },
+        None => {
+          let price = parse(price)?;
+          ModelPrice {
+            prompt: price,
+            completion: price,
+          }
+        }
+      };
+      prices.insert(model.trim().to_owned(), price);
+    }
+    Ok(Self(prices))
+  }
+
+  #[must_use]
+  pub fn cost_usd(&self, model: &str, usage: TokenUsage) -> f64 {
+    self.0.get(model).map_or(0.0, |price| {
+      (f64::from(usage.prompt_tokens) * price.prompt
+        + f64::from(usage.completion_tokens) * price.completion)
+        / 1_000_000.0
+    })
+  }
+}
+
+/// Aggregates token usage per conversation, UTC day, stage and model into `conversation_usage`.
+///
+/// Requests made outside a conversation are not recorded. Write failures are
+/// logged and never fail the request being recorded.
+pub struct UsageLedger {
+  db: DatabaseConnection,
+  prices: ModelPrices,
+}
+
+impl UsageLedger {
+  #[must_use]
+  pub const fn new(db: DatabaseConnection, prices: ModelPrices) -> Self {
+    Self { db, prices }
+  }
+}
+
+#[async_trait]
+impl UsageSink for UsageLedger {
+  async fn record_usage(&self, record: UsageRecord) {
+    let Some(conversation_id) = record.context.conversation_id else {
+      return;
+    };
+    let cost_usd = self.prices.cost_usd(&record.model, record.usage);
+
+    let result = self
+      .db
+      .execute_raw(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r"
+        INSERT INTO conversation_usage
+          (conversation_id, usage_date, stage, model, prompt_tokens, completion_tokens, requests, cost_usd)
+        VALUES ($1, $2, $3, $4, $5, $6, 1, $7)
+        ON CONFLICT (conversation_id, usage_date, stage, model) DO UPDATE SET
+          prompt_tokens = conversation_usage.prompt_tokens + EXCLUDED.prompt_tokens,
+          completion_tokens = conversation_usage.completion_tokens + EXCLUDED.completion_tokens,
+          requests = conversation_usage.requests + 1,
+          cost_usd = conversation_usage.cost_usd + EXCLUDED.cost_usd,
+          updated_at = now();
+        ",
+        [
+          conversation_id.into(),
+          Utc::now().date_naive().into(),
+          record.stage.into(),
+          record.model.into(),
+          i64::from(record.usage.prompt_tokens).into(),
+          i64::from(record.usage.completion_tokens).into(),
+          cost_usd.into(),
+        ],
+      ))
+      .await;
+
+    if let Err(err) = result {
+      tracing::warn!(error = %err, %conversation_id, "failed to record token usage");
+    }
+  }
+}
+
+/// Per-conversation spending caps. `None` leaves that cap unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageBudget {
+  pub daily_tokens: Option<u64>,
+  pub monthly_tokens: Option<u64>,
+  pub daily_cost_usd: Option<f64>,
+  pub monthly_cost_usd: Option<f64>,
+}
+
+impl UsageBudget {
+  #[must_use]
+  pub const fn is_unlimited(&self) -> bool {
+    self.daily_tokens.is_none()
+      && self.monthly_tokens.is_none()
+      && self.daily_cost_usd.is_none()
+      && self.monthly_cost_usd.is_none()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetPeriod {
+  Daily,
+  Monthly,
+}
+
+/// A conversation over one of its [`UsageBudget`] caps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded {
+  pub period: BudgetPeriod,
+  /// Start of the next period (UTC), when the cap no longer applies.
+  pub resets_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, FromQueryResult)]
+struct UsageTotals {
+  daily_tokens: i64,
+  daily_cost_usd: f64,
+  monthly_tokens: i64,
+  monthly_cost_usd: f64,
+}
+
+/// Check a conversation's usage for the current UTC day and month against `budget`.
+pub async fn check_usage_budget(
+  conversation_id: Uuid,
+  budget: &UsageBudget,
+  db: &DatabaseConnection,
+) -> Result<Option<BudgetExceeded>, AppError> {
+  if budget.is_unlimited() {
+    return Ok(None);
+  }
+
+  let today = Utc::now().date_naive();
+  let totals = UsageTotals::find_by_statement(Statement::from_sql_and_values(
+    DbBackend::Postgres,
+    r"
+    SELECT
+      COALESCE(SUM(prompt_tokens + completion_tokens) FILTER (WHERE usage_date = $2), 0)::bigint AS daily_tokens,
+      COALESCE(SUM(cost_usd) FILTER (WHERE usage_date = $2), 0)::float8 AS daily_cost_usd,
+      COALESCE(SUM(prompt_tokens + completion_tokens), 0)::bigint AS monthly_tokens,
+      COALESCE(SUM(cost_usd), 0)::float8 AS monthly_cost_usd
+    FROM conversation_usage
+    WHERE conversation_id = $1 AND usage_date >= $3
+    ",
+    [
+      conversation_id.into(),
+      today.into(),
+      month_start(today).into(),
+    ],
+  ))
+  .one(db)
+  .await?
+  .unwrap_or_default();
+
+  Ok(exceeded_budget(&totals, budget, today))
+}
+
+fn month_start(day: NaiveDate) -> NaiveDate {
+  day.with_day(1).unwrap_or(day)
+}
+
+fn exceeded_budget(
+  totals: &UsageTotals,
+  budget: &UsageBudget,
+  today: NaiveDate,
+) -> Option<BudgetExceeded> {
+  let over_tokens = |used: i64, cap: Option<u64>| cap.is_some_and(|cap| used.unsigned_abs() >= cap);
+  let over_cost = |used: f64, cap: Option<f64>| cap.is_some_and(|cap| used >= cap);
+
+  // The monthly cap outlasts the daily one, so report it first
+  let period = if over_tokens(totals.monthly_tokens, budget.monthly_tokens)
+    || over_cost(totals.monthly_cost_usd, budget.monthly_cost_usd)
+  {
+    BudgetPeriod::Monthly
+  } else if over_tokens(totals.daily_tokens, budget.daily_tokens)
+    || over_cost(totals.daily_cost_usd, budget.daily_cost_usd)
+  {
+    BudgetPeriod::Daily
+  } else {
+    return None;
+  };
+
+  let next_period = match period {
+    BudgetPeriod::Daily => today.succ_opt(),
+    BudgetPeriod::Monthly => month_start(today).checked_add_months(Months::new(1)),
+  }?;
+  Some(BudgetExceeded {
+    period,
+    resets_at: next_period.and_time(NaiveTime::MIN).and_utc(),
+  })
+}
+
+/// Aggregated token usage for one conversation, UTC day, stage and model.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConversationUsage {
+  pub conversation_id: Uuid,
+  pub usage_date: NaiveDate,
+  /// Pipeline stage, e.g. `segmentation_classify` or `embedding`.
+  pub stage: String,
+  pub model: String,
+  pub prompt_tokens: i64,
+  pub completion_tokens: i64,
+  pub requests: i64,
+  /// Cost from `AI_MODEL_PRICES`; zero for unpriced models.
+  pub cost_usd: f64,
+}
+
+impl ConversationUsage {
+  #[must_use]
+  pub fn from_model(model: conversation_usage::Model) -> Self {
+    Self {
+      conversation_id: model.conversation_id,
+      usage_date: model.usage_date,
+      stage: model.stage,
+      model: model.model,
+      prompt_tokens: model.prompt_tokens,
+      completion_tokens: model.completion_tokens,
+      requests: model.requests,
+      cost_usd: model.cost_usd,
+    }
+  }
+}
+
+/// Usage rows for a conversation from `since` (UTC day) on, newest day first.
+pub async fn list_conversation_usage(
+  conversation_id: Uuid,
+  since: Option<NaiveDate>,
+  db: &DatabaseConnection,
+) -> Result<Vec<ConversationUsage>, AppError> {
+  let mut query = conversation_usage::Entity::find()
+    .filter(conversation_usage::Column::ConversationId.eq(conversation_id));
+  if let Some(since) = since {
+    query = query.filter(conversation_usage::Column::UsageDate.gte(since));
+  }
+
+  let models = query
+    .order_by_desc(conversation_usage::Column::UsageDate)
+    .order_by_asc(conversation_usage::Column::Stage)
+    .order_by_asc(conversation_usage::Column::Model)
+    .all(db)
+    .await?;
+
+  Ok(
+    models
+      .into_iter()
+      .map(ConversationUsage::from_model)
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn prices_tokens_per_million() {
+    let prices = ModelPrices::parse("gpt-4o-mini=0.15/0.6, text-embedding-3-small=0.02").unwrap();
+    let usage = TokenUsage {
+      prompt_tokens: 2_000_000,
+      completion_tokens: 1_000_000,
+    };
+    assert!((prices.cost_usd("gpt-4o-mini", usage) - 0.9).abs() < 1e-9);
+    assert!((prices.cost_usd("text-embedding-3-small", usage) - 0.06).abs() < 1e-9);
+    assert!(prices.cost_usd("unknown", usage).abs() < f64::EPSILON);
+    assert!(ModelPrices::parse("gpt-4o-mini").is_err());
+  }
+
+  #[test]
+  fn reports_the_longest_exceeded_period() {
+    let today = NaiveDate::from_ymd_opt(2026, 1, 15).unwrap();
+    let budget = UsageBudget {
+      daily_tokens: Some(1_000),
+      monthly_cost_usd: Some(5.0),
+      ..UsageBudget::default()
+    };
+    let mut totals = UsageTotals {
+      daily_tokens: 999,
+      monthly_cost_usd: 4.0,
+      ..UsageTotals::default()
+    };
+    assert_eq!(exceeded_budget(&totals, &budget, today), None);
+
+    totals.daily_tokens = 1_000;
+    let daily = exceeded_budget(&totals, &budget, today).unwrap();
+    assert_eq!(daily.period, BudgetPeriod::Daily);
+    assert_eq!(daily.resets_at.to_rfc3339(), "2026-01-16T00:00:00+00:00");
+
+    totals.monthly_cost_usd = 5.0;
+    let monthly = exceeded_budget(&totals, &budget, today).unwrap();
+    assert_eq!(monthly.period, BudgetPeriod::Monthly);
+    assert_eq!(monthly.resets_at.to_rfc3339(), "2026-02-01T00:00:00+00:00");
+  }
+}
@@ -0,0 +1,380 @@
+use std::{
+  collections::HashMap,
+  sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+  },
+};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use plastmem_ai::{EmbeddingProvider, EmbeddingResponse};
+use plastmem_entities::embedding_cache;
+use plastmem_shared::AppError;
+use sea_orm::{
+  ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, QueryFilter, Set,
+  Statement, prelude::PgVector, sea_query::Expr, sea_query::OnConflict,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Run LRU eviction after this many inserts.
+const EVICTION_INTERVAL: u64 = 256;
+
+/// Postgres-backed cache in front of an embedding backend.
+///
+/// Entries are keyed by (embedding model, dimension, sha256(text)) and evicted
+/// least-recently-used once the table grows past `max_entries`. Cache failures are
+/// logged and fall through to the backend; they never fail an embedding call.
+pub struct EmbeddingCache {
+  inner: Arc<dyn EmbeddingProvider>,
+  db: DatabaseConnection,
+  dim: i32,
+  max_entries: u64,
+  hits: AtomicU64,
+  misses: AtomicU64,
+  inserts_since_eviction: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EmbeddingCacheStats {
+  pub hits: u64,
+  pub misses: u64,
+}
+
+impl EmbeddingCache {
+  #[must_use]
+  pub fn new(
+    inner: Arc<dyn EmbeddingProvider>,
+    db: DatabaseConnection,
+    dim: usize,
+    max_entries: u64,
+  ) -> Self {
+    Self {
+      inner,
+      db,
+      dim: i32::try_from(dim).unwrap_or(i32::MAX),
+      max_entries,
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+      inserts_since_eviction: AtomicU64::new(0),
+    }
+  }
+
+  /// Hit/miss counters since startup, counted per input text.
+  pub fn stats(&self) -> EmbeddingCacheStats {
+    EmbeddingCacheStats {
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+    }
+  }
+
+  /// Load cached vectors for `hashes` and mark them as recently used.
+  async fn lookup(&self, hashes: Vec<String>) -> Result<HashMap<String, Vec<f32>>, AppError> {
+    let models = embedding_cache::Entity::find()
+      .filter(embedding_cache::Column::Model.eq(self.inner.model()))
+      .filter(embedding_cache::Column::Dim.eq(self.dim))
+      .filter(embedding_cache::Column::ContentHash.is_in(hashes))
+      .all(&self.db)
+      .await?;
+
+    if !models.is_empty() {
+      let found: Vec<String> = models.iter().map(|m| m.content_hash.clone()).collect();
+      embedding_cache::Entity::update_many()
+        .col_expr(embedding_cache::Column::LastUsedAt, Expr::value(Utc::now()))
+        .filter(embedding_cache::Column::Model.eq(self.inner.model()))
+        .filter(embedding_cache::Column::Dim.eq(self.dim))
+        .filter(embedding_cache::Column::ContentHash.is_in(found))
+        .exec(&self.db)
+        .await?;
+    }
+
+    Ok(
+      models
+        .into_iter()
+        .map(|m| (m.content_hash, m.embedding.to_vec()))
+        .collect(),
+    )
+  }
+
+  async fn store(&self, entries: &[(String, Vec<f32>)]) -> Result<(), AppError> {
+    let now = Utc::now();
+    let active_models = entries
+      .iter()
+      .map(|(hash, vector)| embedding_cache::ActiveModel {
+        model: Set(self.inner.model().to_owned()),
+        dim: Set(self.dim),
+        content_hash: Set(hash.clone()),
+        embedding: Set(PgVector::from(vector.clone())),
+        created_at: Set(now.into()),
+        last_used_at: Set(now.into()),
+      });
+
+    embedding_cache::Entity::insert_many(active_models)
+      .on_conflict(
+        OnConflict::columns([
+          embedding_cache::Column::Model,
+          embedding_cache::Column::Dim,
+          embedding_cache::Column::ContentHash,
+        ])
+        .do_nothing()
+        .to_owned(),
+      )
+      .exec_without_returning(&self.db)
+      .await?;
+
+    let inserted = entries.len() as u64;
+    if self
+      .inserts_since_eviction
+      .fetch_add(inserted, Ordering::Relaxed)
+      + inserted
+      >= EVICTION_INTERVAL
+    {
+      self.inserts_since_eviction.store(0, Ordering::Relaxed);
+      self.evict().await?;
+    }
+
+    Ok(())
+  }
+
+  /// Delete least-recently-used entries beyond `max_entries`.
+  async fn evict(&self) -> Result<(), AppError> {
+    let result = self
+      .db
+      .execute_raw(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        r"
+        DELETE FROM embedding_cache
+        WHERE (model, dim, content_hash) IN (
+          SELECT model, dim, content_hash
+          FROM embedding_cache
+          ORDER BY last_used_at DESC
+          OFFSET $1
+        );
+        ",
+        vec![self.max_entries.cast_signed().into()],
+      ))
+      .await?;
+
+    let stats = self.stats();
+    tracing::info!(
+      evicted = result.rows_affected(),
+      hits = stats.hits,
+      misses = stats.misses,
+      "embedding cache eviction"
+    );
+    Ok(())
+  }
+}
+
+fn content_hash(text: &str) -> String {
+  format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+#[async_trait]
+impl EmbeddingProvider for EmbeddingCache {
+  fn model(&self) -> &str {
+    self.inner.model()
+  }
+
+  async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+    if inputs.is_empty() {
+      return Ok(EmbeddingResponse::default());
+    }
+
+    let hashes: Vec<String> = inputs.iter().map(|input| content_hash(input)).collect();
+
+    let mut vectors = self.lookup(hashes.clone()).await.unwrap_or_else(|err| {
+      tracing::warn!(error = %err, "embedding cache lookup failed");
+      HashMap::new()
+    });
+
+    // Embed each missing text once, even if it repeats within the batch
+    let mut missing_hashes = Vec::new();
+    let mut missing_inputs = Vec::new();
+    for (hash, input) in hashes.iter().zip(&inputs) {
+      if !vectors.contains_key(hash) && !missing_hashes.contains(hash) {
+        missing_hashes.push(hash.clone());
+        missing_inputs.push(input.clone());
+      }
+    }
+
+    let missing = inputs.len() - hashes.iter().filter(|h| vectors.contains_key(*h)).count();
+    self
+      .hits
+      .fetch_add((inputs.len() - missing) as u64, Ordering::Relaxed);
+    self.misses.fetch_add(missing as u64, Ordering::Relaxed);
+    tracing::debug!(
+      hits = inputs.len() - missing,
+      misses = missing,
+      "embedding cache"
+    );
+
+    // Cache hits are free; only the backend request for misses is billed
+    let mut usage = None;
+    if !missing_inputs.is_empty() {
+      let embedded = self.inner.embed(missing_inputs).await?;
+      // A short response would leave later inputs paired with the wrong vectors
+      if embedded.embeddings.len() != missing_hashes.len() {
+        return Err(
+          anyhow::anyhow!(
+            "embedding backend returned {} vectors for {} inputs",
+            embedded.embeddings.len(),
+            missing_hashes.len()
+          )
+          .into(),
+        );
+      }
+      usage = embedded.usage;
+      let entries: Vec<(String, Vec<f32>)> = missing_hashes
+        .into_iter()
+        .zip(embedded.embeddings)
+        .collect();
+      if let Err(err) = self.store(&entries).await {
+        tracing::warn!(error = %err, "embedding cache store failed");
+      }
+      vectors.extend(entries);
+    }
+
+    let embeddings = hashes
+      .iter()
+      .map(|hash| {
+        vectors
+          .get(hash)
+          .cloned()
+          .ok_or_else(|| anyhow::anyhow!("embedding missing for cached input"))
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(EmbeddingResponse { embeddings, usage })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Mutex;
+
+  use sea_orm::{MockDatabase, MockExecResult};
+
+  use super::*;
+
+  /// Embeds each text as `[len]`, recording the inputs it was asked for.
+  #[derive(Default)]
+  struct LengthEmbedding {
+    inputs: Mutex<Vec<String>>,
+    short: bool,
+  }
+
+  #[async_trait]
+  impl EmbeddingProvider for LengthEmbedding {
+    fn model(&self) -> &'static str {
+      "length"
+    }
+
+    async fn embed(&self, inputs: Vec<String>) -> Result<EmbeddingResponse, AppError> {
+      self.inputs.lock().unwrap().extend(inputs.iter().cloned());
+      #[allow(clippy::cast_precision_loss)]
+      let mut embeddings = inputs
+        .iter()
+        .map(|input| vec![input.len() as f32])
+        .collect::<Vec<_>>();
+      if self.short {
+        embeddings.pop();
+      }
+      Ok(EmbeddingResponse::new(embeddings))
+    }
+  }
+
+  fn cached(text: &str, embedding: Vec<f32>) -> embedding_cache::Model {
+    let now = Utc::now();
+    embedding_cache::Model {
+      model: "length".to_owned(),
+      dim: 1,
+      content_hash: content_hash(text),
+      embedding: PgVector::from(embedding),
+      created_at: now.into(),
+      last_used_at: now.into(),
+    }
+  }
+
+  fn exec_result(rows_affected: u64) -> MockExecResult {
+    MockExecResult {
+      last_insert_id: 0,
+      rows_affected,
+    }
+  }
+
+  #[tokio::test]
+  async fn serves_hits_and_embeds_each_miss_once() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+      .append_query_results([vec![cached("cached", vec![42.0])]])
+      .append_exec_results([exec_result(1), exec_result(1)])
+      .into_connection();
+    let inner = Arc::new(LengthEmbedding::default());
+    let cache = EmbeddingCache::new(inner.clone(), db.clone(), 1, 100);
+
+    let inputs = ["cached", "new", "new"].map(str::to_owned).to_vec();
+    let response = cache.embed(inputs).await.unwrap();
+    assert_eq!(response.embeddings, [vec![42.0], vec![3.0], vec![3.0]]);
+    assert_eq!(*inner.inputs.lock().unwrap(), ["new"]);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses), (1, 2));
+
+    // Lookups, touches and inserts are all keyed by model and dimension
+    drop(cache);
+    let log = db.into_transaction_log();
+    let statements = log
+      .iter()
+      .flat_map(|transaction| transaction.statements())
+      .map(ToString::to_string)
+      .collect::<Vec<_>>();
+    assert_eq!(statements.len(), 3);
+    assert!(statements[0].starts_with("SELECT"));
+    assert!(statements[1].starts_with("UPDATE"));
+    assert!(statements[2].starts_with("INSERT"));
+    for statement in &statements {
+      assert!(statement.contains("'length'"), "{statement}");
+    }
+    assert!(statements[0].contains(r#""dim" = 1"#));
+    assert!(statements[1].contains(r#""dim" = 1"#));
+  }
+
+  #[tokio::test]
+  async fn rejects_short_backend_responses_without_storing() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+      .append_query_results([Vec::<embedding_cache::Model>::new()])
+      .into_connection();
+    let inner = Arc::new(LengthEmbedding {
+      short: true,
+      ..LengthEmbedding::default()
+    });
+    let cache = EmbeddingCache::new(inner, db.clone(), 1, 100);
+
+    let inputs = ["a", "b"].map(str::to_owned).to_vec();
+    assert!(cache.embed(inputs).await.is_err());
+
+    drop(cache);
+    let log = db.into_transaction_log();
+    assert_eq!(log.len(), 1, "only the lookup should reach the database");
+  }
+
+  #[tokio::test]
+  async fn evicts_least_recently_used_past_max_entries() {
+    let inserts = usize::try_from(EVICTION_INTERVAL).unwrap();
+    let db = MockDatabase::new(DbBackend::Postgres)
+      .append_query_results([Vec::<embedding_cache::Model>::new()])
+      .append_exec_results([exec_result(EVICTION_INTERVAL), exec_result(246)])
+      .into_connection();
+    let cache = EmbeddingCache::new(Arc::new(LengthEmbedding::default()), db.clone(), 1, 10);
+
+    let inputs = (0..inserts).map(|i| i.to_string()).collect();
+    cache.embed(inputs).await.unwrap();
+
+    drop(cache);
+    let log = db.into_transaction_log();
+    let eviction = log.last().unwrap().statements()[0].to_string();
+    assert!(eviction.contains("DELETE FROM embedding_cache"));
+    assert!(eviction.contains("ORDER BY last_used_at DESC"));
+    assert!(eviction.contains("OFFSET 10"));
+  }
+}
@@ -0,0 +1,45 @@
+use anyhow::anyhow;
+use plastmem_shared::AppError;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+
+/// Tables whose `embedding` column is sized by `EMBEDDING_DIM`.
+const EMBEDDING_TABLES: [&str; 2] = ["episodic_memory", "semantic_memory"];
+
+/// Check that the `embedding` columns already in the database match `dim`.
+///
+/// The columns are created once with the dimension configured at the time;
+/// changing `EMBEDDING_DIM` afterwards would make every insert and search fail.
+pub async fn verify_embedding_dim(db: &DatabaseConnection, dim: usize) -> Result<(), AppError> {
+  let expected = format!("vector({dim})");
+
+  for table in EMBEDDING_TABLES {
+    let stmt = Statement::from_sql_and_values(
+      DbBackend::Postgres,
+      r"
+      SELECT format_type(a.atttypid, a.atttypmod) AS column_type
+      FROM pg_attribute a
+      WHERE a.attrelid = to_regclass($1)
+        AND a.attname = 'embedding'
+        AND NOT a.attisdropped
+      ",
+      [table.into()],
+    );
+
+    let Some(row) = db.query_one_raw(stmt).await? else {
+      return Err(anyhow!("{table}.embedding column not found").into());
+    };
+    let column_type: String = row.try_get("", "column_type")?;
+
+    if column_type != expected {
+      return Err(
+        anyhow!(
+          "EMBEDDING_DIM={dim} does not match {table}.embedding ({column_type}); \
+           recreate the database or set EMBEDDING_DIM to the existing dimension"
+        )
+        .into(),
+      );
+    }
+  }
+
+  Ok(())
+}
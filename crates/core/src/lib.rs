@@ -1,16 +1,46 @@
 mod conversation_message;
 pub use conversation_message::ConversationMessage;
 
+mod conversation_settings;
+pub use conversation_settings::{
+  ConversationSettings, get_conversation_settings, set_conversation_settings,
+};
+
+mod conversation_usage;
+pub use conversation_usage::{
+  BudgetExceeded, BudgetPeriod, ConversationUsage, ModelPrice, ModelPrices, UsageBudget,
+  UsageLedger, check_usage_budget, list_conversation_usage,
+};
+
+mod embedding_cache;
+pub use embedding_cache::{EmbeddingCache, EmbeddingCacheStats};
+
+mod embedding_dim;
+pub use embedding_dim::verify_embedding_dim;
+
+mod llm_call_log;
+pub use llm_call_log::{LlmCall, LlmCallFilter, LlmCallLog, list_llm_calls};
+
 mod memory;
 pub use memory::EpisodicMemory;
+pub use memory::ScoreExplanation;
+pub use memory::{DEFAULT_RETRIEVAL_PROFILE, RetrievalProfile, RetrievalProfiles};
 pub use memory::SemanticMemory;
-pub use memory::{DetailLevel, format_tool_result};
+pub use memory::{
+  DEFAULT_OUTPUT_TEMPLATE, DetailLevel, FittedToolResult, OutputTemplate, OutputTemplates,
+  format_tool_result, format_tool_result_within,
+};
+pub use plastmem_shared::TemplateEscape;
+pub use memory::{Packed, TokenPacker};
 
 mod pending_review_queue;
 pub use pending_review_queue::{
   PendingReview, PendingReviewQueueItem, add_pending_review_item, take_pending_review_items,
 };
 
+mod prompt_templates;
+pub use prompt_templates::load_prompt_templates;
+
 mod message_ingest;
 pub use message_ingest::{append_batch_messages, append_message, try_claim_segmentation_job};
 
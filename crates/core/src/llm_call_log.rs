@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use plastmem_ai::{ChatCallRecord, ChatCallSink};
+use plastmem_entities::llm_call;
+use plastmem_shared::AppError;
+use sea_orm::{
+  ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Writes every chat call to the `llm_call` audit table.
+///
+/// Insert failures are logged and never fail the call being recorded.
+pub struct LlmCallLog {
+  db: DatabaseConnection,
+}
+
+impl LlmCallLog {
+  #[must_use]
+  pub const fn new(db: DatabaseConnection) -> Self {
+    Self { db }
+  }
+}
+
+#[async_trait]
+impl ChatCallSink for LlmCallLog {
+  async fn record(&self, record: ChatCallRecord) {
+    let usage = record.usage.unwrap_or_default();
+    let has_usage = record.usage.is_some();
+    let model = llm_call::ActiveModel {
+      id: Set(Uuid::now_v7()),
+      task: Set(record.task.name().to_owned()),
+      job: Set(record.context.job.map(str::to_owned)),
+      conversation_id: Set(record.context.conversation_id),
+      episode_id: Set(record.context.episode_id),
+      model: Set(record.model),
+      request_messages: Set(record.messages),
+      response: Set(record.response),
+      reasoning: Set(record.reasoning),
+      error: Set(record.error),
+      latency_ms: Set(i64::try_from(record.latency.as_millis()).unwrap_or(i64::MAX)),
+      prompt_tokens: Set(has_usage.then(|| usage.prompt_tokens.cast_signed())),
+      completion_tokens: Set(has_usage.then(|| usage.completion_tokens.cast_signed())),
+      created_at: Set(Utc::now().into()),
+    };
+
+    if let Err(err) = llm_call::Entity::insert(model)
+      .exec_without_returning(&self.db)
+      .await
+    {
+      tracing::warn!(error = %err, "failed to record LLM call");
+    }
+  }
+}
+
+/// One recorded chat call.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LlmCall {
+  pub id: Uuid,
+  /// Pipeline stage, e.g. `segmentation_classify`.
+  pub task: String,
+  /// Worker job that made the call, e.g. `predict_calibrate`.
+  pub job: Option<String>,
+  pub conversation_id: Option<Uuid>,
+  pub episode_id: Option<Uuid>,
+  pub model: String,
+  /// Chat messages sent to the model.
+  #[schema(value_type = Object)]
+  pub request_messages: serde_json::Value,
+  /// Raw reply content; absent when the request failed.
+  pub response: Option<String>,
+  pub reasoning: Option<String>,
+  /// Request error, or why the reply failed validation.
+  pub error: Option<String>,
+  pub latency_ms: i64,
+  pub prompt_tokens: Option<i32>,
+  pub completion_tokens: Option<i32>,
+  pub created_at: DateTime<Utc>,
+}
+
+impl LlmCall {
+  #[must_use]
+  pub fn from_model(model: llm_call::Model) -> Self {
+    Self {
+      id: model.id,
+      task: model.task,
+      job: model.job,
+      conversation_id: model.conversation_id,
+      episode_id: model.episode_id,
+      model: model.model,
+      request_messages: model.request_messages,
+      response: model.response,
+      reasoning: model.reasoning,
+      error: model.error,
+      latency_ms: model.latency_ms,
+      prompt_tokens: model.prompt_tokens,
+      completion_tokens: model.completion_tokens,
+      created_at: model.created_at.with_timezone(&Utc),
+    }
+  }
+}
+
+/// Filters for [`list_llm_calls`]. Unset fields match every call.
+#[derive(Debug, Clone, Default)]
+pub struct LlmCallFilter {
+  pub conversation_id: Option<Uuid>,
+  pub episode_id: Option<Uuid>,
+  pub job: Option<String>,
+  pub task: Option<String>,
+}
+
+/// Recorded chat calls matching `filter`, newest first.
+pub async fn list_llm_calls(
+  filter: &LlmCallFilter,
+  limit: u64,
+  db: &DatabaseConnection,
+) -> Result<Vec<LlmCall>, AppError> {
+  let mut query = llm_call::Entity::find();
+  if let Some(conversation_id) = filter.conversation_id {
+    query = query.filter(llm_call::Column::ConversationId.eq(conversation_id));
+  }
+  if let Some(episode_id) = filter.episode_id {
+    query = query.filter(llm_call::Column::EpisodeId.eq(episode_id));
+  }
+  if let Some(job) = &filter.job {
+    query = query.filter(llm_call::Column::Job.eq(job.as_str()));
+  }
+  if let Some(task) = &filter.task {
+    query = query.filter(llm_call::Column::Task.eq(task.as_str()));
+  }
+
+  let models = query
+    .order_by_desc(llm_call::Column::CreatedAt)
+    .limit(limit)
+    .all(db)
+    .await?;
+
+  Ok(models.into_iter().map(LlmCall::from_model).collect())
+}
@@ -1,16 +1,22 @@
 use chrono::{DateTime, Utc};
 use fsrs::{DEFAULT_PARAMETERS, FSRS, FSRS6_DEFAULT_DECAY, MemoryState};
+use plastmem_ai::PromptVersions;
 use plastmem_entities::{EpisodeClassification, episodic_memory};
-use plastmem_shared::{AppError, Message};
+use plastmem_shared::{AppError, Message, fsrs::retrievability_multiplier};
 
 use sea_orm::{
-  ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, Statement,
-  prelude::PgVector,
+  ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait,
+  FromQueryResult, QueryFilter, Set, Statement, prelude::PgVector,
 };
 use serde::Serialize;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use super::{RetrievalProfile, ScoreExplanation};
+
+/// Most flashbulb episodes one retrieval returns.
+const MAX_FLASHBULB_RESULTS: usize = 3;
+
 #[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct EpisodicMemory {
   pub id: Uuid,
@@ -22,9 +28,17 @@ pub struct EpisodicMemory {
   /// Vector embedding (internal use, not exposed in API)
   #[serde(skip)]
   pub embedding: PgVector,
+  /// Embedding model that produced `embedding`
+  #[serde(skip)]
+  pub embedding_model: String,
+  /// Versions of the prompts that produced this episode (internal use, not exposed in API)
+  #[serde(skip)]
+  pub prompt_versions: PromptVersions,
   pub stability: f32,
   pub difficulty: f32,
   pub surprise: f32,
+  /// Flashbulb episodes keep a high retrievability floor and skip memory review
+  pub is_flashbulb: bool,
   pub start_at: DateTime<Utc>,
   pub end_at: DateTime<Utc>,
   pub created_at: DateTime<Utc>,
@@ -42,9 +56,12 @@ impl EpisodicMemory {
       content: model.content,
       classification: model.classification,
       embedding: model.embedding,
+      embedding_model: model.embedding_model,
+      prompt_versions: serde_json::from_value(model.prompt_versions)?,
       stability: model.stability,
       difficulty: model.difficulty,
       surprise: model.surprise,
+      is_flashbulb: model.is_flashbulb,
       start_at: model.start_at.with_timezone(&Utc),
       end_at: model.end_at.with_timezone(&Utc),
       created_at: model.created_at.with_timezone(&Utc),
@@ -62,9 +79,12 @@ impl EpisodicMemory {
       content: self.content.clone(),
       classification: self.classification.clone(),
       embedding: self.embedding.clone(),
+      embedding_model: self.embedding_model.clone(),
+      prompt_versions: serde_json::to_value(&self.prompt_versions)?,
       stability: self.stability,
       difficulty: self.difficulty,
       surprise: self.surprise,
+      is_flashbulb: self.is_flashbulb,
       start_at: self.start_at.into(),
       end_at: self.end_at.into(),
       created_at: self.created_at.into(),
@@ -75,40 +95,90 @@ impl EpisodicMemory {
 
   /// Retrieve episodic memories using hybrid BM25 + vector search with FSRS re-ranking.
   ///
+  /// `profile` sets the RRF merge and candidate pools. Scores are multiplied by
+  /// `floor + (1 - floor) * retrievability`, with a higher floor for flashbulb episodes,
+  /// scaled by the profile's FSRS strength, and by its recency boost. At most
+  /// `MAX_FLASHBULB_RESULTS` flashbulb episodes are returned so they cannot crowd out
+  /// everything else.
+  ///
   /// Accepts a pre-computed embedding so callers can reuse one embedding across retrieval paths.
+  /// The vector leg only considers rows embedded by `embedding_model`, so rows still waiting
+  /// for re-embedding after a model switch are reachable through BM25 alone. Without a
+  /// `query_embedding` (e.g. while the embedding backend is down) only BM25 is used.
   pub async fn retrieve_by_embedding(
     query: &str,
-    query_embedding: PgVector,
+    query_embedding: Option<PgVector>,
+    embedding_model: &str,
     limit: u64,
     conversation_id: Uuid,
     db: &DatabaseConnection,
+    profile: &RetrievalProfile,
   ) -> Result<Vec<(Self, f64)>, AppError> {
+    let results = Self::retrieve_explained(
+      query,
+      query_embedding,
+      embedding_model,
+      limit,
+      conversation_id,
+      db,
+      profile,
+    )
+    .await?;
+    Ok(
+      results
+        .into_iter()
+        .map(|(mem, explanation)| (mem, explanation.score))
+        .collect(),
+    )
+  }
+
+  /// [`EpisodicMemory::retrieve_by_embedding`] with each score broken down by
+  /// search leg and FSRS retrievability.
+  pub async fn retrieve_explained(
+    query: &str,
+    query_embedding: Option<PgVector>,
+    embedding_model: &str,
+    limit: u64,
+    conversation_id: Uuid,
+    db: &DatabaseConnection,
+    profile: &RetrievalProfile,
+  ) -> Result<Vec<(Self, ScoreExplanation)>, AppError> {
     let fsrs = FSRS::new(Some(&DEFAULT_PARAMETERS))?;
 
     let retrieve_sql = r"
     WITH
     fulltext AS (
-      SELECT id, ROW_NUMBER() OVER (ORDER BY pdb.score(id) DESC) AS r
+      SELECT
+        id,
+        ROW_NUMBER() OVER (ORDER BY pdb.score(id) DESC) AS r,
+        pdb.score(id)::float8 AS bm25_score
       FROM episodic_memory
       WHERE search_text ||| $1
         AND conversation_id = $2
       LIMIT $3
     ),
     semantic AS (
-      SELECT id, ROW_NUMBER() OVER (ORDER BY embedding <#> $4) AS r
+      SELECT
+        id,
+        ROW_NUMBER() OVER (ORDER BY embedding <#> $4) AS r,
+        (embedding <#> $4)::float8 AS vector_distance
       FROM episodic_memory
-      WHERE conversation_id = $2
+      WHERE $4::vector IS NOT NULL
+        AND conversation_id = $2
+        AND embedding_model = $6
       LIMIT $3
     ),
     rrf AS (
-      SELECT id, 1.0 / (30 + r) AS s FROM fulltext
-      UNION ALL
-      SELECT id, 1.0 / (30 + r) AS s FROM semantic
-    ),
-    rrf_score AS (
-      SELECT id, SUM(s)::float8 AS score
-      FROM rrf
-      GROUP BY id
+      SELECT
+        id,
+        f.r AS bm25_rank,
+        f.bm25_score,
+        s.r AS vector_rank,
+        s.vector_distance,
+        COALESCE($8 / ($7 + f.r), 0)::float8 AS bm25_rrf,
+        COALESCE($9 / ($7 + s.r), 0)::float8 AS vector_rrf
+      FROM fulltext f
+      FULL OUTER JOIN semantic s USING (id)
     )
     SELECT
       m.id,
@@ -118,26 +188,38 @@ impl EpisodicMemory {
       m.content,
       m.classification,
       m.embedding,
+      m.embedding_model,
+      m.prompt_versions,
       m.stability,
       m.difficulty,
       m.surprise,
+      m.is_flashbulb,
       m.start_at,
       m.end_at,
       m.created_at,
       m.last_reviewed_at,
-      r.score AS score
-    FROM rrf_score r
+      r.bm25_rank,
+      r.bm25_score,
+      r.vector_rank,
+      r.vector_distance,
+      r.bm25_rrf,
+      r.vector_rrf
+    FROM rrf r
     JOIN episodic_memory m USING (id)
-    ORDER BY r.score DESC
+    ORDER BY r.bm25_rrf + r.vector_rrf DESC
     LIMIT $5;
     ";
 
     let params: Vec<sea_orm::Value> = vec![
-      query.to_owned().into(), // $1
-      conversation_id.into(),  // $2
-      100.into(),              // $3: candidate limit
-      query_embedding.into(),  // $4
-      100.into(),              // $5: final limit
+      query.to_owned().into(),        // $1
+      conversation_id.into(),         // $2
+      profile.candidate_limit.into(), // $3: candidate limit per leg
+      query_embedding.into(),         // $4
+      profile.rerank_pool.into(),     // $5: rows re-ranked before the final limit
+      embedding_model.into(),         // $6
+      profile.rrf_k.into(),           // $7
+      profile.bm25_weight.into(),     // $8
+      profile.vector_weight.into(),   // $9
     ];
 
     let retrieve_stmt = Statement::from_sql_and_values(DbBackend::Postgres, retrieve_sql, params);
@@ -148,7 +230,7 @@ impl EpisodicMemory {
 
     for row in rows {
       let model = episodic_memory::Model::from_query_result(&row, "")?;
-      let rrf_score: f64 = row.try_get("", "score")?;
+      let mut explanation = ScoreExplanation::from_row(&row)?;
       let mem = Self::from_model(model)?;
 
       let days_elapsed =
@@ -159,17 +241,40 @@ impl EpisodicMemory {
       };
       let retrievability =
         fsrs.current_retrievability(memory_state, days_elapsed, FSRS6_DEFAULT_DECAY);
+      let multiplier =
+        profile.fsrs_multiplier(retrievability_multiplier(retrievability, mem.is_flashbulb));
+      let recency = profile.recency_multiplier(mem.end_at, now);
 
-      results.push((mem, rrf_score * f64::from(retrievability)));
+      explanation.rerank(retrievability, days_elapsed, multiplier, recency);
+      results.push((mem, explanation));
     }
 
-    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
-    results.truncate(limit);
-
+    rank_results(&mut results, usize::try_from(limit).unwrap_or(usize::MAX));
     Ok(results)
   }
 
+  /// Mark or unmark an episode of `conversation_id` as a flashbulb memory.
+  ///
+  /// Returns the updated episode, or `None` if the conversation has no such episode.
+  pub async fn set_flashbulb(
+    id: Uuid,
+    conversation_id: Uuid,
+    is_flashbulb: bool,
+    db: &DatabaseConnection,
+  ) -> Result<Option<Self>, AppError> {
+    let Some(model) = episodic_memory::Entity::find_by_id(id)
+      .filter(episodic_memory::Column::ConversationId.eq(conversation_id))
+      .one(db)
+      .await?
+    else {
+      return Ok(None);
+    };
+
+    let mut active_model: episodic_memory::ActiveModel = model.into();
+    active_model.is_flashbulb = Set(is_flashbulb);
+    Self::from_model(active_model.update(db).await?).map(Some)
+  }
+
   pub async fn get(id: Uuid, db: &DatabaseConnection) -> Result<Option<Self>, AppError> {
     episodic_memory::Entity::find_by_id(id)
       .one(db)
@@ -178,3 +283,162 @@ impl EpisodicMemory {
       .transpose()
   }
 }
+
+/// Order re-ranked results by final score and keep the best `limit`, skipping
+/// flashbulb episodes past the first `MAX_FLASHBULB_RESULTS`.
+fn rank_results(results: &mut Vec<(EpisodicMemory, ScoreExplanation)>, limit: usize) {
+  results.sort_by(|a, b| {
+    b.1
+      .score
+      .partial_cmp(&a.1.score)
+      .unwrap_or(std::cmp::Ordering::Equal)
+  });
+  let mut flashbulbs = 0;
+  results.retain(|(mem, _)| {
+    flashbulbs += usize::from(mem.is_flashbulb);
+    !mem.is_flashbulb || flashbulbs <= MAX_FLASHBULB_RESULTS
+  });
+  results.truncate(limit);
+}
+
+#[cfg(test)]
+mod tests {
+  use plastmem_migration::{Migrator, MigratorTrait};
+  use plastmem_shared::APP_ENV;
+  use sea_orm::Database;
+
+  use super::*;
+
+  async fn insert_episode(
+    conversation_id: Uuid,
+    title: &str,
+    content: &str,
+    db: &DatabaseConnection,
+  ) -> Uuid {
+    let id = Uuid::now_v7();
+    let now = Utc::now();
+    episodic_memory::ActiveModel {
+      id: Set(id),
+      conversation_id: Set(conversation_id),
+      messages: Set(serde_json::json!([])),
+      content: Set(content.to_owned()),
+      embedding: Set(PgVector::from(vec![0.0; APP_ENV.embedding_dim])),
+      embedding_model: Set("test".to_owned()),
+      title: Set(title.to_owned()),
+      stability: Set(1.0),
+      difficulty: Set(5.0),
+      surprise: Set(0.0),
+      is_flashbulb: Set(false),
+      classification: Set(Some(EpisodeClassification::Informative)),
+      start_at: Set(now.into()),
+      end_at: Set(now.into()),
+      created_at: Set(now.into()),
+      last_reviewed_at: Set(now.into()),
+      consolidated_at: Set(None),
+      prompt_versions: Set(serde_json::json!({})),
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    id
+  }
+
+  #[tokio::test]
+  #[ignore = "requires a ParadeDB database at DATABASE_URL"]
+  async fn bm25_matches_japanese_and_chinese_titles_and_content() {
+    let db = Database::connect(APP_ENV.database_url.as_str())
+      .await
+      .unwrap();
+    Migrator::up(&db, None).await.unwrap();
+    let conversation_id = Uuid::now_v7();
+    let japanese = insert_episode(
+      conversation_id,
+      "東京への引っ越し",
+      "発言日時: 2026年6月15日 15時\nuser: 来月から渋谷の近くに住む予定です。",
+      &db,
+    )
+    .await;
+    let chinese = insert_episode(
+      conversation_id,
+      "上海出差计划",
+      "发言时间: 2026年6月15日 15时\nuser: 下周我要去杭州参加会议。",
+      &db,
+    )
+    .await;
+
+    // Title and content terms, each only in one episode
+    for (query, expected) in [
+      ("引っ越し", japanese),
+      ("渋谷", japanese),
+      ("上海出差", chinese),
+      ("杭州", chinese),
+    ] {
+      let results = EpisodicMemory::retrieve_explained(
+        query,
+        None,
+        "test",
+        5,
+        conversation_id,
+        &db,
+        &RetrievalProfile::default(),
+      )
+      .await
+      .unwrap();
+      assert_eq!(
+        results.first().map(|(mem, _)| mem.id),
+        Some(expected),
+        "query `{query}`"
+      );
+    }
+
+    episodic_memory::Entity::delete_many()
+      .filter(episodic_memory::Column::ConversationId.eq(conversation_id))
+      .exec(&db)
+      .await
+      .unwrap();
+  }
+
+  fn ranked(is_flashbulb: bool, score: f64) -> (EpisodicMemory, ScoreExplanation) {
+    let now = Utc::now();
+    let mem = EpisodicMemory {
+      id: Uuid::now_v7(),
+      conversation_id: Uuid::nil(),
+      messages: Vec::new(),
+      title: String::new(),
+      content: String::new(),
+      classification: None,
+      embedding: PgVector::from(vec![0.0; 4]),
+      embedding_model: String::new(),
+      prompt_versions: PromptVersions::new(),
+      stability: 1.0,
+      difficulty: 5.0,
+      surprise: 0.0,
+      is_flashbulb,
+      start_at: now,
+      end_at: now,
+      created_at: now,
+      last_reviewed_at: now,
+      consolidated_at: None,
+    };
+    (mem, ScoreExplanation::from_rrf(score, 0.0))
+  }
+
+  #[test]
+  fn ranking_caps_flashbulb_episodes_but_keeps_others() {
+    // Five flashbulb episodes outscore every ordinary one
+    let mut results = (0..5)
+      .map(|i| ranked(true, 1.0 - f64::from(i) * 0.01))
+      .chain((0..5).map(|i| ranked(false, 0.5 - f64::from(i) * 0.01)))
+      .rev()
+      .collect::<Vec<_>>();
+
+    rank_results(&mut results, 6);
+
+    let flags = results
+      .iter()
+      .map(|(mem, _)| mem.is_flashbulb)
+      .collect::<Vec<_>>();
+    assert_eq!(flags, [true, true, true, false, false, false]);
+    assert!(results.windows(2).all(|w| w[0].1.score >= w[1].1.score));
+  }
+}
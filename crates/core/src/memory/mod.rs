@@ -1,8 +1,20 @@
 mod episodic;
 pub use episodic::EpisodicMemory;
 
+mod output_template;
+pub use output_template::{DEFAULT_OUTPUT_TEMPLATE, OutputTemplate, OutputTemplates};
+
 mod retrieval;
-pub use retrieval::{DetailLevel, format_tool_result};
+pub use retrieval::{DetailLevel, FittedToolResult, format_tool_result, format_tool_result_within};
+
+mod retrieval_profile;
+pub use retrieval_profile::{DEFAULT_RETRIEVAL_PROFILE, RetrievalProfile, RetrievalProfiles};
+
+mod score_explanation;
+pub use score_explanation::ScoreExplanation;
 
 mod semantic;
 pub use semantic::SemanticMemory;
+
+mod token_packer;
+pub use token_packer::{Packed, TokenPacker};
@@ -0,0 +1,254 @@
+use std::{collections::HashMap, fs, path::Path, sync::LazyLock};
+
+use anyhow::Context;
+use plastmem_shared::{AppError, TemplateEscape, fill_template};
+use serde::Deserialize;
+
+/// Template used when neither the request nor the conversation names one.
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "markdown";
+
+static MARKDOWN: LazyLock<OutputTemplate> = LazyLock::new(OutputTemplate::markdown);
+
+/// Layout of rendered retrieval results.
+///
+/// Each part is a text with `{name}` placeholders; placeholders without a value
+/// are left as they are. Values are escaped per `escape`, except `{messages}`,
+/// which holds already rendered `message` parts. Placeholders:
+///
+/// - `episode`, `episode_detailed`: `{rank}`, `{score}`, `{id}`, `{title}`,
+///   `{content}`, `{time_range}`, `{start_at}`, `{end_at}`, `{flashbulb}`, and
+///   for `episode_detailed` `{messages}` and `{message_count}`
+/// - `message`: `{role}`, `{content}`, `{timestamp}`
+/// - `more_messages`: `{count}` (messages left out of an excerpt)
+/// - `fact`, `fact_detailed`: `{rank}`, `{score}`, `{id}`, `{category}`,
+///   `{fact}`, `{valid_at}`, and for `fact_detailed` `{sources}`
+/// - `omitted`: `{omitted}` (what a token budget left out)
+///
+/// Headers and footers are rendered only around a non-empty section.
+/// Templates read from files start from the markdown preset, so they only
+/// need the parts they change.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct OutputTemplate {
+  pub escape: TemplateEscape,
+  pub episodes_header: String,
+  /// Episodes rendered without details.
+  pub episode: String,
+  /// Episodes the `DetailLevel` expands.
+  pub episode_detailed: String,
+  pub message: String,
+  /// Placed between rendered messages.
+  pub message_separator: String,
+  pub more_messages: String,
+  pub episodes_footer: String,
+  pub facts_header: String,
+  pub fact: String,
+  /// Facts at `DetailLevel::High` that have source episodes.
+  pub fact_detailed: String,
+  pub facts_footer: String,
+  /// Appended after the trimmed output when a token budget left memories out.
+  pub omitted: String,
+}
+
+impl Default for OutputTemplate {
+  fn default() -> Self {
+    Self::markdown()
+  }
+}
+
+impl OutputTemplate {
+  /// `## Episodic Memories` / `## Known Facts` markdown for chat models.
+  #[must_use]
+  pub fn markdown() -> Self {
+    Self {
+      escape: TemplateEscape::None,
+      episodes_header: "## Episodic Memories\n".to_owned(),
+      episode: "{content}\n\n".to_owned(),
+      episode_detailed:
+        "### {title}\n**Time:** {time_range}\n{content}\n**Details:**\n{messages}\n".to_owned(),
+      message: "- {role}: \"{content}\"\n".to_owned(),
+      message_separator: String::new(),
+      more_messages: "- ({count} more messages)\n".to_owned(),
+      episodes_footer: String::new(),
+      facts_header: "## Known Facts\n".to_owned(),
+      fact: "- {fact}\n".to_owned(),
+      fact_detailed: "- {fact} (source episodes: {sources})\n".to_owned(),
+      facts_footer: "\n".to_owned(),
+      omitted: "\n\n_To fit max_tokens: {omitted}._".to_owned(),
+    }
+  }
+
+  /// XML tags, for models tuned on tagged context.
+  #[must_use]
+  pub fn xml() -> Self {
+    Self {
+      escape: TemplateEscape::Xml,
+      episodes_header: "<episodic_memories>\n".to_owned(),
+      episode: "<episode rank=\"{rank}\" score=\"{score}\">\n{content}\n</episode>\n".to_owned(),
+      episode_detailed: "<episode rank=\"{rank}\" score=\"{score}\" id=\"{id}\" title=\"{title}\" \
+                         start=\"{start_at}\" end=\"{end_at}\">\n<content>\n{content}\n</content>\n\
+                         <messages>\n{messages}</messages>\n</episode>\n"
+        .to_owned(),
+      message: "<message role=\"{role}\" at=\"{timestamp}\">{content}</message>\n".to_owned(),
+      message_separator: String::new(),
+      more_messages: "<more_messages count=\"{count}\"/>\n".to_owned(),
+      episodes_footer: "</episodic_memories>\n".to_owned(),
+      facts_header: "<known_facts>\n".to_owned(),
+      fact: "<fact category=\"{category}\">{fact}</fact>\n".to_owned(),
+      fact_detailed: "<fact category=\"{category}\" sources=\"{sources}\">{fact}</fact>\n"
+        .to_owned(),
+      facts_footer: "</known_facts>\n".to_owned(),
+      omitted: "\n<omitted>{omitted}</omitted>".to_owned(),
+    }
+  }
+
+  /// One compact JSON object per line, for tool results.
+  #[must_use]
+  pub fn json_lines() -> Self {
+    Self {
+      escape: TemplateEscape::Json,
+      episodes_header: String::new(),
+      episode: "{\"type\":\"episode\",\"rank\":{rank},\"score\":{score},\"id\":\"{id}\",\
+                \"content\":\"{content}\"}\n"
+        .to_owned(),
+      episode_detailed: "{\"type\":\"episode\",\"rank\":{rank},\"score\":{score},\"id\":\"{id}\",\
+                         \"title\":\"{title}\",\"start_at\":\"{start_at}\",\"end_at\":\"{end_at}\",\
+                         \"content\":\"{content}\",\"messages\":[{messages}]}\n"
+        .to_owned(),
+      message: "{\"role\":\"{role}\",\"content\":\"{content}\",\"timestamp\":\"{timestamp}\"}"
+        .to_owned(),
+      message_separator: ",".to_owned(),
+      more_messages: ",{\"more_messages\":{count}}".to_owned(),
+      episodes_footer: String::new(),
+      facts_header: String::new(),
+      fact: "{\"type\":\"fact\",\"rank\":{rank},\"score\":{score},\"id\":\"{id}\",\
+             \"category\":\"{category}\",\"fact\":\"{fact}\"}\n"
+        .to_owned(),
+      fact_detailed: "{\"type\":\"fact\",\"rank\":{rank},\"score\":{score},\"id\":\"{id}\",\
+                      \"category\":\"{category}\",\"fact\":\"{fact}\",\"sources\":\"{sources}\"}\n"
+        .to_owned(),
+      facts_footer: String::new(),
+      omitted: "\n{\"type\":\"omitted\",\"note\":\"{omitted}\"}".to_owned(),
+    }
+  }
+
+  /// Replace each `{name}` in `part` with its value, escaped unless the name is in `raw`.
+  #[must_use]
+  pub fn fill(&self, part: &str, vars: &[(&str, &str)], raw: &[&str]) -> String {
+    fill_template(part, vars, self.escape, raw)
+  }
+}
+
+/// Output templates by name: the built-in presets plus any loaded from files.
+#[derive(Debug, Clone)]
+pub struct OutputTemplates {
+  templates: HashMap<String, OutputTemplate>,
+}
+
+impl Default for OutputTemplates {
+  /// The `markdown`, `xml` and `json_lines` presets.
+  fn default() -> Self {
+    Self {
+      templates: HashMap::from([
+        (
+          DEFAULT_OUTPUT_TEMPLATE.to_owned(),
+          OutputTemplate::markdown(),
+        ),
+        ("xml".to_owned(), OutputTemplate::xml()),
+        ("json_lines".to_owned(), OutputTemplate::json_lines()),
+      ]),
+    }
+  }
+}
+
+impl OutputTemplates {
+  /// Add `template` as `name`, replacing a preset or earlier template with that name.
+  #[must_use]
+  pub fn with_template(mut self, name: impl Into<String>, template: OutputTemplate) -> Self {
+    self.templates.insert(name.into(), template);
+    self
+  }
+
+  /// Load every `<name>.json` file in `dir` as the template `<name>`.
+  pub fn load_dir(self, dir: impl AsRef<Path>) -> Result<Self, AppError> {
+    let dir = dir.as_ref();
+    let mut templates = self;
+    let entries = fs::read_dir(dir)
+      .with_context(|| format!("failed to read output templates from {}", dir.display()))?;
+    for entry in entries {
+      let path = entry?.path();
+      if path.extension().is_none_or(|extension| extension != "json") {
+        continue;
+      }
+      let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        continue;
+      };
+      let source = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read output template {}", path.display()))?;
+      let template = serde_json::from_str(&source)
+        .with_context(|| format!("invalid output template {}", path.display()))?;
+      templates = templates.with_template(name, template);
+    }
+    Ok(templates)
+  }
+
+  #[must_use]
+  pub fn get(&self, name: &str) -> Option<&OutputTemplate> {
+    self.templates.get(name)
+  }
+
+  /// The default template, `markdown` (possibly replaced by a file).
+  #[must_use]
+  pub fn default_template(&self) -> &OutputTemplate {
+    self
+      .templates
+      .get(DEFAULT_OUTPUT_TEMPLATE)
+      .unwrap_or(&MARKDOWN)
+  }
+
+  /// Template names, sorted.
+  #[must_use]
+  pub fn names(&self) -> Vec<&str> {
+    let mut names = self
+      .templates
+      .keys()
+      .map(String::as_str)
+      .collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fills_and_escapes_placeholders() {
+    let xml = OutputTemplate::xml();
+    assert_eq!(
+      xml.fill(
+        "<a t=\"{title}\">{messages}{missing}</a>",
+        &[("title", "\"Q&A\""), ("messages", "<m/>")],
+        &["messages"],
+      ),
+      "<a t=\"&quot;Q&amp;A&quot;\"><m/>{missing}</a>"
+    );
+    assert_eq!(
+      OutputTemplate::json_lines().fill("\"{v}\"", &[("v", "a \"b\"\n")], &[]),
+      "\"a \\\"b\\\"\\n\""
+    );
+
+    let templates = OutputTemplates::default().with_template(
+      "custom",
+      serde_json::from_str(r#"{ "fact": "* {fact}\n" }"#).unwrap(),
+    );
+    let custom = templates.get("custom").unwrap();
+    assert_eq!(custom.fact, "* {fact}\n");
+    assert_eq!(custom.episode, OutputTemplate::markdown().episode);
+    assert_eq!(
+      templates.names(),
+      ["custom", "json_lines", "markdown", "xml"]
+    );
+  }
+}
@@ -1,12 +1,24 @@
-use std::fmt::Write;
+use std::borrow::Cow;
 
+use chrono::{DateTime, Utc};
+use plastmem_ai::TokenBudget;
 use serde::Deserialize;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 use super::EpisodicMemory;
+use super::OutputTemplate;
 use super::SemanticMemory;
+use super::token_packer::{Packed, TokenPacker};
 
-#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+/// Surprise at which an episode counts as a key moment for `DetailLevel::Low`.
+const KEY_MOMENT_SURPRISE: f32 = 0.7;
+/// Messages shown per episode below `DetailLevel::High`.
+const EXCERPT_MAX_MESSAGES: usize = 12;
+/// Characters shown per message below `DetailLevel::High`.
+const EXCERPT_MAX_CHARS: usize = 280;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum DetailLevel {
   /// Ranks 1-2 always include original message details
@@ -20,44 +32,429 @@ pub enum DetailLevel {
   High,
 }
 
+impl DetailLevel {
+  /// Whether the episode at 0-based `rank` is rendered with title, time range and messages.
+  const fn shows_details(self, rank: usize, mem: &EpisodicMemory) -> bool {
+    match self {
+      Self::Auto => rank < 2,
+      Self::None => false,
+      Self::Low => rank == 0 && (mem.is_flashbulb || mem.surprise >= KEY_MOMENT_SURPRISE),
+      Self::High => true,
+    }
+  }
+}
+
+fn format_time_range(start: DateTime<Utc>, end: DateTime<Utc>) -> String {
+  let start_str = start.format("%Y-%m-%d %H:%M");
+  if start.date_naive() == end.date_naive() {
+    format!("{start_str} - {} UTC", end.format("%H:%M"))
+  } else {
+    format!("{start_str} - {} UTC", end.format("%Y-%m-%d %H:%M"))
+  }
+}
+
+fn excerpt(text: &str) -> String {
+  match text.char_indices().nth(EXCERPT_MAX_CHARS) {
+    Some((end, _)) => format!("{}...", text[..end].trim_end()),
+    None => text.to_owned(),
+  }
+}
+
+fn render_messages(template: &OutputTemplate, mem: &EpisodicMemory, detail: DetailLevel) -> String {
+  let full = detail == DetailLevel::High;
+  let shown = if full {
+    mem.messages.len()
+  } else {
+    mem.messages.len().min(EXCERPT_MAX_MESSAGES)
+  };
+
+  let mut out = mem.messages[..shown]
+    .iter()
+    .map(|msg| {
+      let content = if full {
+        Cow::Borrowed(msg.content.as_str())
+      } else {
+        Cow::Owned(excerpt(&msg.content))
+      };
+      template.fill(
+        &template.message,
+        &[
+          ("role", &msg.role.to_string()),
+          ("content", &content),
+          ("timestamp", &msg.timestamp.to_rfc3339()),
+        ],
+        &[],
+      )
+    })
+    .collect::<Vec<_>>()
+    .join(&template.message_separator);
+  if shown < mem.messages.len() {
+    out.push_str(&template.fill(
+      &template.more_messages,
+      &[("count", &(mem.messages.len() - shown).to_string())],
+      &[],
+    ));
+  }
+  out
+}
+
+/// Render the episode at 0-based `rank` with `content` in place of its own.
+fn render_episode(
+  template: &OutputTemplate,
+  rank: usize,
+  (mem, score): &(EpisodicMemory, f64),
+  detail: DetailLevel,
+  content: &str,
+) -> String {
+  let title = if mem.title.is_empty() {
+    "Memory"
+  } else {
+    mem.title.as_str()
+  };
+  let rank_str = (rank + 1).to_string();
+  let score = format!("{score:.4}");
+  let id = mem.id.to_string();
+  let time_range = format_time_range(mem.start_at, mem.end_at);
+  let start_at = mem.start_at.to_rfc3339();
+  let end_at = mem.end_at.to_rfc3339();
+  let flashbulb = mem.is_flashbulb.to_string();
+  let mut vars = vec![
+    ("rank", rank_str.as_str()),
+    ("score", &score),
+    ("id", &id),
+    ("title", title),
+    ("content", content),
+    ("time_range", &time_range),
+    ("start_at", &start_at),
+    ("end_at", &end_at),
+    ("flashbulb", &flashbulb),
+  ];
+  if !detail.shows_details(rank, mem) {
+    return template.fill(&template.episode, &vars, &[]);
+  }
+
+  let messages = render_messages(template, mem, detail);
+  let message_count = mem.messages.len().to_string();
+  vars.extend([
+    ("messages", messages.as_str()),
+    ("message_count", &message_count),
+  ]);
+  template.fill(&template.episode_detailed, &vars, &["messages"])
+}
+
+fn render_fact(
+  template: &OutputTemplate,
+  rank: usize,
+  (fact, score): &(SemanticMemory, f64),
+  detail: DetailLevel,
+) -> String {
+  let rank = (rank + 1).to_string();
+  let score = format!("{score:.4}");
+  let id = fact.id.to_string();
+  let valid_at = fact.valid_at.to_rfc3339();
+  let mut vars = vec![
+    ("rank", rank.as_str()),
+    ("score", &score),
+    ("id", &id),
+    ("category", &fact.category),
+    ("fact", &fact.fact),
+    ("valid_at", &valid_at),
+  ];
+  if detail != DetailLevel::High || fact.source_episodic_ids.is_empty() {
+    return template.fill(&template.fact, &vars, &[]);
+  }
+
+  let sources = fact
+    .source_episodic_ids
+    .iter()
+    .map(ToString::to_string)
+    .collect::<Vec<_>>()
+    .join(", ");
+  vars.push(("sources", &sources));
+  template.fill(&template.fact_detailed, &vars, &[])
+}
+
+fn push_section<'a>(
+  out: &mut String,
+  header: &str,
+  blocks: impl IntoIterator<Item = &'a str>,
+  footer: &str,
+) {
+  let mut blocks = blocks.into_iter().peekable();
+  if blocks.peek().is_some() {
+    out.push_str(header);
+    out.extend(blocks);
+    out.push_str(footer);
+  }
+}
+
+fn assemble<'a>(
+  template: &OutputTemplate,
+  episodes: impl IntoIterator<Item = &'a str>,
+  facts: impl IntoIterator<Item = &'a str>,
+) -> String {
+  let mut out = String::new();
+  push_section(
+    &mut out,
+    &template.episodes_header,
+    episodes,
+    &template.episodes_footer,
+  );
+  push_section(
+    &mut out,
+    &template.facts_header,
+    facts,
+    &template.facts_footer,
+  );
+  out.trim_end().to_string()
+}
+
+/// Render retrieval results for an LLM through `template`.
+///
+/// Episodes are listed by rank with their rendered content; the ranks selected
+/// by `detail` use the template's detailed layout with title, time range and
+/// original messages (excerpts below `DetailLevel::High`). At
+/// `DetailLevel::High`, facts list their source episodes.
 #[must_use]
 pub fn format_tool_result(
   semantic_results: &[(SemanticMemory, f64)],
   episodic_results: &[(EpisodicMemory, f64)],
-  _detail: &DetailLevel,
+  detail: &DetailLevel,
+  template: &OutputTemplate,
 ) -> String {
-  let mut out = String::new();
+  let episodes = episodic_results
+    .iter()
+    .enumerate()
+    .map(|(rank, result)| render_episode(template, rank, result, *detail, &result.0.content))
+    .collect::<Vec<_>>();
+  let facts = semantic_results
+    .iter()
+    .enumerate()
+    .map(|(rank, result)| render_fact(template, rank, result, *detail))
+    .collect::<Vec<_>>();
+  assemble(
+    template,
+    episodes.iter().map(String::as_str),
+    facts.iter().map(String::as_str),
+  )
+}
 
-  // ── Episodic Memories ──
-  if !episodic_results.is_empty() {
-    let _ = writeln!(out, "## Episodic Memories");
+/// Output of [`format_tool_result_within`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FittedToolResult {
+  pub text: String,
+  /// Episodes rendered whole or trimmed, by rank; dropped episodes are left out
+  pub episode_ids: Vec<Uuid>,
+}
+
+/// What [`format_tool_result_within`] left out to fit its budget.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Omitted {
+  episodes: usize,
+  trimmed_episodes: usize,
+  facts: usize,
+}
+
+impl Omitted {
+  /// E.g. "dropped 1 episodic memory, 2 facts; trimmed 1 episodic memory".
+  fn note(self) -> Option<String> {
+    let plural = |count: usize, one: &str, many: &str| {
+      format!("{count} {}", if count == 1 { one } else { many })
+    };
+    let mut dropped = Vec::new();
+    if self.episodes > 0 {
+      dropped.push(plural(
+        self.episodes,
+        "episodic memory",
+        "episodic memories",
+      ));
+    }
+    if self.facts > 0 {
+      dropped.push(plural(self.facts, "fact", "facts"));
+    }
+
+    let mut parts = Vec::new();
+    if !dropped.is_empty() {
+      parts.push(format!("dropped {}", dropped.join(", ")));
+    }
+    if self.trimmed_episodes > 0 {
+      parts.push(format!(
+        "trimmed {}",
+        plural(
+          self.trimmed_episodes,
+          "episodic memory",
+          "episodic memories"
+        )
+      ));
+    }
+    (!parts.is_empty()).then(|| parts.join("; "))
   }
-  for (mem, _score) in episodic_results {
-    let _ = writeln!(out, "{}", mem.content);
-    let _ = writeln!(out);
+
+  /// Close `out` with the template's `omitted` part, if anything was left out.
+  fn append_to(self, out: String, template: &OutputTemplate) -> String {
+    let Some(note) = self.note() else {
+      return out;
+    };
+    let note = template.fill(&template.omitted, &[("omitted", &note)], &[]);
+    if out.is_empty() {
+      note.trim_start().to_owned()
+    } else {
+      out + &note
+    }
+  }
+}
+
+/// Render an episode without details, its content cut to fit `max_tokens`.
+fn trim_episode(
+  template: &OutputTemplate,
+  budget: &TokenBudget,
+  rank: usize,
+  result: &(EpisodicMemory, f64),
+  max_tokens: usize,
+) -> String {
+  let overhead = budget.count(&render_episode(
+    template,
+    rank,
+    result,
+    DetailLevel::None,
+    "",
+  ));
+  let content = budget.truncate(&result.0.content, max_tokens.saturating_sub(overhead));
+  render_episode(template, rank, result, DetailLevel::None, &content)
+}
+
+/// [`format_tool_result`] cut to fit `budget`, with the episodes it kept.
+///
+/// Facts and episodes are packed greedily by score, highest first. An episode
+/// that no longer fits whole is re-rendered without details and its content
+/// trimmed to the remaining budget, or dropped when too little is left; facts
+/// are only dropped. The template's `omitted` part closes the output with how
+/// many memories were dropped or trimmed.
+#[must_use]
+pub fn format_tool_result_within(
+  semantic_results: &[(SemanticMemory, f64)],
+  episodic_results: &[(EpisodicMemory, f64)],
+  detail: &DetailLevel,
+  template: &OutputTemplate,
+  budget: &TokenBudget,
+) -> FittedToolResult {
+  enum Item {
+    Episode(usize),
+    Fact(usize),
   }
 
-  // ── Known Facts ──
-  if !semantic_results.is_empty() {
-    let _ = writeln!(out, "## Known Facts");
-    for (fact, _score) in semantic_results {
-      let _ = writeln!(out, "- {}", fact.fact);
+  let mut episodes = episodic_results
+    .iter()
+    .enumerate()
+    .map(|(rank, result)| {
+      Some(render_episode(
+        template,
+        rank,
+        result,
+        *detail,
+        &result.0.content,
+      ))
+    })
+    .collect::<Vec<_>>();
+  let mut facts = semantic_results
+    .iter()
+    .enumerate()
+    .map(|(rank, result)| Some(render_fact(template, rank, result, *detail)))
+    .collect::<Vec<_>>();
+
+  let mut packer = TokenPacker::new(budget);
+  for (header, footer, used) in [
+    (
+      &template.episodes_header,
+      &template.episodes_footer,
+      !episodes.is_empty(),
+    ),
+    (
+      &template.facts_header,
+      &template.facts_footer,
+      !facts.is_empty(),
+    ),
+  ] {
+    if used {
+      packer.reserve(header);
+      packer.reserve(footer);
     }
-    let _ = writeln!(out);
   }
 
-  out.trim_end().to_string()
+  // Highest score first; the sort is stable, so ties keep episodes first and ranks in order
+  let mut order = episodic_results
+    .iter()
+    .enumerate()
+    .map(|(index, (_, score))| (*score, Item::Episode(index)))
+    .chain(
+      semantic_results
+        .iter()
+        .enumerate()
+        .map(|(index, (_, score))| (*score, Item::Fact(index))),
+    )
+    .collect::<Vec<_>>();
+  order.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+  let mut omitted = Omitted::default();
+  for (_, item) in order {
+    match item {
+      Item::Episode(rank) => {
+        let Some(block) = episodes[rank].take() else {
+          continue;
+        };
+        episodes[rank] = match packer.pack_or_trim(block, |max_tokens| {
+          trim_episode(template, budget, rank, &episodic_results[rank], max_tokens)
+        }) {
+          Packed::Whole(block) => Some(block),
+          Packed::Trimmed(block) => {
+            omitted.trimmed_episodes += 1;
+            Some(block)
+          }
+          Packed::Dropped => {
+            omitted.episodes += 1;
+            None
+          }
+        };
+      }
+      Item::Fact(index) => {
+        let Some(block) = facts[index].take() else {
+          continue;
+        };
+        facts[index] = packer.pack(block);
+        omitted.facts += usize::from(facts[index].is_none());
+      }
+    }
+  }
+
+  let episode_ids = episodic_results
+    .iter()
+    .zip(&episodes)
+    .filter(|(_, block)| block.is_some())
+    .map(|((mem, _), _)| mem.id)
+    .collect();
+  let out = assemble(
+    template,
+    episodes.iter().flatten().map(String::as_str),
+    facts.iter().flatten().map(String::as_str),
+  );
+  FittedToolResult {
+    text: omitted.append_to(out, template),
+    episode_ids,
+  }
 }
 
 #[cfg(test)]
 mod tests {
-  use chrono::Utc;
+  use std::sync::Arc;
+
   use chrono::TimeZone;
+  use chrono::Utc;
   use sea_orm::prelude::PgVector;
   use uuid::Uuid;
 
   use super::*;
   use crate::memory::episodic::EpisodicMemory;
+  use plastmem_ai::{EstimatedTokenCounter, PromptVersions};
   use plastmem_shared::{Message, MessageRole};
 
   fn episodic_memory(content: &str) -> EpisodicMemory {
@@ -69,13 +466,16 @@ mod tests {
         content: "raw".to_owned(),
         timestamp: Utc.timestamp_opt(0, 0).single().expect("valid timestamp"),
       }],
-      title: "Ignored title".to_owned(),
+      title: "Greeting".to_owned(),
       content: content.to_owned(),
       classification: None,
       embedding: PgVector::from(vec![0.0; 1024]),
+      embedding_model: "test".to_owned(),
+      prompt_versions: PromptVersions::new(),
       stability: 1.0,
       difficulty: 1.0,
       surprise: 0.0,
+      is_flashbulb: false,
       start_at: Utc.timestamp_opt(0, 0).single().expect("valid timestamp"),
       end_at: Utc.timestamp_opt(0, 0).single().expect("valid timestamp"),
       created_at: Utc.timestamp_opt(0, 0).single().expect("valid timestamp"),
@@ -85,13 +485,20 @@ mod tests {
   }
 
   #[test]
-  fn format_tool_result_outputs_only_episodic_content_blocks() {
+  fn format_tool_result_without_details_outputs_only_episodic_content_blocks() {
     let episodic = vec![
-      (episodic_memory("Spoken At: Jun 15, 2026 3 PM\nSam: hello"), 0.9),
-      (episodic_memory("Spoken At: Jun 16, 2026 4 PM\nEvan: hi"), 0.8),
+      (
+        episodic_memory("Spoken At: Jun 15, 2026 3 PM\nSam: hello"),
+        0.9,
+      ),
+      (
+        episodic_memory("Spoken At: Jun 16, 2026 4 PM\nEvan: hi"),
+        0.8,
+      ),
     ];
 
-    let rendered = format_tool_result(&[], &episodic, &DetailLevel::Auto);
+    let markdown = OutputTemplate::markdown();
+    let rendered = format_tool_result(&[], &episodic, &DetailLevel::None, &markdown);
 
     assert_eq!(
       rendered,
@@ -102,4 +509,80 @@ mod tests {
     assert!(!rendered.contains("**Details:**"));
     assert!(!rendered.contains("**Time Evidence:**"));
   }
+
+  #[test]
+  fn format_tool_result_adds_details_for_selected_ranks() {
+    let episodic = vec![
+      (episodic_memory("first"), 0.9),
+      (episodic_memory("second"), 0.8),
+      (episodic_memory("third"), 0.7),
+    ];
+
+    let markdown = OutputTemplate::markdown();
+    let auto = format_tool_result(&[], &episodic, &DetailLevel::Auto, &markdown);
+    assert_eq!(auto.matches("### Greeting").count(), 2);
+    assert!(
+      auto.contains("**Time:** 1970-01-01 00:00 - 00:00 UTC\nfirst\n**Details:**\n- Sam: \"raw\"")
+    );
+    assert!(auto.ends_with("\n\nthird"));
+
+    // Low only details a surprising top episode
+    let low = format_tool_result(&[], &episodic, &DetailLevel::Low, &markdown);
+    assert!(!low.contains("**Details:**"));
+
+    let high = format_tool_result(&[], &episodic, &DetailLevel::High, &markdown);
+    assert_eq!(high.matches("**Details:**").count(), 3);
+  }
+
+  #[test]
+  fn format_tool_result_within_trims_and_reports_dropped_memories() {
+    let episodic = vec![
+      (episodic_memory(&"x ".repeat(150)), 0.9),
+      (episodic_memory("short story"), 0.5),
+    ];
+    let counter = Arc::new(EstimatedTokenCounter);
+    let markdown = OutputTemplate::markdown();
+
+    let roomy = TokenBudget::exact(1000, counter.clone());
+    let fitted = format_tool_result_within(&[], &episodic, &DetailLevel::None, &markdown, &roomy);
+    assert_eq!(
+      fitted.text,
+      format_tool_result(&[], &episodic, &DetailLevel::None, &markdown)
+    );
+    assert_eq!(fitted.episode_ids, [episodic[0].0.id, episodic[1].0.id]);
+
+    // Only the trimmed episode was shown, so only it may be reviewed
+    let tight = TokenBudget::exact(120, counter);
+    let fitted = format_tool_result_within(&[], &episodic, &DetailLevel::None, &markdown, &tight);
+    assert_eq!(fitted.episode_ids, [episodic[0].0.id]);
+    let rendered = fitted.text;
+    assert!(rendered.contains(" [...]"));
+    assert!(!rendered.contains("short story"));
+    assert!(
+      rendered
+        .ends_with("_To fit max_tokens: dropped 1 episodic memory; trimmed 1 episodic memory._")
+    );
+  }
+
+  #[test]
+  fn format_tool_result_renders_json_lines_template() {
+    let episodic = vec![
+      (episodic_memory("said \"hi\"\nthen left"), 0.9),
+      (episodic_memory("second"), 0.8),
+    ];
+
+    let rendered = format_tool_result(
+      &[],
+      &episodic,
+      &DetailLevel::Low,
+      &OutputTemplate::json_lines(),
+    );
+    let lines = rendered
+      .lines()
+      .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["content"], "said \"hi\"\nthen left");
+    assert_eq!(lines[1]["rank"], 2);
+  }
 }
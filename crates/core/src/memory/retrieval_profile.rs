@@ -0,0 +1,213 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use anyhow::{anyhow, ensure};
+use chrono::{DateTime, Utc};
+use plastmem_shared::AppError;
+use serde::Deserialize;
+
+/// Profile used when neither the request nor `RETRIEVAL_PROFILE` names one.
+pub const DEFAULT_RETRIEVAL_PROFILE: &str = "default";
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+static DEFAULT: LazyLock<RetrievalProfile> = LazyLock::new(RetrievalProfile::default);
+
+/// Tuning of hybrid retrieval: how the BM25 and vector legs are merged and how
+/// episodic scores are re-ranked.
+///
+/// Each leg contributes `weight / (rrf_k + rank)` for the memories among its
+/// `candidate_limit` best. The `rerank_pool` best episodes by RRF score are then
+/// multiplied by the FSRS retrievability multiplier, scaled by `fsrs_strength`,
+/// and by `1 + recency_boost * 0.5^(age / recency_half_life_days)` on `end_at`,
+/// before the final limit. Facts are ranked by RRF score alone.
+///
+/// Profiles read from config start from the default, so they only need the
+/// fields they change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetrievalProfile {
+  /// RRF constant; higher values flatten the difference between ranks
+  pub rrf_k: f64,
+  pub bm25_weight: f64,
+  pub vector_weight: f64,
+  /// Candidates fetched per search leg before merging
+  pub candidate_limit: i64,
+  /// Episodes re-ranked by FSRS and recency, by RRF score
+  pub rerank_pool: i64,
+  /// 0 ignores FSRS retrievability, 1 applies the full multiplier
+  pub fsrs_strength: f64,
+  /// Extra weight of a just-ended episode; 0 disables the recency boost
+  pub recency_boost: f64,
+  /// Age at which the recency boost has halved
+  pub recency_half_life_days: f64,
+}
+
+impl Default for RetrievalProfile {
+  fn default() -> Self {
+    Self {
+      rrf_k: 30.0,
+      bm25_weight: 1.0,
+      vector_weight: 1.0,
+      candidate_limit: 100,
+      rerank_pool: 100,
+      fsrs_strength: 1.0,
+      recency_boost: 0.0,
+      recency_half_life_days: 30.0,
+    }
+  }
+}
+
+impl RetrievalProfile {
+  fn validate(&self, name: &str) -> anyhow::Result<()> {
+    ensure!(
+      self.rrf_k > 0.0,
+      "retrieval profile `{name}`: rrf_k must be positive"
+    );
+    ensure!(
+      self.bm25_weight >= 0.0 && self.vector_weight >= 0.0,
+      "retrieval profile `{name}`: leg weights must not be negative"
+    );
+    ensure!(
+      self.candidate_limit > 0 && self.rerank_pool > 0,
+      "retrieval profile `{name}`: candidate_limit and rerank_pool must be positive"
+    );
+    ensure!(
+      (0.0..=1.0).contains(&self.fsrs_strength),
+      "retrieval profile `{name}`: fsrs_strength must be between 0 and 1"
+    );
+    ensure!(
+      self.recency_boost >= 0.0 && self.recency_half_life_days > 0.0,
+      "retrieval profile `{name}`: recency_boost must not be negative and recency_half_life_days must be positive"
+    );
+    Ok(())
+  }
+
+  /// Scale a FSRS retrievability multiplier by `fsrs_strength`.
+  #[must_use]
+  pub fn fsrs_multiplier(&self, multiplier: f64) -> f64 {
+    self.fsrs_strength.mul_add(multiplier - 1.0, 1.0)
+  }
+
+  /// Recency multiplier of an episode that ended at `end_at`.
+  #[must_use]
+  pub fn recency_multiplier(&self, end_at: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    if self.recency_boost <= 0.0 {
+      return 1.0;
+    }
+    // Episodes ending in the future count as just ended
+    let age_days = (now - end_at)
+      .to_std()
+      .map_or(0.0, |age| age.as_secs_f64() / SECONDS_PER_DAY);
+    self
+      .recency_boost
+      .mul_add(0.5_f64.powf(age_days / self.recency_half_life_days), 1.0)
+  }
+}
+
+/// Retrieval profiles by name: the built-in `default` plus any from config.
+#[derive(Debug, Clone)]
+pub struct RetrievalProfiles {
+  profiles: HashMap<String, RetrievalProfile>,
+  default_name: String,
+}
+
+impl Default for RetrievalProfiles {
+  fn default() -> Self {
+    Self {
+      profiles: HashMap::from([(
+        DEFAULT_RETRIEVAL_PROFILE.to_owned(),
+        RetrievalProfile::default(),
+      )]),
+      default_name: DEFAULT_RETRIEVAL_PROFILE.to_owned(),
+    }
+  }
+}
+
+impl RetrievalProfiles {
+  /// Parse `{"<name>": {<profile fields>}, ...}`, e.g. from `RETRIEVAL_PROFILES`.
+  pub fn parse(spec: &str) -> Result<Self, AppError> {
+    let profiles: HashMap<String, RetrievalProfile> =
+      serde_json::from_str(spec).map_err(|err| anyhow!("invalid retrieval profiles: {err}"))?;
+    profiles
+      .into_iter()
+      .try_fold(Self::default(), |profiles, (name, profile)| {
+        profiles.with_profile(name, profile)
+      })
+  }
+
+  /// Add `profile` as `name`, replacing any profile with that name.
+  pub fn with_profile(
+    mut self,
+    name: impl Into<String>,
+    profile: RetrievalProfile,
+  ) -> Result<Self, AppError> {
+    let name = name.into();
+    profile.validate(&name)?;
+    self.profiles.insert(name, profile);
+    Ok(self)
+  }
+
+  /// Use the profile `name` when a request names none.
+  pub fn with_default(mut self, name: &str) -> Result<Self, AppError> {
+    if !self.profiles.contains_key(name) {
+      return Err(anyhow!("unknown default retrieval profile `{name}`").into());
+    }
+    name.clone_into(&mut self.default_name);
+    Ok(self)
+  }
+
+  #[must_use]
+  pub fn get(&self, name: &str) -> Option<&RetrievalProfile> {
+    self.profiles.get(name)
+  }
+
+  #[must_use]
+  pub fn default_profile(&self) -> &RetrievalProfile {
+    self.profiles.get(&self.default_name).unwrap_or(&DEFAULT)
+  }
+
+  #[must_use]
+  pub fn default_name(&self) -> &str {
+    &self.default_name
+  }
+
+  /// Profile names, sorted.
+  #[must_use]
+  pub fn names(&self) -> Vec<&str> {
+    let mut names = self.profiles.keys().map(String::as_str).collect::<Vec<_>>();
+    names.sort_unstable();
+    names
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Duration;
+
+  use super::*;
+
+  #[test]
+  fn parses_partial_profiles_and_scales_multipliers() {
+    let profiles =
+      RetrievalProfiles::parse(r#"{ "recent": { "recency_boost": 1.0, "fsrs_strength": 0.5 } }"#)
+        .unwrap()
+        .with_default("recent")
+        .unwrap();
+    assert_eq!(profiles.names(), ["default", "recent"]);
+    let recent = profiles.default_profile();
+    assert!((recent.rrf_k - 30.0).abs() < f64::EPSILON);
+    assert!((recent.fsrs_multiplier(0.5) - 0.75).abs() < f64::EPSILON);
+
+    let now = Utc::now();
+    assert!((recent.recency_multiplier(now, now) - 2.0).abs() < 1e-9);
+    assert!((recent.recency_multiplier(now - Duration::days(30), now) - 1.5).abs() < 1e-9);
+    assert!((RetrievalProfile::default().recency_multiplier(now, now) - 1.0).abs() < f64::EPSILON);
+
+    assert!(RetrievalProfiles::parse(r#"{ "bad": { "rrf_k": 0 } }"#).is_err());
+    assert!(
+      RetrievalProfiles::default()
+        .with_default("missing")
+        .is_err()
+    );
+  }
+}
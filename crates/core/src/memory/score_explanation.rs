@@ -0,0 +1,111 @@
+use sea_orm::{DbErr, QueryResult};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// How a hybrid retrieval score came about.
+///
+/// Each leg contributes `weight / (k + rank)` to the RRF score when the memory
+/// is among its candidates. Episodic scores are then multiplied by the FSRS
+/// retrievability and recency multipliers; semantic scores are the RRF score.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ScoreExplanation {
+  /// 1-based rank among the BM25 candidates, if the memory matched the query text
+  pub bm25_rank: Option<i64>,
+  pub bm25_score: Option<f64>,
+  /// 1-based rank among the vector candidates, if the memory was one
+  pub vector_rank: Option<i64>,
+  /// Negative inner product with the query embedding; lower is closer
+  pub vector_distance: Option<f64>,
+  pub bm25_rrf: f64,
+  pub vector_rrf: f64,
+  /// Sum of both legs' RRF contributions
+  pub rrf_score: f64,
+  /// FSRS retrievability at retrieval time (episodic only)
+  pub retrievability: Option<f32>,
+  /// Whole days since the last review (episodic only)
+  pub days_elapsed: Option<u32>,
+  /// `floor + (1 - floor) * retrievability`, scaled by the profile's FSRS strength (episodic only)
+  pub retrievability_multiplier: Option<f64>,
+  /// Recency boost on `end_at` (episodic only)
+  pub recency_multiplier: Option<f64>,
+  /// Final score the results are ordered by
+  pub score: f64,
+}
+
+impl ScoreExplanation {
+  /// Breakdown of a score made of both legs' RRF contributions, before any re-ranking.
+  #[must_use]
+  pub fn from_rrf(bm25_rrf: f64, vector_rrf: f64) -> Self {
+    Self {
+      bm25_rank: None,
+      bm25_score: None,
+      vector_rank: None,
+      vector_distance: None,
+      bm25_rrf,
+      vector_rrf,
+      rrf_score: bm25_rrf + vector_rrf,
+      retrievability: None,
+      days_elapsed: None,
+      retrievability_multiplier: None,
+      recency_multiplier: None,
+      score: bm25_rrf + vector_rrf,
+    }
+  }
+
+  /// Read the per-leg columns of a hybrid retrieval row, before any FSRS re-ranking.
+  pub fn from_row(row: &QueryResult) -> Result<Self, DbErr> {
+    Ok(Self {
+      bm25_rank: row.try_get("", "bm25_rank")?,
+      bm25_score: row.try_get("", "bm25_score")?,
+      vector_rank: row.try_get("", "vector_rank")?,
+      vector_distance: row.try_get("", "vector_distance")?,
+      ..Self::from_rrf(row.try_get("", "bm25_rrf")?, row.try_get("", "vector_rrf")?)
+    })
+  }
+
+  /// Re-rank an episodic score by its FSRS retrievability and recency multipliers.
+  pub fn rerank(
+    &mut self,
+    retrievability: f32,
+    days_elapsed: u32,
+    retrievability_multiplier: f64,
+    recency_multiplier: f64,
+  ) {
+    self.retrievability = Some(retrievability);
+    self.days_elapsed = Some(days_elapsed);
+    self.retrievability_multiplier = Some(retrievability_multiplier);
+    self.recency_multiplier = Some(recency_multiplier);
+    self.score = self.rrf_score * retrievability_multiplier * recency_multiplier;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::{Duration, Utc};
+  use plastmem_shared::fsrs::retrievability_multiplier;
+
+  use super::*;
+  use crate::RetrievalProfile;
+
+  #[test]
+  fn breakdown_multiplies_up_to_the_final_score() {
+    let profile = RetrievalProfile {
+      fsrs_strength: 0.5,
+      recency_boost: 1.0,
+      ..RetrievalProfile::default()
+    };
+    let now = Utc::now();
+    let multiplier = profile.fsrs_multiplier(retrievability_multiplier(0.6, false));
+    let recency = profile.recency_multiplier(now - Duration::days(10), now);
+
+    let mut explanation = ScoreExplanation::from_rrf(1.0 / 31.0, 1.0 / 33.0);
+    assert!((explanation.score - explanation.rrf_score).abs() < f64::EPSILON);
+    explanation.rerank(0.6, 10, multiplier, recency);
+
+    let expected = (explanation.bm25_rrf + explanation.vector_rrf)
+      * explanation.retrievability_multiplier.unwrap()
+      * explanation.recency_multiplier.unwrap();
+    assert!((explanation.score - expected).abs() < 1e-12);
+    assert!(explanation.score < explanation.rrf_score * recency);
+  }
+}
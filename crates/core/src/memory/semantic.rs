@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use plastmem_ai::PromptVersions;
 use plastmem_entities::semantic_memory;
 use plastmem_shared::AppError;
 use sea_orm::{
@@ -8,8 +9,7 @@ use serde::Serialize;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-/// Number of candidates fetched per search leg (BM25 and vector) before RRF merging.
-const RETRIEVAL_CANDIDATE_LIMIT: i64 = 100;
+use super::{RetrievalProfile, ScoreExplanation};
 
 // ──────────────────────────────────────────────────
 // Domain model
@@ -27,6 +27,11 @@ pub struct SemanticMemory {
   #[serde(skip)]
   pub embedding: PgVector,
   #[serde(skip)]
+  pub embedding_model: String,
+  /// Versions of the prompts that extracted this fact
+  #[serde(skip)]
+  pub prompt_versions: PromptVersions,
+  #[serde(skip)]
   pub created_at: DateTime<Utc>,
 }
 
@@ -42,6 +47,8 @@ impl SemanticMemory {
       valid_at: model.valid_at.with_timezone(&Utc),
       invalid_at: model.invalid_at.map(|dt| dt.with_timezone(&Utc)),
       embedding: model.embedding,
+      embedding_model: model.embedding_model,
+      prompt_versions: serde_json::from_value(model.prompt_versions).unwrap_or_default(),
       created_at: model.created_at.with_timezone(&Utc),
     }
   }
@@ -52,21 +59,66 @@ impl SemanticMemory {
     self.category == "guideline"
   }
 
-  /// Retrieve semantic facts using hybrid BM25 + vector search with RRF.
+  /// Retrieve semantic facts using hybrid BM25 + vector search with RRF, merged as `profile` sets.
   ///
   /// Accepts a pre-computed embedding so callers can reuse one embedding across retrieval paths.
+  /// The vector leg only considers facts embedded by `embedding_model`, and is skipped
+  /// when `query_embedding` is `None`, leaving BM25 alone.
+  #[expect(
+    clippy::too_many_arguments,
+    reason = "one parameter per retrieval filter"
+  )]
   pub async fn retrieve_by_embedding(
     query: &str,
-    query_embedding: PgVector,
+    query_embedding: Option<PgVector>,
+    embedding_model: &str,
     limit: i64,
     conversation_id: Uuid,
     db: &DatabaseConnection,
     category: Option<&str>,
+    profile: &RetrievalProfile,
   ) -> Result<Vec<(Self, f64)>, AppError> {
+    let results = Self::retrieve_explained(
+      query,
+      query_embedding,
+      embedding_model,
+      limit,
+      conversation_id,
+      db,
+      category,
+      profile,
+    )
+    .await?;
+    Ok(
+      results
+        .into_iter()
+        .map(|(fact, explanation)| (fact, explanation.score))
+        .collect(),
+    )
+  }
+
+  /// [`SemanticMemory::retrieve_by_embedding`] with each score broken down by search leg.
+  #[expect(
+    clippy::too_many_arguments,
+    reason = "one parameter per retrieval filter"
+  )]
+  pub async fn retrieve_explained(
+    query: &str,
+    query_embedding: Option<PgVector>,
+    embedding_model: &str,
+    limit: i64,
+    conversation_id: Uuid,
+    db: &DatabaseConnection,
+    category: Option<&str>,
+    profile: &RetrievalProfile,
+  ) -> Result<Vec<(Self, ScoreExplanation)>, AppError> {
     let sql = r"
     WITH
     fulltext AS (
-      SELECT id, ROW_NUMBER() OVER (ORDER BY pdb.score(id) DESC) AS r
+      SELECT
+        id,
+        ROW_NUMBER() OVER (ORDER BY pdb.score(id) DESC) AS r,
+        pdb.score(id)::float8 AS bm25_score
       FROM semantic_memory
       WHERE fact ||| $1
         AND conversation_id = $2
@@ -75,30 +127,38 @@ impl SemanticMemory {
       LIMIT $3
     ),
     semantic AS (
-      SELECT id, ROW_NUMBER() OVER (ORDER BY embedding <#> $4) AS r
+      SELECT
+        id,
+        ROW_NUMBER() OVER (ORDER BY embedding <#> $4) AS r,
+        (embedding <#> $4)::float8 AS vector_distance
       FROM semantic_memory
-      WHERE conversation_id = $2
+      WHERE $4::vector IS NOT NULL
+        AND conversation_id = $2
         AND invalid_at IS NULL
         AND ($6::text IS NULL OR category = $6)
+        AND embedding_model = $7
       LIMIT $3
     ),
     rrf AS (
-      SELECT id, 1.0 / (30 + r) AS s FROM fulltext
-      UNION ALL
-      SELECT id, 1.0 / (30 + r) AS s FROM semantic
-    ),
-    rrf_score AS (
-      SELECT id, SUM(s)::float8 AS score
-      FROM rrf
-      GROUP BY id
+      SELECT
+        id,
+        f.r AS bm25_rank,
+        f.bm25_score,
+        s.r AS vector_rank,
+        s.vector_distance,
+        COALESCE($9 / ($8 + f.r), 0)::float8 AS bm25_rrf,
+        COALESCE($10 / ($8 + s.r), 0)::float8 AS vector_rrf
+      FROM fulltext f
+      FULL OUTER JOIN semantic s USING (id)
     )
     SELECT
       m.id, m.conversation_id, m.category, m.fact, m.source_episodic_ids,
-      m.valid_at, m.invalid_at, m.embedding, m.created_at,
-      r.score AS score
-    FROM rrf_score r
+      m.valid_at, m.invalid_at, m.embedding, m.embedding_model, m.prompt_versions,
+      m.created_at,
+      r.bm25_rank, r.bm25_score, r.vector_rank, r.vector_distance, r.bm25_rrf, r.vector_rrf
+    FROM rrf r
     JOIN semantic_memory m USING (id)
-    ORDER BY r.score DESC
+    ORDER BY r.bm25_rrf + r.vector_rrf DESC
     LIMIT $5;
     ";
 
@@ -108,10 +168,14 @@ impl SemanticMemory {
       vec![
         query.to_owned().into(),
         conversation_id.into(),
-        RETRIEVAL_CANDIDATE_LIMIT.into(),
+        profile.candidate_limit.into(),
         query_embedding.into(),
         limit.into(),
         category.map(std::borrow::ToOwned::to_owned).into(),
+        embedding_model.into(),
+        profile.rrf_k.into(),
+        profile.bm25_weight.into(),
+        profile.vector_weight.into(),
       ],
     );
 
@@ -120,10 +184,77 @@ impl SemanticMemory {
 
     for row in rows {
       let model = semantic_memory::Model::from_query_result(&row, "")?;
-      let score: f64 = row.try_get("", "score")?;
-      results.push((Self::from_model(model), score));
+      results.push((Self::from_model(model), ScoreExplanation::from_row(&row)?));
     }
 
     Ok(results)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use plastmem_migration::{Migrator, MigratorTrait};
+  use plastmem_shared::APP_ENV;
+  use sea_orm::{ActiveModelTrait, ColumnTrait, Database, EntityTrait, QueryFilter, Set};
+
+  use super::*;
+
+  #[tokio::test]
+  #[ignore = "requires a ParadeDB database at DATABASE_URL"]
+  async fn bm25_matches_japanese_and_chinese_facts() {
+    let db = Database::connect(APP_ENV.database_url.as_str())
+      .await
+      .unwrap();
+    Migrator::up(&db, None).await.unwrap();
+    let conversation_id = Uuid::now_v7();
+    let now = Utc::now();
+
+    let mut ids = Vec::new();
+    for fact in ["ユーザーは猫を二匹飼っている", "用户喜欢喝乌龙茶"] {
+      let id = Uuid::now_v7();
+      semantic_memory::ActiveModel {
+        id: Set(id),
+        conversation_id: Set(conversation_id),
+        category: Set("preference".to_owned()),
+        fact: Set(fact.to_owned()),
+        source_episodic_ids: Set(Vec::new()),
+        valid_at: Set(now.into()),
+        invalid_at: Set(None),
+        embedding: Set(PgVector::from(vec![0.0; APP_ENV.embedding_dim])),
+        embedding_model: Set("test".to_owned()),
+        prompt_versions: Set(serde_json::json!({})),
+        created_at: Set(now.into()),
+      }
+      .insert(&db)
+      .await
+      .unwrap();
+      ids.push(id);
+    }
+
+    for (query, expected) in [("猫", ids[0]), ("乌龙茶", ids[1])] {
+      let results = SemanticMemory::retrieve_explained(
+        query,
+        None,
+        "test",
+        5,
+        conversation_id,
+        &db,
+        None,
+        &RetrievalProfile::default(),
+      )
+      .await
+      .unwrap();
+      assert_eq!(
+        results.first().map(|(fact, _)| fact.id),
+        Some(expected),
+        "query `{query}`"
+      );
+    }
+
+    semantic_memory::Entity::delete_many()
+      .filter(semantic_memory::Column::ConversationId.eq(conversation_id))
+      .exec(&db)
+      .await
+      .unwrap();
+  }
+}
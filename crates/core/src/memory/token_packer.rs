@@ -0,0 +1,61 @@
+use plastmem_ai::TokenBudget;
+
+/// Budget held back for the note listing what was left out.
+const OMITTED_NOTE_TOKENS: usize = 32;
+/// A block that does not fit is trimmed if at least this many tokens are left, else dropped.
+const MIN_TRIMMED_TOKENS: usize = 48;
+
+/// Where [`TokenPacker`] placed a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packed {
+  Whole(String),
+  Trimmed(String),
+  Dropped,
+}
+
+/// Greedy packing of rendered memories into a token budget.
+///
+/// Blocks are placed in the order they are offered, whole while they fit. Room
+/// for a closing note about what was left out is held back from the start.
+pub struct TokenPacker<'a> {
+  budget: &'a TokenBudget,
+  remaining: usize,
+}
+
+impl<'a> TokenPacker<'a> {
+  #[must_use]
+  pub fn new(budget: &'a TokenBudget) -> Self {
+    Self {
+      budget,
+      remaining: budget.available().saturating_sub(OMITTED_NOTE_TOKENS),
+    }
+  }
+
+  /// Set aside room for `text` the output carries anyway, e.g. a section header.
+  pub fn reserve(&mut self, text: &str) {
+    self.remaining = self.remaining.saturating_sub(self.budget.count(text));
+  }
+
+  /// Place `block` if it fits whole, else drop it.
+  pub fn pack(&mut self, block: String) -> Option<String> {
+    let cost = self.budget.count(&block);
+    (cost <= self.remaining).then(|| {
+      self.remaining -= cost;
+      block
+    })
+  }
+
+  /// Place `block` if it fits whole. Otherwise place `trim(remaining)`, a shorter
+  /// rendering of at most `remaining` tokens, if enough is left, else drop it.
+  pub fn pack_or_trim(&mut self, block: String, trim: impl FnOnce(usize) -> String) -> Packed {
+    if let Some(block) = self.pack(block) {
+      return Packed::Whole(block);
+    }
+    if self.remaining < MIN_TRIMMED_TOKENS {
+      return Packed::Dropped;
+    }
+    let trimmed = trim(self.remaining);
+    self.remaining = self.remaining.saturating_sub(self.budget.count(&trimmed));
+    Packed::Trimmed(trimmed)
+  }
+}
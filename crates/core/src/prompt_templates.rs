@@ -0,0 +1,29 @@
+use plastmem_ai::PromptTemplate;
+use plastmem_entities::prompt_template;
+use plastmem_shared::AppError;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+
+/// Active prompt overrides from the `prompt_template` table, oldest first.
+///
+/// Apply them in order, so the newest active version of a prompt wins.
+pub async fn load_prompt_templates(
+  db: &DatabaseConnection,
+) -> Result<Vec<PromptTemplate>, AppError> {
+  let models = prompt_template::Entity::find()
+    .filter(prompt_template::Column::Active.eq(true))
+    .order_by_asc(prompt_template::Column::CreatedAt)
+    .order_by_asc(prompt_template::Column::Version)
+    .all(db)
+    .await?;
+
+  Ok(
+    models
+      .into_iter()
+      .map(|model| PromptTemplate {
+        name: model.name,
+        version: model.version,
+        template: model.template.trim().to_owned(),
+      })
+      .collect(),
+  )
+}
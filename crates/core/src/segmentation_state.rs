@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use plastmem_ai::PromptVersions;
 use plastmem_entities::{
   EpisodeClassification, conversation_message, episode_span, segmentation_state,
 };
@@ -140,28 +141,32 @@ pub struct EpisodeSpan {
   pub start_seq: i64,
   pub end_seq: i64,
   pub classification: EpisodeClassification,
+  /// Versions of the segmentation prompts that produced this span
+  pub prompt_versions: PromptVersions,
   pub created_at: DateTime<Utc>,
 }
 
 impl EpisodeSpan {
-  pub fn from_model(model: episode_span::Model) -> Self {
-    Self {
+  pub fn from_model(model: episode_span::Model) -> Result<Self, AppError> {
+    Ok(Self {
       conversation_id: model.conversation_id,
       start_seq: model.start_seq,
       end_seq: model.end_seq,
       classification: model.classification,
+      prompt_versions: serde_json::from_value(model.prompt_versions)?,
       created_at: model.created_at.with_timezone(&Utc),
-    }
+    })
   }
 
-  pub fn to_model(&self) -> episode_span::Model {
-    episode_span::Model {
+  pub fn to_model(&self) -> Result<episode_span::Model, AppError> {
+    Ok(episode_span::Model {
       conversation_id: self.conversation_id,
       start_seq: self.start_seq,
       end_seq: self.end_seq,
       classification: self.classification.clone(),
+      prompt_versions: serde_json::to_value(&self.prompt_versions)?,
       created_at: self.created_at.into(),
-    }
+    })
   }
 }
 
@@ -286,7 +291,7 @@ pub async fn get_episode_span(
   episode_span::Entity::find_by_id((conversation_id, start_seq))
     .one(db)
     .await?
-    .map(|model| Ok(EpisodeSpan::from_model(model)))
+    .map(EpisodeSpan::from_model)
     .transpose()
 }
 
@@ -358,11 +363,10 @@ pub async fn commit_segmentation_job(
   }
 
   if !finalized_spans.is_empty() {
-    let active_models: Vec<episode_span::ActiveModel> = finalized_spans
+    let active_models = finalized_spans
       .iter()
-      .map(EpisodeSpan::to_model)
-      .map(Into::into)
-      .collect();
+      .map(|span| span.to_model().map(episode_span::ActiveModel::from))
+      .collect::<Result<Vec<_>, _>>()?;
     episode_span::Entity::insert_many(active_models)
       .exec(&txn)
       .await?;
@@ -14,6 +14,8 @@ pub struct Model {
   pub start_seq: i64,
   pub end_seq: i64,
   pub classification: EpisodeClassification,
+  #[sea_orm(column_type = "JsonBinary")]
+  pub prompt_versions: Json,
   pub created_at: DateTimeWithTimeZone,
 }
 
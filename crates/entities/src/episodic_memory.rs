@@ -16,6 +16,10 @@ pub struct Model {
   pub content: String,
   pub embedding: PgVector,
   #[sea_orm(column_type = "Text")]
+  pub embedding_model: String,
+  #[sea_orm(column_type = "JsonBinary")]
+  pub prompt_versions: Json,
+  #[sea_orm(column_type = "Text")]
   pub title: String,
   #[sea_orm(column_type = "Float")]
   pub stability: f32,
@@ -23,6 +27,7 @@ pub struct Model {
   pub difficulty: f32,
   #[sea_orm(column_type = "Float")]
   pub surprise: f32,
+  pub is_flashbulb: bool,
   pub classification: Option<EpisodeClassification>,
   pub start_at: DateTimeWithTimeZone,
   pub end_at: DateTimeWithTimeZone,
@@ -3,10 +3,15 @@
 pub mod prelude;
 
 pub mod conversation_message;
+pub mod conversation_settings;
+pub mod conversation_usage;
+pub mod embedding_cache;
 pub mod episode_classification;
 pub mod episode_span;
 pub mod episodic_memory;
+pub mod llm_call;
 pub mod pending_review_queue;
+pub mod prompt_template;
 pub mod segmentation_state;
 pub mod semantic_memory;
 
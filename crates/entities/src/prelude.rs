@@ -1,8 +1,13 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
 
 pub use super::conversation_message::Entity as ConversationMessage;
+pub use super::conversation_settings::Entity as ConversationSettings;
+pub use super::conversation_usage::Entity as ConversationUsage;
+pub use super::embedding_cache::Entity as EmbeddingCache;
 pub use super::episode_span::Entity as EpisodeSpan;
 pub use super::episodic_memory::Entity as EpisodicMemory;
+pub use super::llm_call::Entity as LlmCall;
 pub use super::pending_review_queue::Entity as PendingReviewQueue;
+pub use super::prompt_template::Entity as PromptTemplate;
 pub use super::segmentation_state::Entity as SegmentationState;
 pub use super::semantic_memory::Entity as SemanticMemory;
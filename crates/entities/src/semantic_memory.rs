@@ -16,6 +16,10 @@ pub struct Model {
   pub valid_at: DateTimeWithTimeZone,
   pub invalid_at: Option<DateTimeWithTimeZone>,
   pub embedding: PgVector,
+  #[sea_orm(column_type = "Text")]
+  pub embedding_model: String,
+  #[sea_orm(column_type = "JsonBinary")]
+  pub prompt_versions: Json,
   pub created_at: DateTimeWithTimeZone,
 }
 
@@ -1,7 +1,8 @@
 use chrono::TimeDelta;
 use plastmem_ai::{
-  ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
-  ChatCompletionRequestUserMessage, generate_object,
+  AiClient, BuiltinPrompt, ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+  ChatCompletionRequestUserMessage, ChatTask, Prompt, PromptRegistry, PromptVersions, TokenBudget,
+  generate_object,
 };
 use plastmem_core::ConversationMessage;
 use plastmem_shared::AppError;
@@ -92,6 +93,11 @@ const HARD_BOUNDARY_TIME_GAP_THRESHOLD: TimeDelta = TimeDelta::hours(3);
 const LOW_INFO_LLM_MAX_MESSAGES: i64 = 4;
 const PRIMITIVE_SPLIT_TRIGGER_MESSAGES: i64 = 20;
 const SECOND_STAGE_RESEG_TRIGGER_MESSAGES: i64 = 30;
+/// A single message may use at most this share of a request's budget, so a
+/// classification request (at most `LOW_INFO_LLM_MAX_MESSAGES`) always fits.
+const MESSAGE_BUDGET_SHARE: usize = 4;
+/// Budget held back for the header and boundary hints around the message lines.
+const SEGMENT_HEADER_TOKENS: usize = 512;
 
 const JSON_SCHEMA_REQUIREMENT: &str = r#"
 Return only JSON that matches the provided schema.
@@ -146,47 +152,96 @@ Split sensitivity:
 3. Do not use context outside the provided messages.
 "#;
 
-fn compose_prompt(sections: &[&str]) -> String {
-  sections.join("\n\n")
-}
-
-pub fn primitive_classification_system_prompt() -> String {
-  compose_prompt(&[
+/// Shared guidance sections, available to every segmentation prompt as `{variable}`s.
+const SECTION_VARS: [(&str, &str); 7] = [
+  ("json_schema_requirement", JSON_SCHEMA_REQUIREMENT),
+  (
+    "classification_label_guidance",
+    CLASSIFICATION_LABEL_GUIDANCE,
+  ),
+  ("boundary_trigger_guidance", BOUNDARY_TRIGGER_GUIDANCE),
+  ("segment_length_guidance", SEGMENT_LENGTH_GUIDANCE),
+  ("index_rules", INDEX_RULES),
+  (
+    "candidate_boundary_hint_rules",
+    CANDIDATE_BOUNDARY_HINT_RULES,
+  ),
+  ("split_sensitivity_guidance", SPLIT_SENSITIVITY_GUIDANCE),
+];
+
+pub const PRIMITIVE_CLASSIFICATION_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "segmentation_classify",
+  "builtin-1",
+  concat!(
     "You are classifying one conversation segment.",
-    JSON_SCHEMA_REQUIREMENT,
+    "\n\n{json_schema_requirement}\n\n",
     "Return structured JSON with:\n- `classification`",
-    CLASSIFICATION_LABEL_GUIDANCE,
+    "\n\n{classification_label_guidance}\n\n",
     "Rules:\n1. Classify only the provided range.\n2. Do not assume context outside the provided messages.\n3. Use `low_info` only for truly thin exchanges such as greetings, sign-offs, acknowledgements, backchannels, or minimal coordination with weak long-term retrieval value.\n4. If the range contains any durable fact, plan, decision, preference, constraint, event detail, or meaningful social exchange, classify it as `informative`.",
-  ])
-}
+  ),
+);
 
-pub fn primitive_split_system_prompt() -> String {
-  compose_prompt(&[
+pub const PRIMITIVE_SPLIT_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "segmentation_split",
+  "builtin-1",
+  concat!(
     "You are reviewing one long conversation segment.",
-    JSON_SCHEMA_REQUIREMENT,
+    "\n\n{json_schema_requirement}\n\n",
     "Return structured JSON with:\n- `split_start_message_indices`",
+    "\n\n",
     "Your job:\n1. Identify the first message of each later child segment.\n2. Start a new child segment whenever there is a meaningful topic shift or a clear surprise/discontinuity.\n3. Focus only on boundary placement. The system will classify child segments separately.",
-    BOUNDARY_TRIGGER_GUIDANCE,
-    SEGMENT_LENGTH_GUIDANCE,
-    SPLIT_SENSITIVITY_GUIDANCE,
-    INDEX_RULES,
+    "\n\n{boundary_trigger_guidance}\n\n{segment_length_guidance}\n\n{split_sensitivity_guidance}\n\n{index_rules}\n\n",
     "Output rules:\n1. Return only later split starts. Do not include 0.\n2. Keep indices unique and ascending.\n3. If there is no meaningful boundary, return an empty array.",
-  ])
-}
+  ),
+);
 
-pub fn constrained_resegment_system_prompt() -> String {
-  compose_prompt(&[
+pub const CONSTRAINED_RESEGMENT_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "segmentation_resegment",
+  "builtin-1",
+  concat!(
     "You are re-segmenting one informative conversation range.",
-    JSON_SCHEMA_REQUIREMENT,
+    "\n\n{json_schema_requirement}\n\n",
     "Return structured JSON with:\n- `split_start_message_indices`",
+    "\n\n",
     "Your job:\n1. Review the candidate boundaries provided for this range.\n2. Identify the first message of each informative subsegment after boundary review.\n3. Start a new informative subsegment whenever there is a meaningful topic shift or a clear surprise/discontinuity inside the provided range.",
-    BOUNDARY_TRIGGER_GUIDANCE,
-    SEGMENT_LENGTH_GUIDANCE,
+    "\n\n{boundary_trigger_guidance}\n\n{segment_length_guidance}\n\n",
     "Rules:\n1. All content in the provided range should remain informative. Do not re-classify.\n2. Use high sensitivity to real topic shifts. If a candidate boundary is plausibly meaningful, keep the split.\n3. Do not use context outside the provided messages.",
-    CANDIDATE_BOUNDARY_HINT_RULES,
-    INDEX_RULES,
+    "\n\n{candidate_boundary_hint_rules}\n\n{index_rules}\n\n",
     "Output rules:\n1. Return only later split starts. Do not include 0.\n2. Keep indices unique and ascending.\n3. If the whole range should remain one informative segment, return an empty array.",
-  ])
+  ),
+);
+
+#[must_use]
+pub fn primitive_classification_system_prompt(prompts: &PromptRegistry) -> Prompt {
+  prompts.render(&PRIMITIVE_CLASSIFICATION_PROMPT, &SECTION_VARS)
+}
+
+#[must_use]
+pub fn primitive_split_system_prompt(prompts: &PromptRegistry) -> Prompt {
+  prompts.render(&PRIMITIVE_SPLIT_PROMPT, &SECTION_VARS)
+}
+
+#[must_use]
+pub fn constrained_resegment_system_prompt(prompts: &PromptRegistry) -> Prompt {
+  prompts.render(&CONSTRAINED_RESEGMENT_PROMPT, &SECTION_VARS)
+}
+
+/// Versions of the segmentation prompts in effect, recorded with committed spans.
+///
+/// The resegmentation prompt only counts when the temporal boundary review ran.
+#[must_use]
+pub fn segmentation_prompt_versions(
+  boundary_review: bool,
+  prompts: &PromptRegistry,
+) -> PromptVersions {
+  let mut builtins = vec![PRIMITIVE_CLASSIFICATION_PROMPT, PRIMITIVE_SPLIT_PROMPT];
+  if boundary_review {
+    builtins.push(CONSTRAINED_RESEGMENT_PROMPT);
+  }
+  builtins
+    .iter()
+    .map(|builtin| (builtin.name.to_owned(), prompts.version(builtin).to_owned()))
+    .collect()
 }
 
 pub fn temporal_rule_segmenter(messages: &[ConversationMessage]) -> Result<RuleSegOutput, String> {
@@ -353,6 +408,7 @@ pub fn resolve_constrained_resegmentation(
 pub async fn primitive_review_llm_segmenter(
   claimed_messages: &[ConversationMessage],
   rule_output: &RuleSegOutput,
+  ai: &AiClient,
 ) -> Result<(Vec<ReviewedSegment>, Vec<ReviewedBoundary>), AppError> {
   let bucket_ranges = derive_bucket_ranges(rule_output);
   if bucket_ranges.is_empty() {
@@ -367,7 +423,7 @@ pub async fn primitive_review_llm_segmenter(
   for (bucket_idx, bucket) in bucket_ranges.iter().enumerate() {
     let primitive_candidate = candidate_from_bucket(bucket);
     let primitive_segments =
-      classify_or_split_bucket(claimed_messages, &primitive_candidate).await?;
+      classify_or_split_bucket(claimed_messages, &primitive_candidate, ai).await?;
     if primitive_segments.is_empty() {
       return Err(AppError::new(anyhow::anyhow!(
         "Primitive review produced no reviewed segments"
@@ -416,6 +472,7 @@ pub async fn temporal_boundary_review_llm_segmenter(
   claimed_messages: &[ConversationMessage],
   reviewed_segments: &[ReviewedSegment],
   reviewed_boundaries: &[ReviewedBoundary],
+  ai: &AiClient,
 ) -> Result<Vec<ReviewedSegment>, AppError> {
   if reviewed_segments.is_empty() {
     return Ok(Vec::new());
@@ -426,6 +483,11 @@ pub async fn temporal_boundary_review_llm_segmenter(
     )));
   }
 
+  let budget = segment_budget(
+    ChatTask::SegmentationResegment,
+    &constrained_resegment_system_prompt(ai.prompts()).text,
+    ai,
+  );
   let mut final_segments = Vec::new();
   let mut index = 0usize;
 
@@ -439,9 +501,10 @@ pub async fn temporal_boundary_review_llm_segmenter(
 
     let mut group_end = index;
     let mut total_len = segment_message_count(current.start_seq, current.end_seq);
+    let mut total_tokens = segment_tokens(claimed_messages, current, &budget)?;
 
     // Keep merging across RuleSoft boundaries while the group remains below the
-    // second-stage threshold. Large or hard-bounded groups stay as-is.
+    // second-stage threshold and fits one request. Large or hard-bounded groups stay as-is.
     while total_len < SECOND_STAGE_RESEG_TRIGGER_MESSAGES && group_end + 1 < reviewed_segments.len()
     {
       let boundary = reviewed_boundaries.get(group_end).ok_or_else(|| {
@@ -456,8 +519,13 @@ pub async fn temporal_boundary_review_llm_segmenter(
       if next_segment.classification != SegmentClassification::Informative {
         break;
       }
+      let next_tokens = segment_tokens(claimed_messages, next_segment, &budget)?;
+      if total_tokens + next_tokens > budget.available() {
+        break;
+      }
 
       total_len += segment_message_count(next_segment.start_seq, next_segment.end_seq);
+      total_tokens += next_tokens;
       group_end += 1;
     }
 
@@ -466,6 +534,7 @@ pub async fn temporal_boundary_review_llm_segmenter(
         claimed_messages,
         &reviewed_segments[index..=group_end],
         &reviewed_boundaries[index..group_end],
+        ai,
       )
       .await?;
       final_segments.extend(merged_segments);
@@ -478,14 +547,55 @@ pub async fn temporal_boundary_review_llm_segmenter(
   Ok(final_segments)
 }
 
+/// Review one rule bucket. A bucket whose messages do not fit one split request
+/// is first cut into consecutive chunks that do, and each chunk is reviewed on its own.
 async fn classify_or_split_bucket(
   claimed_messages: &[ConversationMessage],
   segment: &CandidateSegment,
+  ai: &AiClient,
+) -> Result<Vec<ReviewedSegment>, AppError> {
+  let segment_messages = slice_segment_messages(claimed_messages, segment)?;
+  let budget = segment_budget(
+    ChatTask::SegmentationSplit,
+    &primitive_split_system_prompt(ai.prompts()).text,
+    ai,
+  );
+  let costs = render_message_lines(segment_messages, &budget)
+    .iter()
+    .map(|line| budget.count(line))
+    .collect::<Vec<_>>();
+  let chunks = budget.chunks(&costs);
+  if chunks.len() <= 1 {
+    return review_candidate_segment(claimed_messages, segment, ai).await;
+  }
+
+  tracing::info!(
+    start_seq = segment.start_seq,
+    end_seq = segment.end_seq,
+    chunk_count = chunks.len(),
+    budget_tokens = budget.available(),
+    "Candidate segment exceeds the prompt budget; reviewing it in chunks"
+  );
+  let mut reviewed_segments = Vec::new();
+  for range in chunks {
+    let chunk = CandidateSegment {
+      start_seq: segment_messages[range.start].seq,
+      end_seq: segment_messages[range.end - 1].seq,
+    };
+    reviewed_segments.extend(review_candidate_segment(claimed_messages, &chunk, ai).await?);
+  }
+  Ok(reviewed_segments)
+}
+
+async fn review_candidate_segment(
+  claimed_messages: &[ConversationMessage],
+  segment: &CandidateSegment,
+  ai: &AiClient,
 ) -> Result<Vec<ReviewedSegment>, AppError> {
   let segment_messages = slice_segment_messages(claimed_messages, segment)?;
   let segment_len = message_count(segment_messages)?;
   if segment_len <= LOW_INFO_LLM_MAX_MESSAGES {
-    let (output, message_seqs) = request_primitive_classification(segment_messages).await?;
+    let (output, message_seqs) = request_primitive_classification(segment_messages, ai).await?;
     return resolve_primitive_classification(segment, &message_seqs, output)
       .map_err(|reason| AppError::new(anyhow::anyhow!(reason)));
   }
@@ -494,14 +604,14 @@ async fn classify_or_split_bucket(
     return Ok(vec![reviewed_informative_segment(segment)]);
   }
 
-  let (output, message_seqs) = request_primitive_split(segment_messages).await?;
+  let (output, message_seqs) = request_primitive_split(segment_messages, ai).await?;
   match resolve_primitive_split(segment, &message_seqs, output) {
     Ok(child_segments) => {
       let mut reviewed_segments = Vec::with_capacity(child_segments.len());
       for child_segment in child_segments {
         let child_messages = slice_segment_messages(claimed_messages, &child_segment)?;
         if message_count(child_messages)? <= LOW_INFO_LLM_MAX_MESSAGES {
-          let (output, message_seqs) = request_primitive_classification(child_messages).await?;
+          let (output, message_seqs) = request_primitive_classification(child_messages, ai).await?;
           let child_reviewed =
             resolve_primitive_classification(&child_segment, &message_seqs, output)
               .map_err(|reason| AppError::new(anyhow::anyhow!(reason)))?;
@@ -528,6 +638,7 @@ async fn constrained_resegment_group(
   claimed_messages: &[ConversationMessage],
   group_segments: &[ReviewedSegment],
   group_boundaries: &[ReviewedBoundary],
+  ai: &AiClient,
 ) -> Result<Vec<ReviewedSegment>, AppError> {
   let merged_segment = CandidateSegment {
     start_seq: group_segments
@@ -542,7 +653,7 @@ async fn constrained_resegment_group(
 
   let merged_messages = slice_segment_messages(claimed_messages, &merged_segment)?;
   let (output, message_seqs) =
-    request_constrained_resegmentation(merged_messages, group_boundaries).await?;
+    request_constrained_resegmentation(merged_messages, group_boundaries, ai).await?;
   resolve_constrained_resegmentation(&merged_segment, &message_seqs, output)
     .map_err(|reason| AppError::new(anyhow::anyhow!(reason)))
 }
@@ -553,17 +664,23 @@ async fn constrained_resegment_group(
 
 async fn request_primitive_classification(
   messages: &[ConversationMessage],
+  ai: &AiClient,
 ) -> Result<(PrimitiveClassificationOutput, Vec<i64>), AppError> {
-  let system = ChatCompletionRequestSystemMessage::from(primitive_classification_system_prompt());
-  let user = ChatCompletionRequestUserMessage::from(build_plain_segment_user_content(messages));
+  let prompt = primitive_classification_system_prompt(ai.prompts());
+  let budget = segment_budget(ChatTask::SegmentationClassify, &prompt.text, ai);
+  let system = ChatCompletionRequestSystemMessage::from(prompt.text);
+  let user =
+    ChatCompletionRequestUserMessage::from(build_plain_segment_user_content(messages, &budget));
 
   let output = generate_object::<PrimitiveClassificationOutput>(
+    ChatTask::SegmentationClassify,
     vec![
       ChatCompletionRequestMessage::System(system),
       ChatCompletionRequestMessage::User(user),
     ],
     "primitive_segment_classification".to_owned(),
     Some("Classify one primitive segment".to_owned()),
+    ai,
   )
   .await?;
 
@@ -572,17 +689,23 @@ async fn request_primitive_classification(
 
 async fn request_primitive_split(
   messages: &[ConversationMessage],
+  ai: &AiClient,
 ) -> Result<(PrimitiveSplitOutput, Vec<i64>), AppError> {
-  let system = ChatCompletionRequestSystemMessage::from(primitive_split_system_prompt());
-  let user = ChatCompletionRequestUserMessage::from(build_plain_segment_user_content(messages));
+  let prompt = primitive_split_system_prompt(ai.prompts());
+  let budget = segment_budget(ChatTask::SegmentationSplit, &prompt.text, ai);
+  let system = ChatCompletionRequestSystemMessage::from(prompt.text);
+  let user =
+    ChatCompletionRequestUserMessage::from(build_plain_segment_user_content(messages, &budget));
 
   let output = generate_object::<PrimitiveSplitOutput>(
+    ChatTask::SegmentationSplit,
     vec![
       ChatCompletionRequestMessage::System(system),
       ChatCompletionRequestMessage::User(user),
     ],
     "primitive_segment_split".to_owned(),
     Some("Split one long primitive segment".to_owned()),
+    ai,
   )
   .await?;
 
@@ -592,20 +715,26 @@ async fn request_primitive_split(
 async fn request_constrained_resegmentation(
   messages: &[ConversationMessage],
   boundary_hints: &[ReviewedBoundary],
+  ai: &AiClient,
 ) -> Result<(ConstrainedResegmentationOutput, Vec<i64>), AppError> {
-  let system = ChatCompletionRequestSystemMessage::from(constrained_resegment_system_prompt());
+  let prompt = constrained_resegment_system_prompt(ai.prompts());
+  let budget = segment_budget(ChatTask::SegmentationResegment, &prompt.text, ai);
+  let system = ChatCompletionRequestSystemMessage::from(prompt.text);
   let user = ChatCompletionRequestUserMessage::from(build_constrained_resegment_user_content(
     messages,
     boundary_hints,
+    &budget,
   )?);
 
   let output = generate_object::<ConstrainedResegmentationOutput>(
+    ChatTask::SegmentationResegment,
     vec![
       ChatCompletionRequestMessage::System(system),
       ChatCompletionRequestMessage::User(user),
     ],
     "constrained_resegmentation".to_owned(),
     Some("Re-segment one informative segment group".to_owned()),
+    ai,
   )
   .await?;
 
@@ -616,27 +745,48 @@ async fn request_constrained_resegmentation(
 // Request content builders
 // ──────────────────────────────────────────────────
 
-fn build_plain_segment_user_content(messages: &[ConversationMessage]) -> String {
+/// Prompt budget left for the message lines of a `task` request using `system_prompt`.
+fn segment_budget(task: ChatTask, system_prompt: &str, ai: &AiClient) -> TokenBudget {
+  ai.token_budget(task)
+    .reserve(system_prompt)
+    .reserve_tokens(SEGMENT_HEADER_TOKENS)
+}
+
+/// One `- [idx=N] ...` line per message, each truncated to its share of `budget`.
+fn render_message_lines(messages: &[ConversationMessage], budget: &TokenBudget) -> Vec<String> {
+  let max_message_tokens = budget.available() / MESSAGE_BUDGET_SHARE;
+  messages
+    .iter()
+    .enumerate()
+    .map(|(index, message)| {
+      format!(
+        "- [idx={}] {} [{}] {}\n",
+        index,
+        message.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+        message.role,
+        budget.truncate(&message.content, max_message_tokens)
+      )
+    })
+    .collect()
+}
+
+fn build_plain_segment_user_content(
+  messages: &[ConversationMessage],
+  budget: &TokenBudget,
+) -> String {
   let mut output = String::new();
   output.push_str(&format!(
     "Candidate segment:\n- local message count: {}\n- use only the shown `idx` values for any returned start_message_index\n",
     messages.len()
   ));
-  for (index, message) in messages.iter().enumerate() {
-    output.push_str(&format!(
-      "- [idx={}] {} [{}] {}\n",
-      index,
-      message.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
-      message.role,
-      message.content
-    ));
-  }
+  output.extend(render_message_lines(messages, budget));
   output
 }
 
 fn build_constrained_resegment_user_content(
   messages: &[ConversationMessage],
   boundary_hints: &[ReviewedBoundary],
+  budget: &TokenBudget,
 ) -> Result<String, AppError> {
   let mut output = String::new();
   output.push_str(&format!(
@@ -673,16 +823,7 @@ fn build_constrained_resegment_user_content(
     output.push('\n');
   }
 
-  for (index, message) in messages.iter().enumerate() {
-    output.push_str(&format!(
-      "- [idx={}] {} [{}] {}\n",
-      index,
-      message.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
-      message.role,
-      message.content
-    ));
-  }
-
+  output.extend(render_message_lines(messages, budget));
   Ok(output)
 }
 
@@ -731,6 +872,27 @@ fn slice_segment_messages<'a>(
   Ok(&claimed_messages[start_idx..=end_idx])
 }
 
+/// Tokens the message lines of `segment` take in a request with `budget`.
+fn segment_tokens(
+  claimed_messages: &[ConversationMessage],
+  segment: &ReviewedSegment,
+  budget: &TokenBudget,
+) -> Result<usize, AppError> {
+  let messages = slice_segment_messages(
+    claimed_messages,
+    &CandidateSegment {
+      start_seq: segment.start_seq,
+      end_seq: segment.end_seq,
+    },
+  )?;
+  Ok(
+    render_message_lines(messages, budget)
+      .iter()
+      .map(|line| budget.count(line))
+      .sum(),
+  )
+}
+
 fn segment_message_count(start_seq: i64, end_seq: i64) -> i64 {
   end_seq - start_seq + 1
 }
@@ -749,3 +911,66 @@ fn reviewed_informative_segment(segment: &CandidateSegment) -> ReviewedSegment {
     classification: SegmentClassification::Informative,
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use chrono::{TimeZone, Utc};
+  use plastmem_ai::{EstimatedTokenCounter, ScriptedChatProvider, TokenCounter};
+  use uuid::Uuid;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn oversized_bucket_is_reviewed_in_chunks_that_fit() {
+    let scripted = Arc::new(ScriptedChatProvider::new());
+    for _ in 0..30 {
+      scripted.push_raw(
+        "primitive_segment_classification",
+        r#"{"classification": "informative"}"#,
+      );
+    }
+    let ai = AiClient::scripted(scripted);
+    // Leave about 300 tokens for the message lines of a split request
+    let prompt_tokens =
+      EstimatedTokenCounter.count_tokens(&primitive_split_system_prompt(ai.prompts()).text);
+    let context_tokens = ((prompt_tokens + SEGMENT_HEADER_TOKENS + 300) * 4).div_ceil(3);
+    let ai = ai.with_task_context_tokens(ChatTask::SegmentationSplit, context_tokens);
+    let budget = segment_budget(
+      ChatTask::SegmentationSplit,
+      &primitive_split_system_prompt(ai.prompts()).text,
+      &ai,
+    );
+
+    // One rule bucket of 30 messages, each about 80 tokens
+    let conversation_id = Uuid::now_v7();
+    let start = Utc.with_ymd_and_hms(2026, 6, 15, 15, 0, 0).unwrap();
+    let messages = (0..30)
+      .map(|seq| ConversationMessage {
+        conversation_id,
+        seq,
+        role: "user".into(),
+        content: format!("message {seq} ").repeat(20),
+        timestamp: start + TimeDelta::minutes(seq),
+      })
+      .collect::<Vec<_>>();
+    let bucket = CandidateSegment {
+      start_seq: 0,
+      end_seq: 29,
+    };
+
+    let segments = classify_or_split_bucket(&messages, &bucket, &ai)
+      .await
+      .unwrap();
+    assert!(segments.len() > 1);
+    assert_eq!(segments.first().unwrap().start_seq, 0);
+    assert_eq!(segments.last().unwrap().end_seq, 29);
+    for pair in segments.windows(2) {
+      assert_eq!(pair[1].start_seq, pair[0].end_seq + 1);
+    }
+    for segment in &segments {
+      assert!(segment_tokens(&messages, segment, &budget).unwrap() <= budget.available());
+    }
+  }
+}
@@ -0,0 +1,22 @@
+use plastmem_shared::APP_ENV;
+
+/// pgvector cannot build HNSW indexes on `vector` columns wider than this.
+const HNSW_MAX_DIM: usize = 2000;
+
+/// Column type for embeddings, sized by `EMBEDDING_DIM`.
+pub fn embedding_column_type() -> String {
+  format!("vector({})", APP_ENV.embedding_dim)
+}
+
+/// Whether `EMBEDDING_DIM` allows an HNSW index. Wider columns fall back to exact scans.
+pub fn supports_hnsw_index(table: &str) -> bool {
+  let supported = APP_ENV.embedding_dim <= HNSW_MAX_DIM;
+  if !supported {
+    tracing::warn!(
+      table,
+      embedding_dim = APP_ENV.embedding_dim,
+      "EMBEDDING_DIM exceeds the pgvector HNSW limit of {HNSW_MAX_DIM}, skipping the vector index"
+    );
+  }
+  supported
+}
@@ -1,11 +1,18 @@
 pub use sea_orm_migration::*;
 
+mod embedding_dim;
+
 mod m20260417_01_create_conversation_message_table;
 mod m20260417_02_create_segmentation_state_table;
 mod m20260417_03_create_episode_span_table;
 mod m20260417_04_create_pending_review_queue_table;
 mod m20260417_05_create_episodic_memory_table;
 mod m20260417_06_create_semantic_memory_table;
+mod m20260417_07_create_embedding_cache_table;
+mod m20260417_08_create_llm_call_table;
+mod m20260417_09_create_conversation_usage_table;
+mod m20260417_10_create_prompt_template_table;
+mod m20260417_11_create_conversation_settings_table;
 
 pub struct Migrator;
 
@@ -19,6 +26,11 @@ impl MigratorTrait for Migrator {
       Box::new(m20260417_04_create_pending_review_queue_table::Migration),
       Box::new(m20260417_05_create_episodic_memory_table::Migration),
       Box::new(m20260417_06_create_semantic_memory_table::Migration),
+      Box::new(m20260417_07_create_embedding_cache_table::Migration),
+      Box::new(m20260417_08_create_llm_call_table::Migration),
+      Box::new(m20260417_09_create_conversation_usage_table::Migration),
+      Box::new(m20260417_10_create_prompt_template_table::Migration),
+      Box::new(m20260417_11_create_conversation_settings_table::Migration),
     ]
   }
 }
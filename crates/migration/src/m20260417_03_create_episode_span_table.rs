@@ -1,6 +1,6 @@
 use sea_orm_migration::{
   prelude::*,
-  schema::{big_integer, text, timestamp_with_time_zone, uuid},
+  schema::{big_integer, custom, text, timestamp_with_time_zone, uuid},
 };
 
 #[derive(DeriveMigrationName)]
@@ -18,6 +18,10 @@ impl MigrationTrait for Migration {
           .col(big_integer(EpisodeSpan::StartSeq).not_null())
           .col(big_integer(EpisodeSpan::EndSeq).not_null())
           .col(text(EpisodeSpan::Classification).not_null())
+          .col(custom(
+            EpisodeSpan::PromptVersions,
+            "JSONB NOT NULL DEFAULT '{}'",
+          ))
           .col(
             timestamp_with_time_zone(EpisodeSpan::CreatedAt)
               .not_null()
@@ -47,5 +51,6 @@ enum EpisodeSpan {
   StartSeq,
   EndSeq,
   Classification,
+  PromptVersions,
   CreatedAt,
 }
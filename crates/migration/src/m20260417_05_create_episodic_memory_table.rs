@@ -1,9 +1,11 @@
 use sea_orm_migration::{
   prelude::*,
-  schema::{custom, float, json_binary, text, timestamp_with_time_zone, uuid},
+  schema::{boolean, custom, float, json_binary, text, timestamp_with_time_zone, uuid},
   sea_orm::Statement,
 };
 
+use crate::embedding_dim::{embedding_column_type, supports_hnsw_index};
+
 #[derive(DeriveMigrationName)]
 pub struct Migration;
 
@@ -19,11 +21,21 @@ impl MigrationTrait for Migration {
           .col(uuid(EpisodicMemory::ConversationId).not_null())
           .col(json_binary(EpisodicMemory::Messages).not_null())
           .col(text(EpisodicMemory::Content).not_null())
-          .col(custom(EpisodicMemory::Embedding, "vector(1024)").not_null())
+          .col(custom(EpisodicMemory::Embedding, embedding_column_type()).not_null())
+          .col(text(EpisodicMemory::EmbeddingModel).not_null())
+          .col(custom(
+            EpisodicMemory::PromptVersions,
+            "JSONB NOT NULL DEFAULT '{}'",
+          ))
           .col(text(EpisodicMemory::Title).not_null())
           .col(float(EpisodicMemory::Stability).not_null())
           .col(float(EpisodicMemory::Difficulty).not_null())
           .col(float(EpisodicMemory::Surprise).not_null())
+          .col(
+            boolean(EpisodicMemory::IsFlashbulb)
+              .not_null()
+              .default(false),
+          )
           .col(text(EpisodicMemory::Classification).null())
           .col(timestamp_with_time_zone(EpisodicMemory::StartAt).not_null())
           .col(timestamp_with_time_zone(EpisodicMemory::EndAt).not_null())
@@ -44,13 +56,15 @@ impl MigrationTrait for Migration {
       ))
       .await?;
 
-    manager
-      .get_connection()
-      .execute_raw(Statement::from_string(
-        manager.get_database_backend(),
-        "CREATE INDEX IF NOT EXISTS idx_episodic_memory_embedding_hnsw ON episodic_memory USING hnsw (embedding vector_ip_ops);",
-      ))
-      .await?;
+    if supports_hnsw_index("episodic_memory") {
+      manager
+        .get_connection()
+        .execute_raw(Statement::from_string(
+          manager.get_database_backend(),
+          "CREATE INDEX IF NOT EXISTS idx_episodic_memory_embedding_hnsw ON episodic_memory USING hnsw (embedding vector_ip_ops);",
+        ))
+        .await?;
+    }
 
     manager
       .get_connection()
@@ -78,10 +92,13 @@ enum EpisodicMemory {
   Messages,
   Content,
   Embedding,
+  EmbeddingModel,
+  PromptVersions,
   Title,
   Stability,
   Difficulty,
   Surprise,
+  IsFlashbulb,
   Classification,
   StartAt,
   EndAt,
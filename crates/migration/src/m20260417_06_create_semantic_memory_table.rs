@@ -4,6 +4,8 @@ use sea_orm_migration::{
   sea_orm::Statement,
 };
 
+use crate::embedding_dim::{embedding_column_type, supports_hnsw_index};
+
 #[derive(DeriveMigrationName)]
 pub struct Migration;
 
@@ -29,7 +31,12 @@ impl MigrationTrait for Migration {
               .default(Expr::current_timestamp()),
           )
           .col(timestamp_with_time_zone(SemanticMemory::InvalidAt).null())
-          .col(custom(SemanticMemory::Embedding, "vector(1024)").not_null())
+          .col(custom(SemanticMemory::Embedding, embedding_column_type()).not_null())
+          .col(text(SemanticMemory::EmbeddingModel).not_null())
+          .col(custom(
+            SemanticMemory::PromptVersions,
+            "JSONB NOT NULL DEFAULT '{}'",
+          ))
           .col(
             timestamp_with_time_zone(SemanticMemory::CreatedAt)
               .not_null()
@@ -39,13 +46,15 @@ impl MigrationTrait for Migration {
       )
       .await?;
 
-    manager
-      .get_connection()
-      .execute_raw(Statement::from_string(
-        manager.get_database_backend(),
-        "CREATE INDEX IF NOT EXISTS idx_semantic_memory_embedding ON semantic_memory USING hnsw (embedding vector_ip_ops);",
-      ))
-      .await?;
+    if supports_hnsw_index("semantic_memory") {
+      manager
+        .get_connection()
+        .execute_raw(Statement::from_string(
+          manager.get_database_backend(),
+          "CREATE INDEX IF NOT EXISTS idx_semantic_memory_embedding ON semantic_memory USING hnsw (embedding vector_ip_ops);",
+        ))
+        .await?;
+    }
 
     manager
       .get_connection()
@@ -84,5 +93,7 @@ enum SemanticMemory {
   ValidAt,
   InvalidAt,
   Embedding,
+  EmbeddingModel,
+  PromptVersions,
   CreatedAt,
 }
@@ -0,0 +1,70 @@
+use sea_orm_migration::{
+  prelude::*,
+  schema::{custom, integer, text, timestamp_with_time_zone},
+  sea_orm::Statement,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(EmbeddingCache::Table)
+          .if_not_exists()
+          .col(text(EmbeddingCache::Model).not_null())
+          .col(integer(EmbeddingCache::Dim).not_null())
+          .col(text(EmbeddingCache::ContentHash).not_null())
+          // Raw provider output; the dimension is part of the key, so the column is unbounded
+          .col(custom(EmbeddingCache::Embedding, "vector").not_null())
+          .col(
+            timestamp_with_time_zone(EmbeddingCache::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .col(
+            timestamp_with_time_zone(EmbeddingCache::LastUsedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .primary_key(
+            Index::create()
+              .col(EmbeddingCache::Model)
+              .col(EmbeddingCache::Dim)
+              .col(EmbeddingCache::ContentHash),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .get_connection()
+      .execute_raw(Statement::from_string(
+        manager.get_database_backend(),
+        "CREATE INDEX IF NOT EXISTS idx_embedding_cache_last_used_at ON embedding_cache (last_used_at);",
+      ))
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(EmbeddingCache::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(Iden)]
+enum EmbeddingCache {
+  Table,
+  Model,
+  Dim,
+  ContentHash,
+  Embedding,
+  CreatedAt,
+  LastUsedAt,
+}
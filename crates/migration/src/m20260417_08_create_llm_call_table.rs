@@ -0,0 +1,83 @@
+use sea_orm_migration::{
+  prelude::*,
+  schema::{big_integer, integer, json_binary, text, timestamp_with_time_zone, uuid},
+  sea_orm::Statement,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(LlmCall::Table)
+          .if_not_exists()
+          .col(uuid(LlmCall::Id).primary_key())
+          .col(text(LlmCall::Task).not_null())
+          .col(text(LlmCall::Job).null())
+          .col(uuid(LlmCall::ConversationId).null())
+          .col(uuid(LlmCall::EpisodeId).null())
+          .col(text(LlmCall::Model).not_null())
+          .col(json_binary(LlmCall::RequestMessages).not_null())
+          .col(text(LlmCall::Response).null())
+          .col(text(LlmCall::Reasoning).null())
+          .col(text(LlmCall::Error).null())
+          .col(big_integer(LlmCall::LatencyMs).not_null())
+          .col(integer(LlmCall::PromptTokens).null())
+          .col(integer(LlmCall::CompletionTokens).null())
+          .col(
+            timestamp_with_time_zone(LlmCall::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .to_owned(),
+      )
+      .await?;
+
+    manager
+      .get_connection()
+      .execute_raw(Statement::from_string(
+        manager.get_database_backend(),
+        "CREATE INDEX IF NOT EXISTS idx_llm_call_conversation_created_at ON llm_call (conversation_id, created_at);",
+      ))
+      .await?;
+
+    manager
+      .get_connection()
+      .execute_raw(Statement::from_string(
+        manager.get_database_backend(),
+        "CREATE INDEX IF NOT EXISTS idx_llm_call_job_created_at ON llm_call (job, created_at);",
+      ))
+      .await?;
+
+    Ok(())
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(LlmCall::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(Iden)]
+enum LlmCall {
+  Table,
+  Id,
+  Task,
+  Job,
+  ConversationId,
+  EpisodeId,
+  Model,
+  RequestMessages,
+  Response,
+  Reasoning,
+  Error,
+  LatencyMs,
+  PromptTokens,
+  CompletionTokens,
+  CreatedAt,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::{
+  prelude::*,
+  schema::{big_integer, date, double, text, timestamp_with_time_zone, uuid},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(ConversationUsage::Table)
+          .if_not_exists()
+          .col(uuid(ConversationUsage::ConversationId).not_null())
+          .col(date(ConversationUsage::UsageDate).not_null())
+          .col(text(ConversationUsage::Stage).not_null())
+          .col(text(ConversationUsage::Model).not_null())
+          .col(
+            big_integer(ConversationUsage::PromptTokens)
+              .not_null()
+              .default(0),
+          )
+          .col(
+            big_integer(ConversationUsage::CompletionTokens)
+              .not_null()
+              .default(0),
+          )
+          .col(
+            big_integer(ConversationUsage::Requests)
+              .not_null()
+              .default(0),
+          )
+          .col(double(ConversationUsage::CostUsd).not_null().default(0.0))
+          .col(
+            timestamp_with_time_zone(ConversationUsage::UpdatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .primary_key(
+            Index::create()
+              .col(ConversationUsage::ConversationId)
+              .col(ConversationUsage::UsageDate)
+              .col(ConversationUsage::Stage)
+              .col(ConversationUsage::Model),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(ConversationUsage::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(Iden)]
+enum ConversationUsage {
+  Table,
+  ConversationId,
+  UsageDate,
+  Stage,
+  Model,
+  PromptTokens,
+  CompletionTokens,
+  Requests,
+  CostUsd,
+  UpdatedAt,
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::{
+  prelude::*,
+  schema::{boolean, text, timestamp_with_time_zone},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(PromptTemplate::Table)
+          .if_not_exists()
+          .col(text(PromptTemplate::Name).not_null())
+          .col(text(PromptTemplate::Version).not_null())
+          .col(text(PromptTemplate::Template).not_null())
+          .col(boolean(PromptTemplate::Active).not_null().default(true))
+          .col(
+            timestamp_with_time_zone(PromptTemplate::CreatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .primary_key(
+            Index::create()
+              .col(PromptTemplate::Name)
+              .col(PromptTemplate::Version),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(PromptTemplate::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(Iden)]
+enum PromptTemplate {
+  Table,
+  Name,
+  Version,
+  Template,
+  Active,
+  CreatedAt,
+}
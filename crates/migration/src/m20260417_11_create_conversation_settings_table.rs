@@ -0,0 +1,44 @@
+use sea_orm_migration::{
+  prelude::*,
+  schema::{text, timestamp_with_time_zone, uuid},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+  async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .create_table(
+        Table::create()
+          .table(ConversationSettings::Table)
+          .if_not_exists()
+          .col(uuid(ConversationSettings::ConversationId).primary_key())
+          .col(text(ConversationSettings::Language).not_null())
+          .col(text(ConversationSettings::OutputTemplate).null())
+          .col(
+            timestamp_with_time_zone(ConversationSettings::UpdatedAt)
+              .not_null()
+              .default(Expr::current_timestamp()),
+          )
+          .to_owned(),
+      )
+      .await
+  }
+
+  async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+    manager
+      .drop_table(Table::drop().table(ConversationSettings::Table).to_owned())
+      .await
+  }
+}
+
+#[derive(Iden)]
+enum ConversationSettings {
+  Table,
+  ConversationId,
+  Language,
+  OutputTemplate,
+  UpdatedAt,
+}
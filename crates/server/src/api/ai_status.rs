@@ -0,0 +1,31 @@
+use axum::{Json, extract::State};
+use plastmem_ai::BreakerStatus;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::utils::AppState;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AiStatus {
+  /// Breaker shared by every chat backend.
+  pub chat: BreakerStatus,
+  pub embedding: BreakerStatus,
+}
+
+/// State of the AI circuit breakers.
+///
+/// While a breaker is `open`, LLM-dependent jobs are paused and retrieval falls back to BM25.
+#[utoipa::path(
+  get,
+  path = "/api/v0/ai_status",
+  responses(
+    (status = 200, description = "Chat and embedding circuit breaker state", body = AiStatus)
+  )
+)]
+#[axum::debug_handler]
+pub async fn ai_status(State(state): State<AppState>) -> Json<AiStatus> {
+  Json(AiStatus {
+    chat: state.ai.chat_breaker().status(),
+    embedding: state.ai.embedding_breaker().status(),
+  })
+}
@@ -0,0 +1,74 @@
+use axum::{
+  Json,
+  extract::{Query, State},
+  http::StatusCode,
+};
+use plastmem_core::{ConversationSettings, get_conversation_settings, set_conversation_settings};
+use plastmem_shared::{APP_ENV, AppError};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::utils::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationSettingsQuery {
+  pub conversation_id: Uuid,
+}
+
+/// Settings of a conversation, or the server defaults when none are stored.
+#[utoipa::path(
+  get,
+  path = "/api/v0/conversation_settings",
+  params(
+    ("conversation_id" = Uuid, Query, description = "Conversation to read settings for")
+  ),
+  responses(
+    (status = 200, description = "Effective conversation settings", body = ConversationSettings),
+    (status = 400, description = "Invalid request")
+  )
+)]
+#[axum::debug_handler]
+pub async fn conversation_settings(
+  State(state): State<AppState>,
+  Query(query): Query<ConversationSettingsQuery>,
+) -> Result<Json<ConversationSettings>, AppError> {
+  let settings = get_conversation_settings(query.conversation_id, &state.db)
+    .await?
+    .unwrap_or(ConversationSettings {
+      conversation_id: query.conversation_id,
+      language: APP_ENV.default_language,
+      output_template: None,
+    });
+  Ok(Json(settings))
+}
+
+/// Replace the settings of a conversation. Memories already created keep their language.
+#[utoipa::path(
+  put,
+  path = "/api/v0/conversation_settings",
+  request_body = ConversationSettings,
+  responses(
+    (status = 200, description = "Settings stored", body = ConversationSettings),
+    (status = 400, description = "Invalid request or unknown output template")
+  )
+)]
+#[axum::debug_handler]
+#[tracing::instrument(skip(state), fields(conversation_id = %payload.conversation_id))]
+pub async fn update_conversation_settings(
+  State(state): State<AppState>,
+  Json(payload): Json<ConversationSettings>,
+) -> Result<Json<ConversationSettings>, AppError> {
+  if let Some(name) = payload.output_template.as_deref()
+    && state.output_templates.get(name).is_none()
+  {
+    return Err(AppError::with_status(
+      StatusCode::BAD_REQUEST,
+      anyhow::anyhow!(
+        "Unknown output template {name:?}, expected one of {:?}",
+        state.output_templates.names()
+      ),
+    ));
+  }
+  set_conversation_settings(&payload, &state.db).await?;
+  Ok(Json(payload))
+}
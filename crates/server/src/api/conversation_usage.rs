@@ -0,0 +1,39 @@
+use axum::{
+  Json,
+  extract::{Query, State},
+};
+use chrono::NaiveDate;
+use plastmem_core::{ConversationUsage, list_conversation_usage};
+use plastmem_shared::AppError;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::utils::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ConversationUsageQuery {
+  pub conversation_id: Uuid,
+  pub since: Option<NaiveDate>,
+}
+
+/// Token usage and cost of a conversation, per UTC day, pipeline stage and model.
+#[utoipa::path(
+  get,
+  path = "/api/v0/conversation_usage",
+  params(
+    ("conversation_id" = Uuid, Query, description = "Conversation to report on"),
+    ("since" = Option<NaiveDate>, Query, description = "First UTC day to include, e.g. `2026-01-01`")
+  ),
+  responses(
+    (status = 200, description = "Aggregated usage, newest day first", body = Vec<ConversationUsage>),
+    (status = 400, description = "Invalid request")
+  )
+)]
+#[axum::debug_handler]
+pub async fn conversation_usage(
+  State(state): State<AppState>,
+  Query(query): Query<ConversationUsageQuery>,
+) -> Result<Json<Vec<ConversationUsage>>, AppError> {
+  let usage = list_conversation_usage(query.conversation_id, query.since, &state.db).await?;
+  Ok(Json(usage))
+}
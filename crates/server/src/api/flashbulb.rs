@@ -0,0 +1,58 @@
+use axum::{Json, extract::State, http::StatusCode};
+use plastmem_core::EpisodicMemory;
+use plastmem_shared::AppError;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::utils::AppState;
+
+const fn default_is_flashbulb() -> bool {
+  true
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MarkFlashbulb {
+  /// Conversation the episode belongs to
+  pub conversation_id: Uuid,
+  /// Episode to mark
+  pub episode_id: Uuid,
+  /// `false` turns an episode back into an ordinary one (default: true)
+  #[serde(default = "default_is_flashbulb")]
+  pub is_flashbulb: bool,
+}
+
+/// Mark an episode as a flashbulb memory ("remember this forever").
+///
+/// Flashbulb episodes keep a high retrievability floor and are no longer
+/// reviewed, so their FSRS state stays as it is.
+#[utoipa::path(
+  put,
+  path = "/api/v0/episodic_memory/flashbulb",
+  request_body = MarkFlashbulb,
+  responses(
+    (status = 200, description = "Updated episode", body = EpisodicMemory),
+    (status = 404, description = "Episode not found in the conversation")
+  )
+)]
+#[axum::debug_handler]
+#[tracing::instrument(skip(state), fields(conversation_id = %payload.conversation_id))]
+pub async fn mark_flashbulb(
+  State(state): State<AppState>,
+  Json(payload): Json<MarkFlashbulb>,
+) -> Result<Json<EpisodicMemory>, AppError> {
+  let episode = EpisodicMemory::set_flashbulb(
+    payload.episode_id,
+    payload.conversation_id,
+    payload.is_flashbulb,
+    &state.db,
+  )
+  .await?
+  .ok_or_else(|| {
+    AppError::with_status(
+      StatusCode::NOT_FOUND,
+      anyhow::anyhow!("Episode {} not found", payload.episode_id),
+    )
+  })?;
+  Ok(Json(episode))
+}
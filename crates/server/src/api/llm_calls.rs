@@ -0,0 +1,57 @@
+use axum::{
+  Json,
+  extract::{Query, State},
+};
+use plastmem_core::{LlmCall, LlmCallFilter, list_llm_calls};
+use plastmem_shared::AppError;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::utils::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct LlmCallsQuery {
+  pub conversation_id: Option<Uuid>,
+  pub episode_id: Option<Uuid>,
+  pub job: Option<String>,
+  pub task: Option<String>,
+  #[serde(default = "default_limit")]
+  pub limit: u64,
+}
+
+const fn default_limit() -> u64 {
+  50
+}
+
+/// List recorded LLM calls, newest first.
+///
+/// Calls are only recorded while `LLM_CALL_LOG_ENABLED` is set.
+#[utoipa::path(
+  get,
+  path = "/api/v0/llm_calls",
+  params(
+    ("conversation_id" = Option<Uuid>, Query, description = "Only calls made for this conversation"),
+    ("episode_id" = Option<Uuid>, Query, description = "Only calls made for this episode"),
+    ("job" = Option<String>, Query, description = "Only calls made by this worker job, e.g. `predict_calibrate`"),
+    ("task" = Option<String>, Query, description = "Only calls for this pipeline stage, e.g. `consolidation`"),
+    ("limit" = Option<u64>, Query, description = "Maximum calls to return (default: 50, max: 500)")
+  ),
+  responses(
+    (status = 200, description = "Recorded LLM calls", body = Vec<LlmCall>),
+    (status = 400, description = "Invalid request")
+  )
+)]
+#[axum::debug_handler]
+pub async fn llm_calls(
+  State(state): State<AppState>,
+  Query(query): Query<LlmCallsQuery>,
+) -> Result<Json<Vec<LlmCall>>, AppError> {
+  let filter = LlmCallFilter {
+    conversation_id: query.conversation_id,
+    episode_id: query.episode_id,
+    job: query.job,
+    task: query.task,
+  };
+  let calls = list_llm_calls(&filter, query.limit.clamp(1, 500), &state.db).await?;
+  Ok(Json(calls))
+}
@@ -6,18 +6,25 @@ use utoipa_scalar::{Scalar, Servable};
 use crate::utils::AppState;
 
 mod add_message;
+mod ai_status;
 #[cfg(debug_assertions)]
 mod benchmark;
+mod conversation_settings;
+mod conversation_usage;
+mod flashbulb;
+mod llm_calls;
 mod recent_memory;
 mod retrieve_memory;
 
 pub use add_message::{IngestMessageResult, InputConversationMessage, InputConversationMessages, InputMessage};
+pub use ai_status::AiStatus;
 #[cfg(debug_assertions)]
 pub use benchmark::BenchmarkJobStatus;
+pub use flashbulb::MarkFlashbulb;
 pub use recent_memory::RecentMemory;
 pub use retrieve_memory::{
-  ContextPreRetrieve, EpisodicMemoryResult, RetrieveMemory, RetrieveMemoryRawResult,
-  SemanticMemoryResult,
+  ContextPreRetrieve, EpisodicMemoryResult, RetrieveMemory, RetrieveMemoryRaw,
+  RetrieveMemoryRawResult, SemanticMemoryResult,
 };
 
 pub fn app() -> Router<AppState> {
@@ -28,7 +35,15 @@ pub fn app() -> Router<AppState> {
     .routes(routes!(recent_memory::recent_memory_raw))
     .routes(routes!(retrieve_memory::retrieve_memory))
     .routes(routes!(retrieve_memory::retrieve_memory_raw))
-    .routes(routes!(retrieve_memory::context_pre_retrieve));
+    .routes(routes!(retrieve_memory::context_pre_retrieve))
+    .routes(routes!(flashbulb::mark_flashbulb))
+    .routes(routes!(llm_calls::llm_calls))
+    .routes(routes!(conversation_usage::conversation_usage))
+    .routes(routes!(
+      conversation_settings::conversation_settings,
+      conversation_settings::update_conversation_settings
+    ))
+    .routes(routes!(ai_status::ai_status));
 
   #[cfg(debug_assertions)]
   let router = router
@@ -58,15 +73,25 @@ pub fn app() -> Router<AppState> {
     BenchmarkJobStatus,
     RecentMemory,
     RetrieveMemory,
+    RetrieveMemoryRaw,
     ContextPreRetrieve,
+    MarkFlashbulb,
     RetrieveMemoryRawResult,
     EpisodicMemoryResult,
     SemanticMemoryResult,
     plastmem_core::EpisodicMemory,
     plastmem_core::SemanticMemory,
+    plastmem_core::ScoreExplanation,
+    plastmem_core::LlmCall,
+    plastmem_core::ConversationUsage,
+    plastmem_core::ConversationSettings,
     plastmem_core::DetailLevel,
     plastmem_shared::Message,
     plastmem_shared::MessageRole,
+    plastmem_shared::Language,
+    AiStatus,
+    plastmem_ai::BreakerStatus,
+    plastmem_ai::BreakerState,
   ))
 )]
 pub struct ApiDoc;
@@ -82,15 +107,25 @@ pub struct ApiDoc;
     IngestMessageResult,
     RecentMemory,
     RetrieveMemory,
+    RetrieveMemoryRaw,
     ContextPreRetrieve,
+    MarkFlashbulb,
     RetrieveMemoryRawResult,
     EpisodicMemoryResult,
     SemanticMemoryResult,
     plastmem_core::EpisodicMemory,
     plastmem_core::SemanticMemory,
+    plastmem_core::ScoreExplanation,
+    plastmem_core::LlmCall,
+    plastmem_core::ConversationUsage,
+    plastmem_core::ConversationSettings,
     plastmem_core::DetailLevel,
     plastmem_shared::Message,
     plastmem_shared::MessageRole,
+    plastmem_shared::Language,
+    AiStatus,
+    plastmem_ai::BreakerStatus,
+    plastmem_ai::BreakerState,
   ))
 )]
 pub struct ApiDoc;
@@ -1,9 +1,10 @@
 use std::fmt::Write;
 
 use axum::{Json, extract::State};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use chrono_humanize::HumanTime;
-use plastmem_core::EpisodicMemory;
+use plastmem_ai::TokenBudget;
+use plastmem_core::{EpisodicMemory, Packed, TokenPacker};
 use plastmem_entities::episodic_memory;
 use plastmem_shared::AppError;
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
@@ -23,6 +24,8 @@ pub struct RecentMemory {
   /// Maximum memories to return (default: 10, max: 100)
   #[serde(default = "default_limit")]
   pub limit: u64,
+  /// Token budget for the markdown output; older memories beyond it are trimmed or dropped
+  pub max_tokens: Option<usize>,
 }
 
 const fn default_limit() -> u64 {
@@ -73,6 +76,82 @@ pub async fn recent_memory_raw(
   Ok(Json(memories))
 }
 
+/// Render recent memories (newest first) as markdown, fitting `budget` when given.
+///
+/// Whole memories are kept while they fit; the first one that does not is
+/// trimmed if enough budget is left, and it and all older ones are dropped otherwise.
+fn format_recent(
+  memories: &[EpisodicMemory],
+  budget: Option<&TokenBudget>,
+  now: DateTime<Utc>,
+) -> String {
+  if memories.is_empty() {
+    return "No recent memories found.\n".to_owned();
+  }
+
+  let mut out = String::from("## Recent Memories\n\n");
+  let blocks = memories.iter().map(|mem| {
+    let key_moment = if mem.is_flashbulb || mem.surprise >= 0.7 {
+      " (key moment)"
+    } else {
+      ""
+    };
+    let header = if mem.title.is_empty() {
+      "Memory".to_string()
+    } else {
+      mem.title.clone()
+    };
+    let time_str = HumanTime::from(now.signed_duration_since(mem.created_at));
+    format!(
+      "### {header}{key_moment}\n**When:** {time_str}\n**Content:** {}\n\n",
+      mem.content
+    )
+  });
+
+  let Some(budget) = budget else {
+    out.extend(blocks);
+    return out.trim_end().to_string();
+  };
+
+  let mut packer = TokenPacker::new(budget);
+  packer.reserve(&out);
+  let mut kept = 0;
+  let mut trimmed = false;
+  for block in blocks {
+    match packer.pack_or_trim(block.clone(), |max_tokens| {
+      format!(
+        "{}\n\n",
+        budget.truncate(block.trim_end(), max_tokens.saturating_sub(2))
+      )
+    }) {
+      Packed::Whole(block) => out.push_str(&block),
+      Packed::Trimmed(block) => {
+        out.push_str(&block);
+        trimmed = true;
+        kept += 1;
+        break;
+      }
+      Packed::Dropped => break,
+    }
+    kept += 1;
+  }
+
+  let mut omitted = Vec::new();
+  if kept < memories.len() {
+    let dropped = memories.len() - kept;
+    let noun = if dropped == 1 { "memory" } else { "memories" };
+    omitted.push(format!("dropped {dropped} older {noun}"));
+  }
+  if trimmed {
+    omitted.push("trimmed 1 memory".to_owned());
+  }
+  if !omitted.is_empty() {
+    let _ = writeln!(out, "_To fit max_tokens: {}._", omitted.join("; "));
+  }
+
+  out.trim_end().to_string()
+}
+
 /// Retrieve recent memories formatted as markdown for LLM consumption.
 /// Returns only episode content, no full message details.
 #[utoipa::path(
@@ -89,34 +168,63 @@ pub async fn recent_memory(
   Json(payload): Json<RecentMemory>,
 ) -> Result<String, AppError> {
   let memories = fetch_recent(&payload, sanitize_limit(payload.limit), &state.db).await?;
+  let budget = payload
+    .max_tokens
+    .map(|max_tokens| state.ai.output_budget(max_tokens));
+  Ok(format_recent(&memories, budget.as_ref(), Utc::now()))
+}
 
-  let now = Utc::now();
-  let mut out = String::new();
-
-  if memories.is_empty() {
-    let _ = writeln!(out, "No recent memories found.");
-    return Ok(out);
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use plastmem_ai::{EstimatedTokenCounter, PromptVersions};
+  use sea_orm::prelude::PgVector;
+
+  use super::*;
+
+  fn memory(index: i64, now: DateTime<Utc>) -> EpisodicMemory {
+    let created_at = now - Duration::days(index);
+    EpisodicMemory {
+      id: Uuid::now_v7(),
+      conversation_id: Uuid::nil(),
+      messages: Vec::new(),
+      title: format!("Memory {index}"),
+      content: format!("story {index} ").repeat(30),
+      classification: None,
+      embedding: PgVector::from(vec![0.0; 4]),
+      embedding_model: String::new(),
+      prompt_versions: PromptVersions::new(),
+      stability: 1.0,
+      difficulty: 5.0,
+      surprise: 0.0,
+      is_flashbulb: false,
+      start_at: created_at,
+      end_at: created_at,
+      created_at,
+      last_reviewed_at: created_at,
+      consolidated_at: None,
+    }
   }
 
-  let _ = writeln!(out, "## Recent Memories\n");
-
-  for mem in memories {
-    let key_moment = if mem.surprise >= 0.7 {
-      " (key moment)"
-    } else {
-      ""
-    };
-    let header = if mem.title.is_empty() {
-      "Memory".to_string()
-    } else {
-      mem.title.clone()
-    };
-    let time_str = HumanTime::from(now.signed_duration_since(mem.created_at));
-
-    let _ = writeln!(out, "### {header}{key_moment}");
-    let _ = writeln!(out, "**When:** {time_str}");
-    let _ = writeln!(out, "**Content:** {}\n", mem.content);
+  #[test]
+  fn budget_keeps_the_newest_memories_and_trims_the_first_that_does_not_fit() {
+    let now = Utc::now();
+    let memories = (0..4).map(|i| memory(i, now)).collect::<Vec<_>>();
+    let counter = Arc::new(EstimatedTokenCounter);
+
+    let unbudgeted = format_recent(&memories, None, now);
+    assert_eq!(unbudgeted.matches("### Memory").count(), 4);
+    let roomy = TokenBudget::exact(10_000, counter.clone());
+    assert_eq!(format_recent(&memories, Some(&roomy), now), unbudgeted);
+
+    let tight = TokenBudget::exact(300, counter);
+    let rendered = format_recent(&memories, Some(&tight), now);
+    assert!(rendered.contains("### Memory 1"));
+    assert!(rendered.contains("### Memory 2"));
+    assert!(rendered.contains(" [...]"));
+    assert!(!rendered.contains("### Memory 3"));
+    assert!(rendered.ends_with("_To fit max_tokens: dropped 1 older memory; trimmed 1 memory._"));
+    assert!(tight.count(&rendered) <= 300);
   }
-
-  Ok(out.trim_end().to_string())
 }
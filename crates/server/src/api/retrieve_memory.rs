@@ -1,7 +1,9 @@
-use axum::{Json, extract::State};
-use plastmem_ai::embed;
+use axum::{Json, extract::State, http::StatusCode};
+use plastmem_ai::{AiClient, CallContext, EmbeddingKind, embed};
 use plastmem_core::{
-  DetailLevel, EpisodicMemory, SemanticMemory, add_pending_review_item, format_tool_result,
+  DetailLevel, EpisodicMemory, FittedToolResult, OutputTemplate, RetrievalProfile,
+  ScoreExplanation, SemanticMemory, add_pending_review_item, format_tool_result,
+  format_tool_result_within, get_conversation_settings,
 };
 use plastmem_shared::{APP_ENV, AppError};
 use sea_orm::prelude::PgVector;
@@ -34,7 +36,7 @@ pub struct RetrieveMemory {
   pub conversation_id: Uuid,
   /// Search query text
   pub query: String,
-  /// Optional precomputed embedding for the query
+  /// Optional precomputed embedding for the query, from the active embedding model
   pub query_embedding: Option<Vec<f32>>,
   /// Maximum episodic memories to return (1-100)
   #[serde(default = "default_episodic_limit")]
@@ -47,69 +49,215 @@ pub struct RetrieveMemory {
   pub detail: DetailLevel,
   /// Optional category filter, e.g. "guideline", "preference"
   pub category: Option<String>,
+  /// Token budget for the rendered output; memories beyond it are trimmed or dropped
+  pub max_tokens: Option<usize>,
+  /// Output template: `markdown`, `xml`, `json_lines` or a loaded one; defaults to the conversation's setting
+  pub template: Option<String>,
+  /// Retrieval profile, e.g. `default`; defaults to `RETRIEVAL_PROFILE`
+  pub profile: Option<String>,
 }
 
-/// Fetch both memory types and record a pending review for episodic results.
-async fn fetch_memory(
+/// Client whose query embeddings are billed to `conversation_id`.
+fn query_ai(state: &AppState, conversation_id: Uuid) -> AiClient {
+  state.ai.with_call_context(CallContext {
+    job: Some("retrieve_memory"),
+    conversation_id: Some(conversation_id),
+    episode_id: None,
+  })
+}
+
+/// Embed the query unless the caller supplied an embedding.
+///
+/// If embedding fails, e.g. while the embedding backend's circuit breaker is open,
+/// retrieval degrades to BM25 only instead of failing the request.
+async fn query_embedding_or_bm25(
   state: &AppState,
   conversation_id: Uuid,
   query: &str,
   query_embedding: Option<&Vec<f32>>,
-  episodic_limit: u64,
-  semantic_limit: u64,
-  category: Option<&str>,
-) -> Result<(Vec<(SemanticMemory, f64)>, Vec<(EpisodicMemory, f64)>), AppError> {
-  let query_embedding = match query_embedding {
-    Some(embedding) => PgVector::from(embedding.clone()),
-    None => embed(query).await?,
-  };
+) -> Option<PgVector> {
+  if let Some(embedding) = query_embedding {
+    return Some(PgVector::from(embedding.clone()));
+  }
+  let ai = query_ai(state, conversation_id);
+  embed(EmbeddingKind::Query, query, &ai)
+    .await
+    .inspect_err(|err| {
+      tracing::warn!(error = %err, %conversation_id, "query embedding failed, falling back to BM25-only retrieval");
+    })
+    .ok()
+}
+
+type Explained<T> = Vec<(T, ScoreExplanation)>;
+
+/// Fetch both memory types.
+async fn fetch_memory(
+  state: &AppState,
+  payload: &RetrieveMemory,
+  profile: &RetrievalProfile,
+) -> Result<(Explained<SemanticMemory>, Explained<EpisodicMemory>), AppError> {
+  let conversation_id = payload.conversation_id;
+  let query = payload.query.as_str();
+  let query_embedding = query_embedding_or_bm25(
+    state,
+    conversation_id,
+    query,
+    payload.query_embedding.as_ref(),
+  )
+  .await;
   let (semantic, episodic) = tokio::try_join!(
-    SemanticMemory::retrieve_by_embedding(
+    SemanticMemory::retrieve_explained(
       query,
       query_embedding.clone(),
-      sanitize_limit(semantic_limit),
+      state.ai.embedding().model(),
+      sanitize_limit(payload.semantic_limit),
       conversation_id,
       &state.db,
-      category,
+      payload.category.as_deref(),
+      profile,
     ),
-    EpisodicMemory::retrieve_by_embedding(
+    EpisodicMemory::retrieve_explained(
       query,
       query_embedding,
-      episodic_limit,
+      state.ai.embedding().model(),
+      payload.episodic_limit,
       conversation_id,
       &state.db,
+      profile,
     ),
   )?;
-  if APP_ENV.enable_fsrs_review && !episodic.is_empty() {
-    let memory_ids = episodic.iter().map(|(m, _)| m.id).collect();
-    add_pending_review_item(conversation_id, memory_ids, query.to_owned(), &state.db).await?;
-  }
   Ok((semantic, episodic))
 }
 
+/// Record a pending review of the episodes the caller was shown.
+async fn record_pending_review(
+  state: &AppState,
+  payload: &RetrieveMemory,
+  memory_ids: Vec<Uuid>,
+) -> Result<(), AppError> {
+  if APP_ENV.enable_fsrs_review && !memory_ids.is_empty() {
+    add_pending_review_item(
+      payload.conversation_id,
+      memory_ids,
+      payload.query.clone(),
+      &state.db,
+    )
+    .await?;
+  }
+  Ok(())
+}
+
 async fn fetch_semantic_memory(
   state: &AppState,
-  conversation_id: Uuid,
-  query: &str,
-  query_embedding: Option<&Vec<f32>>,
-  semantic_limit: u64,
-  category: Option<&str>,
+  payload: &ContextPreRetrieve,
+  profile: &RetrievalProfile,
 ) -> Result<Vec<(SemanticMemory, f64)>, AppError> {
-  let query_embedding = match query_embedding {
-    Some(embedding) => PgVector::from(embedding.clone()),
-    None => embed(query).await?,
-  };
+  let query_embedding = query_embedding_or_bm25(
+    state,
+    payload.conversation_id,
+    &payload.query,
+    payload.query_embedding.as_ref(),
+  )
+  .await;
   SemanticMemory::retrieve_by_embedding(
-    query,
+    &payload.query,
     query_embedding,
-    sanitize_limit(semantic_limit),
-    conversation_id,
+    state.ai.embedding().model(),
+    sanitize_limit(payload.semantic_limit),
+    payload.conversation_id,
     &state.db,
-    category,
+    payload.category.as_deref(),
+    profile,
   )
   .await
 }
 
+/// Profile named by the request, else the configured default.
+fn resolve_profile<'a>(
+  state: &'a AppState,
+  name: Option<&str>,
+) -> Result<&'a RetrievalProfile, AppError> {
+  let Some(name) = name else {
+    return Ok(state.retrieval_profiles.default_profile());
+  };
+  state.retrieval_profiles.get(name).ok_or_else(|| {
+    AppError::with_status(
+      StatusCode::BAD_REQUEST,
+      anyhow::anyhow!(
+        "Unknown retrieval profile {name:?}, expected one of {:?}",
+        state.retrieval_profiles.names()
+      ),
+    )
+  })
+}
+
+/// Drop the score breakdowns, keeping the final scores.
+fn scores<T>(results: Explained<T>) -> Vec<(T, f64)> {
+  results
+    .into_iter()
+    .map(|(memory, explanation)| (memory, explanation.score))
+    .collect()
+}
+
+/// Template named by the request, else by the conversation's settings, else the default.
+///
+/// An unknown name in the request is rejected; an unknown name in the settings,
+/// e.g. of a template file since removed, falls back to the default.
+async fn resolve_template<'a>(
+  state: &'a AppState,
+  conversation_id: Uuid,
+  name: Option<&str>,
+) -> Result<&'a OutputTemplate, AppError> {
+  if let Some(name) = name {
+    return state.output_templates.get(name).ok_or_else(|| {
+      AppError::with_status(
+        StatusCode::BAD_REQUEST,
+        anyhow::anyhow!(
+          "Unknown output template {name:?}, expected one of {:?}",
+          state.output_templates.names()
+        ),
+      )
+    });
+  }
+  let stored = get_conversation_settings(conversation_id, &state.db)
+    .await?
+    .and_then(|settings| settings.output_template);
+  let template = stored.and_then(|name| {
+    let template = state.output_templates.get(&name);
+    if template.is_none() {
+      tracing::warn!(%conversation_id, template = %name, "unknown output template in conversation settings, using the default");
+    }
+    template
+  });
+  Ok(template.unwrap_or_else(|| state.output_templates.default_template()))
+}
+
+/// Render through `template`, fitting the output into `max_tokens` when given.
+fn render_tool_result(
+  state: &AppState,
+  semantic: &[(SemanticMemory, f64)],
+  episodic: &[(EpisodicMemory, f64)],
+  detail: &DetailLevel,
+  template: &OutputTemplate,
+  max_tokens: Option<usize>,
+) -> FittedToolResult {
+  max_tokens.map_or_else(
+    || FittedToolResult {
+      text: format_tool_result(semantic, episodic, detail, template),
+      episode_ids: episodic.iter().map(|(mem, _)| mem.id).collect(),
+    },
+    |max_tokens| {
+      format_tool_result_within(
+        semantic,
+        episodic,
+        detail,
+        template,
+        &state.ai.output_budget(max_tokens),
+      )
+    },
+  )
+}
+
 // --- Pre-retrieval context endpoint (no pending review) ---
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -123,9 +271,15 @@ pub struct ContextPreRetrieve {
   pub detail: DetailLevel,
   /// Optional category filter, e.g. "guideline", "preference"
   pub category: Option<String>,
+  /// Token budget for the rendered output; facts beyond it are dropped
+  pub max_tokens: Option<usize>,
+  /// Output template: `markdown`, `xml`, `json_lines` or a loaded one; defaults to the conversation's setting
+  pub template: Option<String>,
+  /// Retrieval profile, e.g. `default`; defaults to `RETRIEVAL_PROFILE`
+  pub profile: Option<String>,
 }
 
-/// Retrieve semantic memories as markdown for pre-retrieval context injection.
+/// Retrieve semantic memories, rendered for pre-retrieval context injection.
 /// Semantic-only (facts + behavioral guidelines); episodic retrieval is left to LLM tool calls.
 /// Does NOT record a pending review (no FSRS update triggered).
 #[utoipa::path(
@@ -133,8 +287,8 @@ pub struct ContextPreRetrieve {
   path = "/api/v0/context_pre_retrieve",
   request_body = ContextPreRetrieve,
   responses(
-    (status = 200, description = "Markdown context for system prompt injection", body = String),
-    (status = 400, description = "Query cannot be empty")
+    (status = 200, description = "Rendered context for system prompt injection", body = String),
+    (status = 400, description = "Query cannot be empty, or unknown output template or retrieval profile")
   )
 )]
 #[axum::debug_handler]
@@ -146,26 +300,43 @@ pub async fn context_pre_retrieve(
   if payload.query.is_empty() {
     return Err(AppError::new(anyhow::anyhow!("Query cannot be empty")));
   }
-  let semantic = fetch_semantic_memory(
-    &state,
-    payload.conversation_id,
-    &payload.query,
-    payload.query_embedding.as_ref(),
-    payload.semantic_limit,
-    payload.category.as_deref(),
+  let template =
+    resolve_template(&state, payload.conversation_id, payload.template.as_deref()).await?;
+  let profile = resolve_profile(&state, payload.profile.as_deref())?;
+  let semantic = fetch_semantic_memory(&state, &payload, profile).await?;
+  Ok(
+    render_tool_result(
+      &state,
+      &semantic,
+      &[],
+      &payload.detail,
+      template,
+      payload.max_tokens,
+    )
+    .text,
   )
-  .await?;
-  Ok(format_tool_result(&semantic, &[], &payload.detail))
 }
 
 // --- Raw JSON endpoint ---
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RetrieveMemoryRaw {
+  #[serde(flatten)]
+  pub retrieve: RetrieveMemory,
+  /// Break each score down by search leg and FSRS retrievability
+  #[serde(default)]
+  pub explain: bool,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct SemanticMemoryResult {
   #[serde(flatten)]
   pub memory: SemanticMemory,
   /// RRF score
   pub score: f64,
+  /// Score breakdown, with `explain`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub explanation: Option<ScoreExplanation>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -180,61 +351,73 @@ pub struct RetrieveMemoryRawResult {
 pub struct EpisodicMemoryResult {
   #[serde(flatten)]
   pub memory: EpisodicMemory,
-  /// Final score (RRF score × FSRS retrievability)
+  /// Final score (RRF score × FSRS retrievability multiplier)
   pub score: f64,
+  /// Score breakdown, with `explain`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub explanation: Option<ScoreExplanation>,
 }
 
 /// Retrieve memories in raw JSON format
+///
+/// With `explain`, each result also carries its BM25 and vector ranks, the RRF
+/// contribution of each leg and, for episodes, the FSRS retrievability.
 #[utoipa::path(
   post,
   path = "/api/v0/retrieve_memory/raw",
-  request_body = RetrieveMemory,
+  request_body = RetrieveMemoryRaw,
   responses(
     (status = 200, description = "Semantic facts and episodic memories", body = RetrieveMemoryRawResult),
-    (status = 400, description = "Query cannot be empty")
+    (status = 400, description = "Query cannot be empty or unknown retrieval profile")
   )
 )]
 #[axum::debug_handler]
-#[tracing::instrument(skip(state), fields(conversation_id = %payload.conversation_id))]
+#[tracing::instrument(skip(state), fields(conversation_id = %payload.retrieve.conversation_id))]
 pub async fn retrieve_memory_raw(
   State(state): State<AppState>,
-  Json(payload): Json<RetrieveMemory>,
+  Json(payload): Json<RetrieveMemoryRaw>,
 ) -> Result<Json<RetrieveMemoryRawResult>, AppError> {
+  let RetrieveMemoryRaw {
+    retrieve: payload,
+    explain,
+  } = payload;
   if payload.query.is_empty() {
     return Err(AppError::new(anyhow::anyhow!("Query cannot be empty")));
   }
-  let (semantic, episodic) = fetch_memory(
-    &state,
-    payload.conversation_id,
-    &payload.query,
-    payload.query_embedding.as_ref(),
-    payload.episodic_limit,
-    payload.semantic_limit,
-    payload.category.as_deref(),
-  )
-  .await?;
+  let profile = resolve_profile(&state, payload.profile.as_deref())?;
+  let (semantic, episodic) = fetch_memory(&state, &payload, profile).await?;
+  let memory_ids = episodic.iter().map(|(mem, _)| mem.id).collect();
+  record_pending_review(&state, &payload, memory_ids).await?;
   Ok(Json(RetrieveMemoryRawResult {
     semantic: semantic
       .into_iter()
-      .map(|(memory, score)| SemanticMemoryResult { memory, score })
+      .map(|(memory, explanation)| SemanticMemoryResult {
+        memory,
+        score: explanation.score,
+        explanation: explain.then_some(explanation),
+      })
       .collect(),
     episodic: episodic
       .into_iter()
-      .map(|(memory, score)| EpisodicMemoryResult { memory, score })
+      .map(|(memory, explanation)| EpisodicMemoryResult {
+        memory,
+        score: explanation.score,
+        explanation: explain.then_some(explanation),
+      })
       .collect(),
   }))
 }
 
 // --- Tool result (markdown) endpoint ---
 
-/// Retrieve memories formatted as markdown for LLM consumption
+/// Retrieve memories rendered for LLM consumption, as markdown unless another template is chosen
 #[utoipa::path(
   post,
   path = "/api/v0/retrieve_memory",
   request_body = RetrieveMemory,
   responses(
-    (status = 200, description = "Markdown formatted memory results", body = String),
-    (status = 400, description = "Query cannot be empty")
+    (status = 200, description = "Rendered memory results", body = String),
+    (status = 400, description = "Query cannot be empty, or unknown output template or retrieval profile")
   )
 )]
 #[axum::debug_handler]
@@ -246,15 +429,19 @@ pub async fn retrieve_memory(
   if payload.query.is_empty() {
     return Err(AppError::new(anyhow::anyhow!("Query cannot be empty")));
   }
-  let (semantic, episodic) = fetch_memory(
+  let template =
+    resolve_template(&state, payload.conversation_id, payload.template.as_deref()).await?;
+  let profile = resolve_profile(&state, payload.profile.as_deref())?;
+  let (semantic, episodic) = fetch_memory(&state, &payload, profile).await?;
+  // Only episodes that made it into the output were shown, so only they are reviewed
+  let rendered = render_tool_result(
     &state,
-    payload.conversation_id,
-    &payload.query,
-    payload.query_embedding.as_ref(),
-    payload.episodic_limit,
-    payload.semantic_limit,
-    payload.category.as_deref(),
-  )
-  .await?;
-  Ok(format_tool_result(&semantic, &episodic, &payload.detail))
+    &scores(semantic),
+    &scores(episodic),
+    &payload.detail,
+    template,
+    payload.max_tokens,
+  );
+  record_pending_review(&state, &payload, rendered.episode_ids).await?;
+  Ok(rendered.text)
 }
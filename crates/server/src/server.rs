@@ -10,13 +10,16 @@ use axum::response::Html;
 #[cfg(debug_assertions)]
 use axum::{Extension, response::Redirect};
 use axum::{Router, routing::get};
+use plastmem_ai::AiClient;
+use plastmem_core::{OutputTemplates, RetrievalProfiles};
 use plastmem_shared::AppError;
 use plastmem_worker::{
   EpisodeCreationJob, EventSegmentationJob, MemoryReviewJob, PredictCalibrateJob,
 };
 use sea_orm::DatabaseConnection;
+use std::sync::Arc;
 #[cfg(debug_assertions)]
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 use tokio::net::TcpListener;
 
 use crate::{
@@ -33,18 +36,24 @@ async fn handler() -> Html<&'static str> {
 
 pub async fn server(
   db: DatabaseConnection,
+  ai: AiClient,
   segment_job_storage: PostgresStorage<EventSegmentationJob>,
   episode_creation_job_storage: PostgresStorage<EpisodeCreationJob>,
   review_job_storage: PostgresStorage<MemoryReviewJob>,
   predict_calibrate_job_storage: PostgresStorage<PredictCalibrateJob>,
+  output_templates: OutputTemplates,
+  retrieval_profiles: RetrievalProfiles,
   #[cfg(debug_assertions)] board_broadcaster: Arc<Mutex<TracingBroadcaster>>,
 ) -> Result<(), AppError> {
   let app_state = AppState::new(
     db,
+    ai,
     segment_job_storage,
     episode_creation_job_storage,
     review_job_storage,
     predict_calibrate_job_storage,
+    Arc::new(output_templates),
+    Arc::new(retrieval_profiles),
   );
 
   let app = Router::new().merge(api::app());
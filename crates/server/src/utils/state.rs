@@ -1,4 +1,8 @@
+use std::sync::Arc;
+
 use apalis_postgres::PostgresStorage;
+use plastmem_ai::AiClient;
+use plastmem_core::{OutputTemplates, RetrievalProfiles};
 use sea_orm::DatabaseConnection;
 
 use plastmem_worker::{
@@ -8,10 +12,13 @@ use plastmem_worker::{
 #[derive(Clone)]
 pub struct AppState {
   pub db: DatabaseConnection,
+  pub ai: AiClient,
   pub segmentation_job_storage: PostgresStorage<EventSegmentationJob>,
   pub episode_creation_job_storage: PostgresStorage<EpisodeCreationJob>,
   pub review_job_storage: PostgresStorage<MemoryReviewJob>,
   pub predict_calibrate_job_storage: PostgresStorage<PredictCalibrateJob>,
+  pub output_templates: Arc<OutputTemplates>,
+  pub retrieval_profiles: Arc<RetrievalProfiles>,
 }
 
 impl AppState {
@@ -19,17 +26,23 @@ impl AppState {
   #[allow(clippy::missing_const_for_fn)]
   pub fn new(
     db: DatabaseConnection,
+    ai: AiClient,
     segmentation_job_storage: PostgresStorage<EventSegmentationJob>,
     episode_creation_job_storage: PostgresStorage<EpisodeCreationJob>,
     review_job_storage: PostgresStorage<MemoryReviewJob>,
     predict_calibrate_job_storage: PostgresStorage<PredictCalibrateJob>,
+    output_templates: Arc<OutputTemplates>,
+    retrieval_profiles: Arc<RetrievalProfiles>,
   ) -> Self {
     Self {
       db,
+      ai,
       segmentation_job_storage,
       episode_creation_job_storage,
       review_job_storage,
       predict_calibrate_job_storage,
+      output_templates,
+      retrieval_profiles,
     }
   }
 }
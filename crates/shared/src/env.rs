@@ -1,10 +1,19 @@
 use std::env;
 use std::sync::LazyLock;
 
+use crate::Language;
+
 fn required_env(key: &str) -> String {
   env::var(key).unwrap_or_else(|_| panic!("env {key} must be set"))
 }
 
+fn optional_env(key: &str) -> Option<String> {
+  env::var(key)
+    .ok()
+    .map(|value| value.trim().to_owned())
+    .filter(|value| !value.is_empty())
+}
+
 fn seed_env(key: &str) -> Option<i64> {
   env::var(key)
     .ok()
@@ -28,6 +37,20 @@ fn u64_env(key: &str, default: u64) -> u64 {
     .unwrap_or(default)
 }
 
+fn u32_env(key: &str, default: u32) -> u32 {
+  env::var(key)
+    .ok()
+    .and_then(|value| value.trim().parse::<u32>().ok())
+    .unwrap_or(default)
+}
+
+fn f64_env(key: &str, default: f64) -> f64 {
+  env::var(key)
+    .ok()
+    .and_then(|value| value.trim().parse::<f64>().ok())
+    .unwrap_or(default)
+}
+
 fn usize_env(key: &str, default: usize) -> usize {
   env::var(key)
     .ok()
@@ -35,16 +58,68 @@ fn usize_env(key: &str, default: usize) -> usize {
     .unwrap_or(default)
 }
 
+/// Settings for one chat backend.
+#[derive(Debug, Clone)]
+pub struct ChatModelEnv {
+  /// Wire protocol: `openai`, `ollama` or `messages`.
+  pub protocol: String,
+  pub base_url: String,
+  pub api_key: String,
+  pub model: String,
+  pub seed: Option<i64>,
+  /// Context window of the model, in tokens.
+  pub context_tokens: usize,
+  pub reasoning_effort: String,
+  pub structured_output: String,
+  pub timeout_seconds: u64,
+}
+
+#[allow(clippy::struct_excessive_bools)]
 pub struct AppEnv {
   pub database_url: String,
   pub openai_base_url: String,
   pub openai_api_key: String,
   pub openai_chat_model: String,
+  pub openai_chat_protocol: String,
   pub openai_chat_seed: Option<i64>,
+  pub openai_context_tokens: usize,
   pub openai_embedding_model: String,
+  pub embedding_dim: usize,
+  pub embedding_query_template: Option<String>,
+  pub embedding_document_template: Option<String>,
+  pub embedding_batch_window_ms: u64,
+  pub embedding_batch_max_inputs: usize,
+  pub embedding_backend: String,
+  pub local_embedding_model_dir: Option<String>,
+  pub local_embedding_pooling: String,
+  pub local_embedding_max_tokens: usize,
   pub openai_request_timeout_seconds: u64,
+  pub ai_max_in_flight: usize,
+  pub ai_requests_per_minute: u32,
+  pub ai_tokens_per_minute: u32,
+  pub ai_breaker_failure_threshold: u32,
+  pub ai_breaker_cooldown_seconds: u64,
+  pub ai_model_prices: Option<String>,
+  pub conversation_daily_token_budget: u64,
+  pub conversation_monthly_token_budget: u64,
+  pub conversation_daily_cost_budget_usd: f64,
+  pub conversation_monthly_cost_budget_usd: f64,
+  pub openai_reasoning_effort: String,
+  pub openai_structured_output: String,
+  pub log_reasoning_traces: bool,
+  pub llm_call_log_enabled: bool,
   pub enable_fsrs_review: bool,
   pub predict_calibrate_concurrency: usize,
+  pub embedding_cache_enabled: bool,
+  pub embedding_cache_max_entries: u64,
+  pub ai_fixtures_dir: Option<String>,
+  pub ai_fixtures_record: bool,
+  pub prompt_templates_dir: Option<String>,
+  pub prompt_tokenizer_path: Option<String>,
+  pub output_templates_dir: Option<String>,
+  pub retrieval_profiles: Option<String>,
+  pub retrieval_profile: Option<String>,
+  pub default_language: Language,
 }
 
 impl AppEnv {
@@ -58,13 +133,123 @@ impl AppEnv {
         .to_owned(),
       openai_api_key: required_env("OPENAI_API_KEY"),
       openai_chat_model: required_env("OPENAI_CHAT_MODEL"),
+      openai_chat_protocol: optional_env("OPENAI_CHAT_PROTOCOL")
+        .unwrap_or_else(|| "openai".to_owned()),
       openai_chat_seed: seed_env("OPENAI_CHAT_SEED"),
+      openai_context_tokens: usize_env("OPENAI_CONTEXT_TOKENS", 32_768),
       openai_embedding_model: required_env("OPENAI_EMBEDDING_MODEL"),
+      embedding_dim: usize_env("EMBEDDING_DIM", 1024),
+      embedding_query_template: optional_env("EMBEDDING_QUERY_TEMPLATE"),
+      embedding_document_template: optional_env("EMBEDDING_DOCUMENT_TEMPLATE"),
+      embedding_batch_window_ms: u64_env("EMBEDDING_BATCH_WINDOW_MS", 5),
+      embedding_batch_max_inputs: usize_env("EMBEDDING_BATCH_MAX_INPUTS", 64),
+      embedding_backend: optional_env("EMBEDDING_BACKEND").unwrap_or_else(|| "openai".to_owned()),
+      local_embedding_model_dir: optional_env("LOCAL_EMBEDDING_MODEL_DIR"),
+      local_embedding_pooling: optional_env("LOCAL_EMBEDDING_POOLING")
+        .unwrap_or_else(|| "mean".to_owned()),
+      local_embedding_max_tokens: usize_env("LOCAL_EMBEDDING_MAX_TOKENS", 512),
       openai_request_timeout_seconds: u64_env("OPENAI_REQUEST_TIMEOUT_SECONDS", 60),
+      ai_max_in_flight: usize_env("AI_MAX_IN_FLIGHT", 8),
+      ai_requests_per_minute: u32_env("AI_REQUESTS_PER_MINUTE", 0),
+      ai_tokens_per_minute: u32_env("AI_TOKENS_PER_MINUTE", 0),
+      ai_breaker_failure_threshold: u32_env("AI_BREAKER_FAILURE_THRESHOLD", 5),
+      ai_breaker_cooldown_seconds: u64_env("AI_BREAKER_COOLDOWN_SECONDS", 30),
+      ai_model_prices: optional_env("AI_MODEL_PRICES"),
+      conversation_daily_token_budget: u64_env("CONVERSATION_DAILY_TOKEN_BUDGET", 0),
+      conversation_monthly_token_budget: u64_env("CONVERSATION_MONTHLY_TOKEN_BUDGET", 0),
+      conversation_daily_cost_budget_usd: f64_env("CONVERSATION_DAILY_COST_BUDGET_USD", 0.0),
+      conversation_monthly_cost_budget_usd: f64_env("CONVERSATION_MONTHLY_COST_BUDGET_USD", 0.0),
+      openai_reasoning_effort: optional_env("OPENAI_REASONING_EFFORT")
+        .unwrap_or_else(|| "none".to_owned()),
+      openai_structured_output: optional_env("OPENAI_STRUCTURED_OUTPUT")
+        .unwrap_or_else(|| "strict_schema".to_owned()),
+      log_reasoning_traces: bool_env("LOG_REASONING_TRACES", false),
+      llm_call_log_enabled: bool_env("LLM_CALL_LOG_ENABLED", false),
       enable_fsrs_review: bool_env("ENABLE_FSRS_REVIEW", true),
       predict_calibrate_concurrency: usize_env("PREDICT_CALIBRATE_CONCURRENCY", 4),
+      embedding_cache_enabled: bool_env("EMBEDDING_CACHE_ENABLED", true),
+      embedding_cache_max_entries: u64_env("EMBEDDING_CACHE_MAX_ENTRIES", 200_000),
+      ai_fixtures_dir: optional_env("AI_FIXTURES_DIR"),
+      ai_fixtures_record: bool_env("AI_FIXTURES_RECORD", false),
+      prompt_templates_dir: optional_env("PROMPT_TEMPLATES_DIR"),
+      prompt_tokenizer_path: optional_env("PROMPT_TOKENIZER_PATH"),
+      output_templates_dir: optional_env("OUTPUT_TEMPLATES_DIR"),
+      retrieval_profiles: optional_env("RETRIEVAL_PROFILES"),
+      retrieval_profile: optional_env("RETRIEVAL_PROFILE"),
+      default_language: optional_env("DEFAULT_LANGUAGE")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default(),
     }
   }
 }
 
+impl AppEnv {
+  /// Default chat backend settings from `OPENAI_*`.
+  #[must_use]
+  pub fn chat_model_env(&self) -> ChatModelEnv {
+    ChatModelEnv {
+      protocol: self.openai_chat_protocol.clone(),
+      base_url: self.openai_base_url.clone(),
+      api_key: self.openai_api_key.clone(),
+      model: self.openai_chat_model.clone(),
+      seed: self.openai_chat_seed,
+      context_tokens: self.openai_context_tokens,
+      reasoning_effort: self.openai_reasoning_effort.clone(),
+      structured_output: self.openai_structured_output.clone(),
+      timeout_seconds: self.openai_request_timeout_seconds,
+    }
+  }
+
+  /// Chat backend overrides read from
+  /// `<prefix>{PROTOCOL,BASE_URL,API_KEY,MODEL,SEED,CONTEXT_TOKENS,REASONING_EFFORT,STRUCTURED_OUTPUT,TIMEOUT_SECONDS}`.
+  ///
+  /// Unset fields fall back to [`AppEnv::chat_model_env`]. Returns `None` when no
+  /// override is set, so callers can keep sharing the default backend.
+  #[must_use]
+  pub fn chat_model_env_override(&self, prefix: &str) -> Option<ChatModelEnv> {
+    let var = |name: &str| optional_env(&format!("{prefix}{name}"));
+    let protocol = var("PROTOCOL");
+    let base_url = var("BASE_URL");
+    let api_key = var("API_KEY");
+    let model = var("MODEL");
+    let seed = var("SEED");
+    let context_tokens = var("CONTEXT_TOKENS");
+    let reasoning_effort = var("REASONING_EFFORT");
+    let structured_output = var("STRUCTURED_OUTPUT");
+    let timeout_seconds = var("TIMEOUT_SECONDS");
+
+    if protocol.is_none()
+      && base_url.is_none()
+      && api_key.is_none()
+      && model.is_none()
+      && seed.is_none()
+      && context_tokens.is_none()
+      && reasoning_effort.is_none()
+      && structured_output.is_none()
+      && timeout_seconds.is_none()
+    {
+      return None;
+    }
+
+    let default = self.chat_model_env();
+    Some(ChatModelEnv {
+      protocol: protocol.unwrap_or(default.protocol),
+      base_url: base_url.map_or(default.base_url, |url| url.trim_end_matches('/').to_owned()),
+      api_key: api_key.unwrap_or(default.api_key),
+      model: model.unwrap_or(default.model),
+      seed: seed
+        .and_then(|value| value.parse::<i64>().ok())
+        .or(default.seed),
+      context_tokens: context_tokens
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(default.context_tokens),
+      reasoning_effort: reasoning_effort.unwrap_or(default.reasoning_effort),
+      structured_output: structured_output.unwrap_or(default.structured_output),
+      timeout_seconds: timeout_seconds
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(default.timeout_seconds),
+    })
+  }
+}
+
 pub static APP_ENV: LazyLock<AppEnv> = LazyLock::new(AppEnv::new);
@@ -43,6 +43,15 @@ impl AppError {
     self.status_code
   }
 
+  /// The underlying error, if it is an `E`.
+  #[must_use]
+  pub fn downcast_ref<E>(&self) -> Option<&E>
+  where
+    E: Display + std::fmt::Debug + Send + Sync + 'static,
+  {
+    self.err.downcast_ref()
+  }
+
   /// Get the captured span trace
   #[must_use]
   pub const fn span_trace(&self) -> &SpanTrace {
@@ -1,2 +1,36 @@
 /// Target retention probability (90%).
 pub const DESIRED_RETENTION: f32 = 0.9;
+
+/// Lowest retrieval multiplier of an ordinary episode, however long ago it was reviewed.
+pub const RETRIEVABILITY_FLOOR: f64 = 0.25;
+/// Lowest retrieval multiplier of a flashbulb episode; these still fade, but only this far.
+pub const FLASHBULB_RETRIEVABILITY_FLOOR: f64 = 0.7;
+/// Episodes at least this surprising are marked as flashbulb memories on creation.
+pub const FLASHBULB_SURPRISE_THRESHOLD: f32 = 0.85;
+
+/// Soft-mix FSRS retrievability into a retrieval score multiplier:
+/// `floor + (1 - floor) * retrievability`.
+#[must_use]
+pub fn retrievability_multiplier(retrievability: f32, is_flashbulb: bool) -> f64 {
+  let floor = if is_flashbulb {
+    FLASHBULB_RETRIEVABILITY_FLOOR
+  } else {
+    RETRIEVABILITY_FLOOR
+  };
+  (1.0 - floor).mul_add(f64::from(retrievability), floor)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn flashbulb_episodes_keep_a_higher_floor() {
+    assert!((retrievability_multiplier(1.0, false) - 1.0).abs() < f64::EPSILON);
+    assert!((retrievability_multiplier(0.0, false) - RETRIEVABILITY_FLOOR).abs() < f64::EPSILON);
+    assert!(
+      (retrievability_multiplier(0.0, true) - FLASHBULB_RETRIEVABILITY_FLOOR).abs() < f64::EPSILON
+    );
+    assert!(retrievability_multiplier(0.5, true) > retrievability_multiplier(0.5, false));
+  }
+}
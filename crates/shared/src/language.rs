@@ -0,0 +1,69 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Language a conversation is held in.
+///
+/// Selects localized prompts and date rendering, so titles, time anchors and
+/// facts are written in the conversation's own language.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum Language {
+  #[default]
+  #[serde(rename = "en")]
+  English,
+  #[serde(rename = "ja")]
+  Japanese,
+  #[serde(rename = "zh")]
+  Chinese,
+}
+
+impl Language {
+  /// ISO 639-1 code, as accepted by the API and `DEFAULT_LANGUAGE`.
+  #[must_use]
+  pub const fn code(self) -> &'static str {
+    match self {
+      Self::English => "en",
+      Self::Japanese => "ja",
+      Self::Chinese => "zh",
+    }
+  }
+
+  /// English name of the language, for use inside prompts.
+  #[must_use]
+  pub const fn name(self) -> &'static str {
+    match self {
+      Self::English => "English",
+      Self::Japanese => "Japanese",
+      Self::Chinese => "Chinese",
+    }
+  }
+}
+
+impl FromStr for Language {
+  type Err = anyhow::Error;
+
+  /// Parse a language code. Region and script subtags (`ja-JP`, `zh_Hans`) are ignored.
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let primary = value
+      .trim()
+      .split(['-', '_'])
+      .next()
+      .unwrap_or_default()
+      .to_ascii_lowercase();
+    match primary.as_str() {
+      "en" => Ok(Self::English),
+      "ja" => Ok(Self::Japanese),
+      "zh" => Ok(Self::Chinese),
+      _ => Err(anyhow::anyhow!(
+        "unsupported language `{value}`: expected en, ja or zh"
+      )),
+    }
+  }
+}
+
+impl fmt::Display for Language {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.code())
+  }
+}
@@ -2,9 +2,15 @@ mod error;
 pub use error::AppError;
 
 mod env;
-pub use env::APP_ENV;
+pub use env::{APP_ENV, ChatModelEnv};
 
 pub mod fsrs;
 
+mod language;
+pub use language::Language;
+
 mod message;
 pub use message::{Message, MessageRole};
+
+mod template;
+pub use template::{TemplateEscape, fill_template};
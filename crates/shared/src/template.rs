@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+/// How values are escaped before [`fill_template`] substitutes them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateEscape {
+  #[default]
+  None,
+  /// `&`, `<`, `>`, `"` and `'` become entities.
+  Xml,
+  /// Values are escaped for use inside a JSON string, without the quotes.
+  Json,
+}
+
+impl TemplateEscape {
+  #[must_use]
+  pub fn apply(self, value: &str) -> Cow<'_, str> {
+    match self {
+      Self::None => Cow::Borrowed(value),
+      Self::Xml => {
+        if !value.contains(['&', '<', '>', '"', '\'']) {
+          return Cow::Borrowed(value);
+        }
+        let mut out = String::with_capacity(value.len());
+        for c in value.chars() {
+          match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+          }
+        }
+        Cow::Owned(out)
+      }
+      Self::Json => {
+        let quoted = serde_json::Value::from(value).to_string();
+        Cow::Owned(quoted[1..quoted.len() - 1].to_owned())
+      }
+    }
+  }
+}
+
+/// Replace each `{name}` in `template` with its value, escaped per `escape`
+/// unless the name is in `raw`.
+///
+/// Placeholders without a value are left as they are, so templates may contain
+/// literal braces, e.g. JSON.
+#[must_use]
+pub fn fill_template(
+  template: &str,
+  vars: &[(&str, &str)],
+  escape: TemplateEscape,
+  raw: &[&str],
+) -> String {
+  let mut out = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(start) = rest.find('{') {
+    out.push_str(&rest[..start]);
+    let after = &rest[start + 1..];
+    let value = after.find('}').and_then(|end| {
+      let name = &after[..end];
+      vars
+        .iter()
+        .find(|(var, _)| *var == name)
+        .map(|(var, value)| (*var, *value, end))
+    });
+    if let Some((name, value, end)) = value {
+      if raw.contains(&name) {
+        out.push_str(value);
+      } else {
+        out.push_str(&escape.apply(value));
+      }
+      rest = &after[end + 1..];
+    } else {
+      out.push('{');
+      rest = after;
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fills_known_placeholders_and_keeps_the_rest() {
+    assert_eq!(
+      fill_template(
+        "{\"a\": \"{a}\", \"b\": {b}} {missing} {",
+        &[("a", "x\"y"), ("b", "[1]")],
+        TemplateEscape::Json,
+        &["b"],
+      ),
+      "{\"a\": \"x\\\"y\", \"b\": [1]} {missing} {"
+    );
+    assert_eq!(
+      fill_template("<t>{v}</t>", &[("v", "a<b")], TemplateEscape::Xml, &[]),
+      "<t>a&lt;b</t>"
+    );
+    // Values are not scanned for placeholders again
+    assert_eq!(
+      fill_template(
+        "{a}{b}",
+        &[("a", "{b}"), ("b", "!")],
+        TemplateEscape::None,
+        &[]
+      ),
+      "{b}!"
+    );
+  }
+}
@@ -5,18 +5,20 @@ use apalis_postgres::PostgresStorage;
 use chrono::{DateTime, Datelike, Timelike, Utc};
 use fsrs::{DEFAULT_PARAMETERS, FSRS};
 use plastmem_ai::{
-  ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
-  ChatCompletionRequestUserMessage, embed, generate_object,
+  AiClient, BuiltinPrompt, CallContext, ChatCompletionRequestMessage,
+  ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage, ChatTask, EmbeddingKind,
+  PromptVersions, embed, generate_object,
 };
 use plastmem_core::{EpisodeSpan, get_episode_span, get_messages_in_range};
 use plastmem_entities::{EpisodeClassification, episodic_memory};
-use plastmem_shared::{AppError, Message};
+use plastmem_event_segmentation::SurpriseLevel;
+use plastmem_shared::{AppError, Language, Message, fsrs::FLASHBULB_SURPRISE_THRESHOLD};
 use schemars::JsonSchema;
 use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::PredictCalibrateJob;
+use super::{PredictCalibrateJob, conversation_language};
 
 const DESIRED_RETENTION: f32 = 0.9;
 const EPISODE_CREATION_JOB_NAMESPACE: Uuid =
@@ -53,6 +55,15 @@ impl EpisodeCreationJob {
 #[derive(Debug, Deserialize, JsonSchema)]
 struct EpisodeTitleOutput {
   title: String,
+  surprise_level: SurpriseLevel,
+}
+
+/// Title, rendered content, and surprise signal generated for a new episode.
+#[derive(Debug)]
+pub(super) struct EpisodeArtifacts {
+  pub title: String,
+  pub content: String,
+  pub surprise: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -94,18 +105,30 @@ struct TimeAnchorInsertion {
   precision: TimeAnchorPrecision,
 }
 
-const EPISODE_TITLE_SYSTEM_PROMPT: &str = r"
+const EPISODE_TITLE_SYSTEM_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "episode_title",
+  "builtin-2",
+  r"
 You are naming one conversation segment for episodic memory retrieval.
-Return only JSON with `title`.
+Return only JSON with `title` and `surprise_level`.
 
 Requirements:
 1. The title must be concise, descriptive, and easy to search.
 2. Keep it within 10-20 words and name the main topic, activity, or event.
 3. Preserve names, places, products, and distinctive wording when they help retrieval.
 4. Do not invent facts or generalize away the concrete topic.
-";
 
-const EPISODE_TIME_ANCHOR_SYSTEM_PROMPT: &str = r"
+`surprise_level` measures how unexpected and emotionally significant the segment is:
+- `low`: routine exchange, small talk, or expected updates.
+- `high`: noticeable emotion, unexpected news, or personal disclosure.
+- `extremely_high`: a turning point the user will remember, such as shocking news, intense emotion, deep vulnerability, or an explicit request to remember this.
+",
+);
+
+const EPISODE_TIME_ANCHOR_SYSTEM_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "episode_time_anchor",
+  "builtin-1",
+  r"
 You are adding grounded time anchors to existing conversation lines.
 Return only JSON with `insertions`.
 
@@ -133,7 +156,8 @@ Rules:
 10. Preserve the original phrase in the line and resolve it inline after that phrase.
 11. Do not modify non-time text.
 12. Do not include parentheses inside `anchor_text`.
-";
+",
+);
 
 // ──────────────────────────────────────────────────
 // Entry
@@ -142,6 +166,7 @@ Rules:
 pub async fn process_episode_creation(
   job: EpisodeCreationJob,
   db: Data<DatabaseConnection>,
+  ai: Data<AiClient>,
   predict_storage: Data<PostgresStorage<PredictCalibrateJob>>,
 ) -> Result<(), AppError> {
   let db = &*db;
@@ -151,7 +176,12 @@ pub async fn process_episode_creation(
   };
 
   let episode_id = job.deterministic_episode_id();
-  let already_consolidated = try_ensure_episode_exists(episode_id, &span, db).await?;
+  let ai = ai.with_call_context(CallContext {
+    job: Some("episode_creation"),
+    conversation_id: Some(span.conversation_id),
+    episode_id: Some(episode_id),
+  });
+  let already_consolidated = try_ensure_episode_exists(episode_id, &span, &ai, db).await?;
 
   try_enqueue_predict_calibrate_if_needed(
     span.conversation_id,
@@ -202,6 +232,7 @@ async fn try_load_current_span(
 async fn try_ensure_episode_exists(
   episode_id: Uuid,
   span: &EpisodeSpan,
+  ai: &AiClient,
   db: &DatabaseConnection,
 ) -> Result<bool, AppError> {
   if let Some(existing_episode) = episodic_memory::Entity::find_by_id(episode_id)
@@ -212,7 +243,7 @@ async fn try_ensure_episode_exists(
   }
 
   let messages = load_episode_source_messages(span, db).await?;
-  create_episode_record(episode_id, span, &messages, db).await?;
+  create_episode_record(episode_id, span, &messages, ai, db).await?;
   Ok(false)
 }
 
@@ -276,10 +307,18 @@ async fn create_episode_record(
   episode_id: Uuid,
   span: &EpisodeSpan,
   messages: &[Message],
+  ai: &AiClient,
   db: &DatabaseConnection,
 ) -> Result<(), AppError> {
-  let (title, content) = generate_episode_artifacts(messages).await?;
-  let embedding = embed(&content).await?;
+  let language = conversation_language(span.conversation_id, db).await?;
+  // Segmentation prompt versions carry over from the span
+  let mut prompt_versions = span.prompt_versions.clone();
+  let EpisodeArtifacts {
+    title,
+    content,
+    surprise,
+  } = generate_episode_artifacts(messages, language, &mut prompt_versions, ai).await?;
+  let embedding = embed(EmbeddingKind::Document, &content, ai).await?;
 
   let fsrs = FSRS::new(Some(&DEFAULT_PARAMETERS))?;
   let initial_states = fsrs.next_states(None, DESIRED_RETENTION, 0)?;
@@ -294,16 +333,19 @@ async fn create_episode_record(
     messages: Set(serde_json::to_value(messages.to_vec())?),
     content: Set(content),
     embedding: Set(embedding),
+    embedding_model: Set(ai.embedding().model().to_owned()),
     title: Set(title),
     stability: Set(initial_state.stability),
     difficulty: Set(initial_state.difficulty),
-    surprise: Set(0.0),
+    surprise: Set(surprise),
+    is_flashbulb: Set(surprise >= FLASHBULB_SURPRISE_THRESHOLD),
     classification: Set(Some(span.classification.clone())),
     start_at: Set(start_at.into()),
     end_at: Set(end_at.into()),
     created_at: Set(now.into()),
     last_reviewed_at: Set(now.into()),
     consolidated_at: Set(None),
+    prompt_versions: Set(serde_json::to_value(prompt_versions)?),
   }
   .insert(db)
   .await?;
@@ -316,54 +358,92 @@ async fn create_episode_record(
 // ──────────────────────────────────────────────────
 
 // Render a deterministic transcript first, then let the LLM add grounded time
-// anchors before generating the retrieval title.
-async fn generate_episode_artifacts(messages: &[Message]) -> Result<(String, String), AppError> {
+// anchors before generating the retrieval title and surprise signal.
+pub(super) async fn generate_episode_artifacts(
+  messages: &[Message],
+  language: Language,
+  prompt_versions: &mut PromptVersions,
+  ai: &AiClient,
+) -> Result<EpisodeArtifacts, AppError> {
   let mut lines = render_episode_lines(messages);
-  try_anchor_episode_lines(&mut lines).await;
-  let content = render_episode_content(&lines);
-  let title = generate_episode_title(messages, &content).await?;
-  Ok((title, content))
+  try_anchor_episode_lines(&mut lines, language, prompt_versions, ai).await;
+  let content = render_episode_content(&lines, language);
+  let (title, surprise_level) =
+    generate_episode_title(messages, &content, language, prompt_versions, ai).await?;
+  Ok(EpisodeArtifacts {
+    title,
+    content,
+    surprise: surprise_level.to_signal(),
+  })
 }
 
-async fn generate_episode_title(messages: &[Message], content: &str) -> Result<String, AppError> {
-  let system = ChatCompletionRequestSystemMessage::from(EPISODE_TITLE_SYSTEM_PROMPT.trim());
+async fn generate_episode_title(
+  messages: &[Message],
+  content: &str,
+  language: Language,
+  prompt_versions: &mut PromptVersions,
+  ai: &AiClient,
+) -> Result<(String, SurpriseLevel), AppError> {
+  let prompt = ai
+    .prompts()
+    .render_in(&EPISODE_TITLE_SYSTEM_PROMPT, language, &[]);
+  prompt.record(prompt_versions);
+  // Content and source messages share the budget; the title needs only their gist
+  let budget = ai
+    .token_budget(ChatTask::EpisodeTitle)
+    .reserve(&prompt.text);
+  let half = budget.available() / 2;
+  let source_messages = format_messages(messages);
+  let system = ChatCompletionRequestSystemMessage::from(prompt.text.trim());
   let user = ChatCompletionRequestUserMessage::from(format!(
     "Episode content:\n{}\n\nSource messages:\n{}",
-    content,
-    format_messages(messages)
+    budget.truncate(content, half),
+    budget.truncate(&source_messages, half)
   ));
 
   let output = generate_object::<EpisodeTitleOutput>(
+    ChatTask::EpisodeTitle,
     vec![
       ChatCompletionRequestMessage::System(system),
       ChatCompletionRequestMessage::User(user),
     ],
     "episodic_title_generation".to_owned(),
     Some("Generate an episodic memory title".to_owned()),
+    ai,
   )
   .await?;
 
   let title = output.title.trim();
-  Ok(if title.is_empty() {
-    "Conversation Segment".to_owned()
+  let title = if title.is_empty() {
+    fallback_title(language).to_owned()
   } else {
     title.to_owned()
-  })
+  };
+  Ok((title, output.surprise_level))
 }
 
-async fn try_anchor_episode_lines(lines: &mut [RenderedEpisodeLine]) {
+async fn try_anchor_episode_lines(
+  lines: &mut [RenderedEpisodeLine],
+  language: Language,
+  prompt_versions: &mut PromptVersions,
+  ai: &AiClient,
+) {
   let candidates = build_time_anchor_candidates(lines);
   if candidates.is_empty() {
     return;
   }
 
-  let output = match request_time_anchor_insertions(&candidates).await {
+  let prompt = ai
+    .prompts()
+    .render_in(&EPISODE_TIME_ANCHOR_SYSTEM_PROMPT, language, &[]);
+  let output = match request_time_anchor_insertions(&candidates, &prompt.text, ai).await {
     Ok(output) => output,
     Err(err) => {
       tracing::warn!(error = %err, "Episode time anchoring failed; using deterministic content");
       return;
     }
   };
+  prompt.record(prompt_versions);
 
   for candidate in &candidates {
     let Some(line) = lines.get_mut(candidate.line_index) else {
@@ -390,17 +470,21 @@ async fn try_anchor_episode_lines(lines: &mut [RenderedEpisodeLine]) {
 
 async fn request_time_anchor_insertions(
   candidates: &[TimeAnchorCandidateLine],
+  system_prompt: &str,
+  ai: &AiClient,
 ) -> Result<TimeAnchorOutput, AppError> {
-  let system = ChatCompletionRequestSystemMessage::from(EPISODE_TIME_ANCHOR_SYSTEM_PROMPT.trim());
+  let system = ChatCompletionRequestSystemMessage::from(system_prompt.trim());
   let user = ChatCompletionRequestUserMessage::from(build_time_anchor_user_content(candidates));
 
   generate_object::<TimeAnchorOutput>(
+    ChatTask::EpisodeTimeAnchor,
     vec![
       ChatCompletionRequestMessage::System(system),
       ChatCompletionRequestMessage::User(user),
     ],
     "episodic_time_anchoring".to_owned(),
     Some("Add grounded time anchors to existing conversation lines".to_owned()),
+    ai,
   )
   .await
 }
@@ -422,7 +506,7 @@ fn render_episode_lines(messages: &[Message]) -> Vec<RenderedEpisodeLine> {
     .collect()
 }
 
-fn render_episode_content(lines: &[RenderedEpisodeLine]) -> String {
+fn render_episode_content(lines: &[RenderedEpisodeLine], language: Language) -> String {
   let mut out = String::new();
   let mut current_bucket: Option<(i32, u32, u32, u32)> = None;
 
@@ -437,7 +521,7 @@ fn render_episode_content(lines: &[RenderedEpisodeLine]) -> String {
       if !out.is_empty() {
         out.push_str("\n\n");
       }
-      let _ = write!(out, "{}", format_at_header(line.timestamp));
+      let _ = write!(out, "{}", format_at_header(line.timestamp, language));
       current_bucket = Some(bucket);
       out.push('\n');
     } else {
@@ -520,21 +604,36 @@ fn month_abbrev(month: u32) -> &'static str {
   }
 }
 
-fn format_at_header(timestamp: DateTime<Utc>) -> String {
-  let hour = timestamp.hour();
-  let hour_12 = match hour % 12 {
-    0 => 12,
-    value => value,
-  };
-  let meridiem = if hour < 12 { "AM" } else { "PM" };
-  format!(
-    "Spoken At: {} {}, {} {} {}",
-    month_abbrev(timestamp.month()),
-    timestamp.day(),
+fn format_at_header(timestamp: DateTime<Utc>, language: Language) -> String {
+  let (year, month, day, hour) = (
     timestamp.year(),
-    hour_12,
-    meridiem
-  )
+    timestamp.month(),
+    timestamp.day(),
+    timestamp.hour(),
+  );
+  match language {
+    Language::English => {
+      let hour_12 = match hour % 12 {
+        0 => 12,
+        value => value,
+      };
+      let meridiem = if hour < 12 { "AM" } else { "PM" };
+      format!(
+        "Spoken At: {} {day}, {year} {hour_12} {meridiem}",
+        month_abbrev(month)
+      )
+    }
+    Language::Japanese => format!("発言日時: {year}年{month}月{day}日 {hour}時"),
+    Language::Chinese => format!("发言时间: {year}年{month}月{day}日 {hour}时"),
+  }
+}
+
+const fn fallback_title(language: Language) -> &'static str {
+  match language {
+    Language::English => "Conversation Segment",
+    Language::Japanese => "会話セグメント",
+    Language::Chinese => "对话片段",
+  }
 }
 
 fn insertion_already_applied(content: &str, exact_text: &str) -> bool {
@@ -578,7 +677,7 @@ fn is_valid_time_anchor_insertion(
   if insertion.exact_text.trim().is_empty() || insertion.anchor_text.trim().is_empty() {
     return false;
   }
-  if insertion.anchor_text.contains('(') || insertion.anchor_text.contains(')') {
+  if insertion.anchor_text.contains(['(', ')', '（', '）']) {
     return false;
   }
   if !candidate.content.contains(&insertion.exact_text) {
@@ -592,3 +691,44 @@ fn is_valid_time_anchor_insertion(
   }
   true
 }
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use plastmem_ai::ScriptedChatProvider;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn only_extremely_surprising_episodes_cross_the_flashbulb_threshold() {
+    let scripted = Arc::new(ScriptedChatProvider::new());
+    let ai = AiClient::scripted(scripted.clone());
+    let messages = vec![Message {
+      role: "user".into(),
+      content: "My sister just told me she is pregnant!".to_owned(),
+      timestamp: Utc::now(),
+    }];
+
+    let mut surprises = Vec::new();
+    for level in ["high", "extremely_high"] {
+      scripted.push_raw("episodic_time_anchoring", r#"{"insertions": []}"#);
+      scripted.push_raw(
+        "episodic_title_generation",
+        format!(r#"{{"title": "Sister's pregnancy news", "surprise_level": "{level}"}}"#),
+      );
+      let artifacts = generate_episode_artifacts(
+        &messages,
+        Language::English,
+        &mut PromptVersions::new(),
+        &ai,
+      )
+      .await
+      .unwrap();
+      surprises.push(artifacts.surprise);
+    }
+
+    assert!(surprises[0] < FLASHBULB_SURPRISE_THRESHOLD);
+    assert!(surprises[1] >= FLASHBULB_SURPRISE_THRESHOLD);
+  }
+}
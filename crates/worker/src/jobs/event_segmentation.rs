@@ -1,21 +1,22 @@
 use apalis::prelude::{Data, TaskSink};
 use apalis_postgres::PostgresStorage;
 use chrono::Utc;
+use plastmem_ai::{AiClient, CallContext};
 use plastmem_core::{
-  EpisodeSpan, SegmentJobState, SegmentationJobClaim, abort_segmentation_job,
+  EpisodeSpan, SegmentJobState, SegmentationJobClaim, abort_segmentation_job, check_usage_budget,
   commit_segmentation_job, get_claim_messages, get_segmentation_state, take_pending_review_items,
   try_claim_segmentation_job,
 };
 use plastmem_entities::EpisodeClassification;
 use plastmem_event_segmentation::{
   ReviewedSegment, SegmentClassification, primitive_review_llm_segmenter,
-  temporal_boundary_review_llm_segmenter, temporal_rule_segmenter,
+  segmentation_prompt_versions, temporal_boundary_review_llm_segmenter, temporal_rule_segmenter,
 };
 use plastmem_shared::{APP_ENV, AppError, Message};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{EpisodeCreationJob, MemoryReviewJob};
+use super::{EpisodeCreationJob, MemoryReviewJob, usage_budget};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventSegmentationJob {
@@ -50,6 +51,7 @@ struct CommitPlan {
 
 struct SegmentationContext<'a> {
   db: &'a sea_orm::DatabaseConnection,
+  ai: &'a AiClient,
   segmentation_storage: &'a PostgresStorage<EventSegmentationJob>,
   episode_creation_storage: &'a PostgresStorage<EpisodeCreationJob>,
   review_storage: &'a PostgresStorage<MemoryReviewJob>,
@@ -58,12 +60,14 @@ struct SegmentationContext<'a> {
 impl<'a> SegmentationContext<'a> {
   const fn new(
     db: &'a sea_orm::DatabaseConnection,
+    ai: &'a AiClient,
     segmentation_storage: &'a PostgresStorage<EventSegmentationJob>,
     episode_creation_storage: &'a PostgresStorage<EpisodeCreationJob>,
     review_storage: &'a PostgresStorage<MemoryReviewJob>,
   ) -> Self {
     Self {
       db,
+      ai,
       segmentation_storage,
       episode_creation_storage,
       review_storage,
@@ -78,13 +82,20 @@ impl<'a> SegmentationContext<'a> {
 pub async fn process_event_segmentation(
   job: EventSegmentationJob,
   db: Data<sea_orm::DatabaseConnection>,
+  ai: Data<AiClient>,
   segmentation_storage: Data<PostgresStorage<EventSegmentationJob>>,
   episode_creation_storage: Data<PostgresStorage<EpisodeCreationJob>>,
   review_storage: Data<PostgresStorage<MemoryReviewJob>>,
 ) -> Result<(), AppError> {
   let db = &*db;
+  let ai = ai.with_call_context(CallContext {
+    job: Some("event_segmentation"),
+    conversation_id: Some(job.conversation_id),
+    episode_id: None,
+  });
   let ctx = SegmentationContext::new(
     db,
+    &ai,
     &*segmentation_storage,
     &*episode_creation_storage,
     &*review_storage,
@@ -211,13 +222,25 @@ async fn process_claimed_segment_range(
   let rule_output = temporal_rule_segmenter(claimed_messages)
     .map_err(|reason| AppError::new(anyhow::anyhow!(reason)))?;
   let (reviewed_segments, reviewed_boundaries) =
-    primitive_review_llm_segmenter(claimed_messages, &rule_output).await?;
-  let final_segments = temporal_boundary_review_llm_segmenter(
-    claimed_messages,
-    &reviewed_segments,
-    &reviewed_boundaries,
-  )
-  .await?;
+    primitive_review_llm_segmenter(claimed_messages, &rule_output, ctx.ai).await?;
+  // Over budget, keep the primitive review's boundaries instead of paying for another pass
+  let budget_exceeded = check_usage_budget(claim.conversation_id, &usage_budget(), ctx.db).await?;
+  let final_segments = if let Some(exceeded) = budget_exceeded {
+    tracing::info!(
+      conversation_id = %claim.conversation_id,
+      period = ?exceeded.period,
+      "Usage budget exceeded, skipping temporal boundary review"
+    );
+    reviewed_segments
+  } else {
+    temporal_boundary_review_llm_segmenter(
+      claimed_messages,
+      &reviewed_segments,
+      &reviewed_boundaries,
+      ctx.ai,
+    )
+    .await?
+  };
 
   let commit_plan = build_commit_plan(
     &final_segments,
@@ -226,6 +249,7 @@ async fn process_claimed_segment_range(
   )
   .map_err(|reason| AppError::new(anyhow::anyhow!(reason)))?;
 
+  let prompt_versions = segmentation_prompt_versions(budget_exceeded.is_none(), ctx.ai.prompts());
   let created_at = Utc::now();
   let finalized_spans: Vec<EpisodeSpan> = commit_plan
     .finalized_segments
@@ -235,6 +259,7 @@ async fn process_claimed_segment_range(
       start_seq: segment.start_seq,
       end_seq: segment.end_seq,
       classification: map_classification(segment.classification.clone()),
+      prompt_versions: prompt_versions.clone(),
       created_at,
     })
     .collect();
@@ -338,6 +363,7 @@ async fn enqueue_pending_reviews(
 
   if let Some(pending_reviews) = take_pending_review_items(conversation_id, db).await? {
     let review_job = MemoryReviewJob {
+      conversation_id: Some(conversation_id),
       pending_reviews,
       context_messages: context_messages.to_vec(),
       reviewed_at: Utc::now(),
@@ -5,8 +5,9 @@ use apalis::prelude::Data;
 use chrono::{DateTime, Utc};
 use fsrs::{DEFAULT_PARAMETERS, FSRS, MemoryState};
 use plastmem_ai::{
-  ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
-  ChatCompletionRequestUserMessage, generate_object,
+  AiClient, BuiltinPrompt, CallContext, ChatCompletionRequestMessage,
+  ChatCompletionRequestSystemMessage, ChatCompletionRequestUserMessage, ChatTask, TokenBudget,
+  generate_object,
 };
 use plastmem_core::PendingReview;
 use plastmem_entities::episodic_memory;
@@ -52,7 +53,10 @@ impl Rating {
   }
 }
 
-const REVIEW_SYSTEM_PROMPT: &str = "\
+const REVIEW_SYSTEM_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "memory_review",
+  "builtin-1",
+  "\
 You are a memory relevance reviewer. Evaluate how relevant each retrieved memory was to the conversation context.
 
 For each memory, assign a rating:
@@ -65,36 +69,90 @@ Consider:
 - Whether the assistant's responses reflect knowledge from the memory
 - Whether the memory's content aligns with the conversation topic
 - How central the memory is to the conversation flow
-- A memory matched by multiple queries may indicate higher relevance, but judge by actual usage in context";
+- A memory matched by multiple queries may indicate higher relevance, but judge by actual usage in context",
+);
+
+/// Share of the prompt budget for conversation context; memories get the rest.
+const CONTEXT_BUDGET_SHARE: usize = 2;
+/// Largest share of the prompt budget a single message or memory may take.
+const ITEM_BUDGET_SHARE: usize = 8;
+
+/// Render the conversation context, keeping the most recent messages that fit.
+fn build_context_section(context_messages: &[Message], budget: &TokenBudget) -> String {
+  let max_tokens = budget.available() / CONTEXT_BUDGET_SHARE;
+  let item_tokens = budget.available() / ITEM_BUDGET_SHARE;
+
+  let mut lines = Vec::new();
+  let mut used = 0;
+  for msg in context_messages.iter().rev() {
+    let line = format!(
+      "- {}: \"{}\"",
+      msg.role,
+      budget.truncate(&msg.content, item_tokens)
+    );
+    used += budget.count(&line);
+    if used > max_tokens {
+      break;
+    }
+    lines.push(line);
+  }
+  lines.reverse();
 
-/// Build the markdown user message for the reviewer LLM.
-fn build_review_user_message(
-  context_messages: &[Message],
-  memories: &[(Uuid, String, Vec<String>)], // (id, content, matched_queries)
-) -> String {
   let mut out = String::new();
-
   let _ = writeln!(out, "## Conversation Context\n");
-  for msg in context_messages {
-    let _ = writeln!(out, "- {}: \"{}\"", msg.role, msg.content);
-  }
-
-  let _ = writeln!(out, "\n## Retrieved Memories\n");
-  for (id, content, queries) in memories {
-    let _ = writeln!(out, "### Memory {id}");
-    let _ = writeln!(out, "**Content:** {content}");
-    let queries_str = queries
-      .iter()
-      .map(|q| format!("\"{q}\""))
-      .collect::<Vec<_>>()
-      .join(", ");
-    let _ = writeln!(out, "**Matched queries:** {queries_str}");
-    let _ = writeln!(out);
+  for line in lines {
+    let _ = writeln!(out, "{line}");
   }
+  out
+}
 
+/// Render one memory block for the reviewer LLM.
+fn build_memory_block(id: Uuid, content: &str, queries: &[String], budget: &TokenBudget) -> String {
+  let mut out = String::new();
+  let _ = writeln!(out, "### Memory {id}");
+  let _ = writeln!(
+    out,
+    "**Content:** {}",
+    budget.truncate(content, budget.available() / ITEM_BUDGET_SHARE)
+  );
+  let queries_str = queries
+    .iter()
+    .map(|q| format!("\"{q}\""))
+    .collect::<Vec<_>>()
+    .join(", ");
+  let _ = writeln!(out, "**Matched queries:** {queries_str}");
+  let _ = writeln!(out);
   out
 }
 
+/// Build the markdown user messages for the reviewer LLM.
+///
+/// The conversation context is repeated in every message; memories that do not
+/// fit next to it are split across several messages.
+fn build_review_user_messages(
+  context_messages: &[Message],
+  memories: &[(Uuid, String, Vec<String>)], // (id, content, matched_queries)
+  budget: TokenBudget,
+) -> Vec<String> {
+  let context = build_context_section(context_messages, &budget);
+  let header = format!("{context}\n## Retrieved Memories\n\n");
+  let blocks = memories
+    .iter()
+    .map(|(id, content, queries)| build_memory_block(*id, content, queries, &budget))
+    .collect::<Vec<_>>();
+
+  let budget = budget.reserve(&header);
+  let costs = blocks
+    .iter()
+    .map(|block| budget.count(block))
+    .collect::<Vec<_>>();
+  budget
+    .chunks(&costs)
+    .into_iter()
+    .map(|range| format!("{header}{}", blocks[range].concat()))
+    .collect()
+}
+
 /// Aggregate pending reviews: deduplicate memory IDs and collect matched queries.
 fn aggregate_pending_reviews(pending_reviews: &[PendingReview]) -> HashMap<Uuid, Vec<String>> {
   let mut map: HashMap<Uuid, Vec<String>> = HashMap::new();
@@ -106,6 +164,16 @@ fn aggregate_pending_reviews(pending_reviews: &[PendingReview]) -> HashMap<Uuid,
   map
 }
 
+/// Whether a retrieved episode gets reviewed: at least a day after its last review,
+/// and never for flashbulb episodes, whose FSRS state stays frozen.
+fn is_due_for_review(model: &episodic_memory::Model, reviewed_at: DateTime<Utc>) -> bool {
+  if model.is_flashbulb {
+    return false;
+  }
+  let last_reviewed_at = model.last_reviewed_at.with_timezone(&Utc);
+  reviewed_at > last_reviewed_at && (reviewed_at - last_reviewed_at).num_days() >= 1
+}
+
 // --- Job ---
 
 /// Job to review retrieved memories using LLM and update FSRS parameters.
@@ -113,6 +181,9 @@ fn aggregate_pending_reviews(pending_reviews: &[PendingReview]) -> HashMap<Uuid,
 /// Enqueued by the event segmentation worker when pending reviews exist.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MemoryReviewJob {
+  /// Conversation the reviews were queued for; absent on jobs enqueued before it was tracked.
+  #[serde(default)]
+  pub conversation_id: Option<Uuid>,
   pub pending_reviews: Vec<PendingReview>,
   pub context_messages: Vec<Message>,
   pub reviewed_at: DateTime<Utc>,
@@ -121,8 +192,14 @@ pub struct MemoryReviewJob {
 pub async fn process_memory_review(
   job: MemoryReviewJob,
   db: Data<DatabaseConnection>,
+  ai: Data<AiClient>,
 ) -> Result<(), AppError> {
   let db = &*db;
+  let ai = ai.with_call_context(CallContext {
+    job: Some("memory_review"),
+    conversation_id: job.conversation_id,
+    episode_id: None,
+  });
 
   if job.pending_reviews.is_empty() {
     return Ok(());
@@ -142,12 +219,7 @@ pub async fn process_memory_review(
     else {
       continue; // memory was deleted
     };
-
-    let last_reviewed_at = model.last_reviewed_at.with_timezone(&Utc);
-    if job.reviewed_at <= last_reviewed_at {
-      continue;
-    }
-    if (job.reviewed_at - last_reviewed_at).num_days() < 1 {
+    if !is_due_for_review(&model, job.reviewed_at) {
       continue;
     }
 
@@ -159,25 +231,37 @@ pub async fn process_memory_review(
     return Ok(());
   }
 
-  // 3. Call LLM for review
-  let user_message = build_review_user_message(&job.context_messages, &memories_for_review);
-  let system = ChatCompletionRequestSystemMessage::from(REVIEW_SYSTEM_PROMPT);
-  let user = ChatCompletionRequestUserMessage::from(user_message);
-
-  let output = generate_object::<MemoryReviewOutput>(
-    vec![
-      ChatCompletionRequestMessage::System(system),
-      ChatCompletionRequestMessage::User(user),
-    ],
-    "memory_review".to_owned(),
-    Some("Review retrieved memories for relevance".to_owned()),
-  )
-  .await?;
+  // 3. Call LLM for review, splitting memories that do not fit in one prompt
+  let system_prompt = ai.prompts().render(&REVIEW_SYSTEM_PROMPT, &[]).text;
+  let budget = ai
+    .token_budget(ChatTask::MemoryReview)
+    .reserve(&system_prompt);
+  let user_messages =
+    build_review_user_messages(&job.context_messages, &memories_for_review, budget);
+
+  let mut ratings = Vec::new();
+  for user_message in user_messages {
+    let system = ChatCompletionRequestSystemMessage::from(system_prompt.clone());
+    let user = ChatCompletionRequestUserMessage::from(user_message);
+
+    let output = generate_object::<MemoryReviewOutput>(
+      ChatTask::MemoryReview,
+      vec![
+        ChatCompletionRequestMessage::System(system),
+        ChatCompletionRequestMessage::User(user),
+      ],
+      "memory_review".to_owned(),
+      Some("Review retrieved memories for relevance".to_owned()),
+      &ai,
+    )
+    .await?;
+    ratings.extend(output.ratings);
+  }
 
   // 4. Parse ratings and update FSRS parameters
   let fsrs = FSRS::new(Some(&DEFAULT_PARAMETERS))?;
 
-  for rating_output in &output.ratings {
+  for rating_output in &ratings {
     let Ok(memory_id) = rating_output.memory_id.parse::<Uuid>() else {
       continue;
     };
@@ -217,3 +301,112 @@ pub async fn process_memory_review(
 
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use plastmem_ai::EstimatedTokenCounter;
+  use sea_orm::prelude::PgVector;
+
+  use super::*;
+
+  fn budget() -> TokenBudget {
+    TokenBudget::exact(400, Arc::new(EstimatedTokenCounter))
+  }
+
+  fn context(count: usize) -> Vec<Message> {
+    (0..count)
+      .map(|i| Message {
+        role: "user".into(),
+        content: format!("context {i} ").repeat(30),
+        timestamp: Utc::now(),
+      })
+      .collect()
+  }
+
+  fn episode(is_flashbulb: bool, last_reviewed_at: DateTime<Utc>) -> episodic_memory::Model {
+    episodic_memory::Model {
+      id: Uuid::now_v7(),
+      conversation_id: Uuid::nil(),
+      messages: serde_json::json!([]),
+      content: String::new(),
+      embedding: PgVector::from(vec![0.0; 4]),
+      embedding_model: String::new(),
+      title: String::new(),
+      stability: 1.0,
+      difficulty: 5.0,
+      surprise: 0.0,
+      is_flashbulb,
+      classification: None,
+      start_at: last_reviewed_at.into(),
+      end_at: last_reviewed_at.into(),
+      created_at: last_reviewed_at.into(),
+      last_reviewed_at: last_reviewed_at.into(),
+      consolidated_at: None,
+      prompt_versions: serde_json::json!({}),
+    }
+  }
+
+  #[test]
+  fn flashbulb_and_recently_reviewed_episodes_are_not_due() {
+    let now = Utc::now();
+    let two_days_ago = now - chrono::TimeDelta::days(2);
+
+    assert!(is_due_for_review(&episode(false, two_days_ago), now));
+    assert!(!is_due_for_review(&episode(true, two_days_ago), now));
+    assert!(!is_due_for_review(
+      &episode(false, now - chrono::TimeDelta::hours(3)),
+      now
+    ));
+    assert!(!is_due_for_review(&episode(false, now), two_days_ago));
+  }
+
+  #[test]
+  fn context_keeps_the_most_recent_messages_that_fit() {
+    let budget = budget();
+    let section = build_context_section(&context(20), &budget);
+
+    assert!(section.contains("context 19"));
+    assert!(!section.contains("context 0 "));
+    assert!(section.contains("[...]"));
+    // The section heading comes on top of the context share
+    assert!(budget.count(&section) <= budget.available() / CONTEXT_BUDGET_SHARE + 16);
+  }
+
+  #[test]
+  fn memories_are_split_across_messages_next_to_the_context() {
+    let budget = budget();
+    let memories = (0..6)
+      .map(|i| {
+        (
+          Uuid::now_v7(),
+          format!("memory {i} ").repeat(60),
+          vec![format!("query {i}")],
+        )
+      })
+      .collect::<Vec<_>>();
+
+    let messages = build_review_user_messages(&context(20), &memories, budget.clone());
+    assert!(messages.len() > 1);
+    let header = build_context_section(&context(20), &budget);
+    for message in &messages {
+      assert!(message.starts_with(&header));
+      assert!(budget.count(message) <= budget.available());
+    }
+    // Every memory lands in exactly one message, in order
+    let ids = messages
+      .iter()
+      .flat_map(|message| {
+        memories
+          .iter()
+          .filter(|(id, _, _)| message.contains(&id.to_string()))
+          .map(|(id, _, _)| *id)
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      ids,
+      memories.iter().map(|(id, _, _)| *id).collect::<Vec<_>>()
+    );
+  }
+}
@@ -10,7 +10,60 @@ pub use memory_review::*;
 mod predict_calibrate;
 pub use predict_calibrate::*;
 
-use plastmem_shared::AppError;
+mod reembed;
+pub use reembed::*;
+
+use plastmem_ai::{AiClient, is_circuit_open};
+use plastmem_core::{UsageBudget, get_conversation_settings};
+use plastmem_shared::{APP_ENV, AppError, Language};
+use sea_orm::DatabaseConnection;
+use uuid::Uuid;
+
+/// Per-conversation caps from `CONVERSATION_*_BUDGET*`; zero leaves a cap unlimited.
+pub(crate) fn usage_budget() -> UsageBudget {
+  UsageBudget {
+    daily_tokens: Some(APP_ENV.conversation_daily_token_budget).filter(|&cap| cap > 0),
+    monthly_tokens: Some(APP_ENV.conversation_monthly_token_budget).filter(|&cap| cap > 0),
+    daily_cost_usd: Some(APP_ENV.conversation_daily_cost_budget_usd).filter(|&cap| cap > 0.0),
+    monthly_cost_usd: Some(APP_ENV.conversation_monthly_cost_budget_usd).filter(|&cap| cap > 0.0),
+  }
+}
+
+/// Language of a conversation, falling back to `DEFAULT_LANGUAGE`.
+pub(crate) async fn conversation_language(
+  conversation_id: Uuid,
+  db: &DatabaseConnection,
+) -> Result<Language, AppError> {
+  Ok(
+    get_conversation_settings(conversation_id, db)
+      .await?
+      .map_or(APP_ENV.default_language, |settings| settings.language),
+  )
+}
+
+/// Run a job once the AI circuit breakers let requests through, and again whenever it
+/// fails on an open one.
+///
+/// While the AI backend is down, jobs wait here instead of failing and burning apalis attempts.
+pub(crate) async fn run_when_ai_available<F, Fut>(mut run: F, ai: &AiClient) -> Result<(), AppError>
+where
+  F: FnMut() -> Fut,
+  Fut: Future<Output = Result<(), AppError>>,
+{
+  loop {
+    ai.chat_breaker().wait_until_available().await;
+    ai.embedding_breaker().wait_until_available().await;
+    match run().await {
+      Err(err) if is_circuit_open(&err) => {
+        tracing::warn!(
+          error = %err,
+          "AI backend unavailable, job paused until the circuit breaker lets requests through"
+        );
+      }
+      result => return result,
+    }
+  }
+}
 
 /// Error type for apalis job boundary.
 /// Jobs internally use `AppError`; this wrapper converts at the worker boundary.
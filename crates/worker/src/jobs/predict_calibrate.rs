@@ -1,14 +1,16 @@
 use std::{cmp::Ordering, collections::HashMap, time::Instant};
 
 use anyhow::anyhow;
-use apalis::prelude::Data;
+use apalis::prelude::{Data, Task, TaskSink};
+use apalis_postgres::PostgresStorage;
 use chrono::{DateTime, Utc};
 use plastmem_ai::{
-  ChatCompletionRequestMessage, embed, embed_many, generate_object, generate_text,
+  AiClient, BuiltinPrompt, CallContext, ChatCompletionRequestMessage, ChatTask, EmbeddingKind,
+  PromptVersions, TokenBudget, embed, embed_many, generate_object, generate_text,
 };
-use plastmem_core::{EpisodicMemory, SemanticMemory};
+use plastmem_core::{EpisodicMemory, RetrievalProfile, SemanticMemory, check_usage_budget};
 use plastmem_entities::{episodic_memory, semantic_memory};
-use plastmem_shared::AppError;
+use plastmem_shared::{AppError, Language};
 use schemars::JsonSchema;
 use sea_orm::{
   ActiveModelTrait, ConnectionTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult,
@@ -17,6 +19,8 @@ use sea_orm::{
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::{conversation_language, usage_budget};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PredictCalibrateJob {
   pub conversation_id: Uuid,
@@ -48,7 +52,14 @@ enum SemanticActionKind {
   Invalidate,
 }
 
-const COLD_START_SYSTEM_PROMPT: &str = "\
+/// Categories accepted by `normalize_category`, as listed in the consolidation prompts.
+const SEMANTIC_CATEGORIES: &str =
+  "identity, preference, interest, personality, relationship, experience, goal, guideline";
+
+const COLD_START_SYSTEM_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "consolidation_cold_start",
+  "builtin-1",
+  "\
 You are a semantic memory consolidation system.
 
 Read the episode and output semantic consolidation actions as strict JSON.
@@ -59,7 +70,7 @@ Extract only high-value, durable semantic facts that should become active semant
 ## Rules
 1. In cold start mode, you may only emit `new` actions.
 2. Each action must use one of these categories exactly:
-   identity, preference, interest, personality, relationship, experience, goal, guideline
+   {categories}
 3. Each fact must be atomic, self-contained, and useful for future retrieval.
 4. Preserve exact names, titles, locations, diagnoses, products, model names, and other distinctive phrases.
 5. Do not rewrite stable speaker labels into `User` or `Assistant` unless the source only uses those labels.
@@ -82,9 +93,13 @@ preserve both the original time expression AND the grounded anchor in the fact.
 - GOOD new: fact='Sam works at ByteDance as a senior ML engineer', category='identity'
 - GOOD new: fact='Sam prefers Rust over Python for systems programming', category='preference'
 - BAD: fact='The user was happy', category='personality'
-- BAD: fact='They talked about programming', category='interest'";
+- BAD: fact='They talked about programming', category='interest'",
+);
 
-const PREDICTION_SYSTEM_PROMPT: &str = "\
+const PREDICTION_SYSTEM_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "prediction",
+  "builtin-1",
+  "\
 You are performing the PREDICT phase of Predict-Calibrate Learning.
 
 Given existing semantic knowledge about the conversation participants and an episode title, predict what the conversation content would be.
@@ -100,9 +115,13 @@ Generate a prediction of what the conversation SHOULD contain based on:
 3. If knowledge is insufficient, indicate what you cannot predict.
 4. Preserve named participants and exact item names already present in the knowledge.
 5. Do not normalize named speakers into `User` or `Assistant` unless the knowledge only provides generic role labels.
-6. The prediction will be compared with actual conversation to identify knowledge gaps.";
+6. The prediction will be compared with actual conversation to identify knowledge gaps.",
+);
 
-const EXTRACT_FROM_COMPARISON_PROMPT: &str = "\
+const EXTRACT_FROM_COMPARISON_PROMPT: BuiltinPrompt = BuiltinPrompt::new(
+  "consolidation_calibrate",
+  "builtin-1",
+  "\
 You are performing the CALIBRATE phase of Predict-Calibrate Learning.
 
 You will receive:
@@ -152,18 +171,32 @@ preserve both the original time expression AND the grounded anchor in the fact.
 - Use `reinforce` when the episode simply confirms an existing fact.
 
 ## Categories
-identity, preference, interest, personality, relationship, experience, goal, guideline";
+{categories}",
+);
 
 const DEDUPE_THRESHOLD: f64 = 0.95;
 const MAX_STATEMENTS_FOR_PREDICTION: usize = 10;
 const MAX_GUIDELINES_FOR_PREDICTION: usize = 3;
 const MAX_FACTS_FOR_ACTIONS: usize = 20;
+/// Episode content, existing facts and the prediction may each use at most this
+/// share of the consolidation budget; the messages get the rest, split into
+/// several requests when they do not fit one.
+const SECTION_BUDGET_SHARE: usize = 4;
+/// A single message may use at most this share of a request's message budget.
+const MESSAGE_BUDGET_SHARE: usize = 4;
 
 pub async fn process_predict_calibrate(
   job: PredictCalibrateJob,
   db: Data<DatabaseConnection>,
+  ai: Data<AiClient>,
+  predict_storage: Data<PostgresStorage<PredictCalibrateJob>>,
 ) -> Result<(), AppError> {
   let db = &*db;
+  let ai = &ai.with_call_context(CallContext {
+    job: Some("predict_calibrate"),
+    conversation_id: Some(job.conversation_id),
+    episode_id: Some(job.episode_id),
+  });
 
   let Some(episode) = EpisodicMemory::get(job.episode_id, db).await? else {
     tracing::warn!(
@@ -181,6 +214,25 @@ pub async fn process_predict_calibrate(
     return Ok(());
   }
 
+  // Over budget, retry once the budget period resets; forced runs go ahead regardless
+  if !job.force
+    && let Some(exceeded) = check_usage_budget(job.conversation_id, &usage_budget(), db).await?
+  {
+    tracing::info!(
+      conversation_id = %job.conversation_id,
+      episode_id = %job.episode_id,
+      period = ?exceeded.period,
+      resets_at = %exceeded.resets_at,
+      "Usage budget exceeded, deferring predict-calibrate"
+    );
+    let run_at = u64::try_from(exceeded.resets_at.timestamp()).unwrap_or_default();
+    let mut storage = (*predict_storage).clone();
+    storage
+      .push_task(Task::builder(job).run_at_timestamp(run_at).build())
+      .await?;
+    return Ok(());
+  }
+
   tracing::info!(
     conversation_id = %job.conversation_id,
     episode_id = %job.episode_id,
@@ -192,6 +244,7 @@ pub async fn process_predict_calibrate(
   tracing::info!(episode_id = %episode.id, "Predict-Calibrate stage start: load_related_facts");
   let existing_facts = load_related_facts(
     &episode,
+    ai,
     db,
     i64::try_from(MAX_FACTS_FOR_ACTIONS)
       .map_err(|_| anyhow!("MAX_FACTS_FOR_ACTIONS must fit in i64"))?,
@@ -205,16 +258,25 @@ pub async fn process_predict_calibrate(
   );
 
   let extraction_start = Instant::now();
+  let language = conversation_language(job.conversation_id, db).await?;
+  let mut prompt_versions = PromptVersions::new();
   let actions = if existing_facts.is_empty() {
     tracing::info!(episode_id = %episode.id, "No existing knowledge, using cold start mode");
-    cold_start_extraction(&episode).await?
+    cold_start_extraction(&episode, language, &mut prompt_versions, ai).await?
   } else {
     tracing::debug!(
       episode_id = %episode.id,
       facts_found = existing_facts.len(),
       "Using Predict-Calibrate with existing knowledge"
     );
-    predict_calibrate_extraction(&episode, &existing_facts).await?
+    predict_calibrate_extraction(
+      &episode,
+      &existing_facts,
+      language,
+      &mut prompt_versions,
+      ai,
+    )
+    .await?
   };
   tracing::info!(
     episode_id = %episode.id,
@@ -235,7 +297,15 @@ pub async fn process_predict_calibrate(
     action_count = actions.len(),
     "Predict-Calibrate stage start: consolidate_actions"
   );
-  consolidate_actions(&actions, &episode, &existing_facts, db).await?;
+  consolidate_actions(
+    &actions,
+    &episode,
+    &existing_facts,
+    &prompt_versions,
+    ai,
+    db,
+  )
+  .await?;
   tracing::info!(
     episode_id = %episode.id,
     elapsed_ms = consolidate_start.elapsed().as_millis(),
@@ -252,51 +322,102 @@ pub async fn process_predict_calibrate(
   Ok(())
 }
 
-fn format_messages(episode: &EpisodicMemory) -> String {
-  episode
+/// Episode messages as `Message N [role]: ...` lines, grouped into chunks that each fit `budget`.
+///
+/// Always returns at least one (possibly empty) chunk.
+fn message_chunks(episode: &EpisodicMemory, budget: &TokenBudget) -> Vec<String> {
+  let max_message_tokens = budget.available() / MESSAGE_BUDGET_SHARE;
+  let lines = episode
     .messages
     .iter()
     .enumerate()
-    .map(|(i, m)| format!("Message {} [{}]: {}", i + 1, m.role, m.content))
-    .collect::<Vec<_>>()
-    .join("\n")
+    .map(|(i, m)| {
+      format!(
+        "Message {} [{}]: {}",
+        i + 1,
+        m.role,
+        budget.truncate(&m.content, max_message_tokens)
+      )
+    })
+    .collect::<Vec<_>>();
+  let costs = lines
+    .iter()
+    .map(|line| budget.count(line) + 1)
+    .collect::<Vec<_>>();
+  let chunks = budget
+    .chunks(&costs)
+    .into_iter()
+    .map(|range| lines[range].join("\n"))
+    .collect::<Vec<_>>();
+  if chunks.is_empty() {
+    vec![String::new()]
+  } else {
+    chunks
+  }
 }
 
-async fn cold_start_extraction(episode: &EpisodicMemory) -> Result<Vec<SemanticAction>, AppError> {
-  let user_content = format!(
-    "Episode Title: {}\nEpisode Content: {}\n\nMessages:\n{}",
+async fn cold_start_extraction(
+  episode: &EpisodicMemory,
+  language: Language,
+  prompt_versions: &mut PromptVersions,
+  ai: &AiClient,
+) -> Result<Vec<SemanticAction>, AppError> {
+  let prompt = ai.prompts().render_in(
+    &COLD_START_SYSTEM_PROMPT,
+    language,
+    &[("categories", SEMANTIC_CATEGORIES)],
+  );
+  prompt.record(prompt_versions);
+  let budget = ai
+    .token_budget(ChatTask::Consolidation)
+    .reserve(&prompt.text);
+  let header = format!(
+    "Episode Title: {}\nEpisode Content: {}\n\nMessages:\n",
     episode.title,
-    episode.content,
-    format_messages(episode)
+    budget.truncate(&episode.content, budget.available() / SECTION_BUDGET_SHARE)
   );
+  let chunks = message_chunks(episode, &budget.reserve(&header));
 
   tracing::debug!(episode_id = %episode.id, "Cold-start extraction");
   let generation_start = Instant::now();
-  tracing::info!(episode_id = %episode.id, "Predict-Calibrate stage start: cold_start_generate");
+  tracing::info!(
+    episode_id = %episode.id,
+    chunk_count = chunks.len(),
+    "Predict-Calibrate stage start: cold_start_generate"
+  );
 
-  let output = generate_object::<SemanticActionOutput>(
-    vec![
-      ChatCompletionRequestMessage::System(COLD_START_SYSTEM_PROMPT.into()),
-      ChatCompletionRequestMessage::User(user_content.into()),
-    ],
-    "pcl_cold_start".to_owned(),
-    Some("Generate semantic memory creation actions from the first episode".to_owned()),
-  )
-  .await?;
+  let mut actions = Vec::new();
+  for messages in chunks {
+    let output = generate_object::<SemanticActionOutput>(
+      ChatTask::Consolidation,
+      vec![
+        ChatCompletionRequestMessage::System(prompt.text.clone().into()),
+        ChatCompletionRequestMessage::User(format!("{header}{messages}").into()),
+      ],
+      "pcl_cold_start".to_owned(),
+      Some("Generate semantic memory creation actions from the first episode".to_owned()),
+      ai,
+    )
+    .await?;
+    actions.extend(output.actions);
+  }
 
   tracing::info!(
     episode_id = %episode.id,
     elapsed_ms = generation_start.elapsed().as_millis(),
-    action_count = output.actions.len(),
+    action_count = actions.len(),
     "Predict-Calibrate stage done: cold_start_generate"
   );
 
-  Ok(normalize_actions(output.actions))
+  Ok(normalize_actions(actions))
 }
 
 async fn predict_calibrate_extraction(
   episode: &EpisodicMemory,
   existing_facts: &[(SemanticMemory, f64)],
+  language: Language,
+  prompt_versions: &mut PromptVersions,
+  ai: &AiClient,
 ) -> Result<Vec<SemanticAction>, AppError> {
   let prediction_facts = select_relevant_facts(existing_facts);
   let action_candidates = select_action_candidates(existing_facts);
@@ -307,7 +428,14 @@ async fn predict_calibrate_extraction(
     fact_count = prediction_facts.len(),
     "Predict-Calibrate stage start: predict"
   );
-  let prediction = predict_episode(&episode.title, &prediction_facts).await?;
+  let prediction = predict_episode(
+    &episode.title,
+    &prediction_facts,
+    language,
+    prompt_versions,
+    ai,
+  )
+  .await?;
   tracing::info!(
     episode_id = %episode.id,
     elapsed_ms = predict_start.elapsed().as_millis(),
@@ -315,44 +443,77 @@ async fn predict_calibrate_extraction(
     "Predict-Calibrate stage done: predict"
   );
 
-  let existing_facts_text = format_existing_facts_for_prompt(&action_candidates);
-  let user_content = format!(
-    "## Episode Title\n{}\n\n## Existing Active Facts\n{}\n\n## PREDICTED Content\n{}\n\n## ACTUAL Messages\n{}",
+  let prompt = ai.prompts().render_in(
+    &EXTRACT_FROM_COMPARISON_PROMPT,
+    language,
+    &[("categories", SEMANTIC_CATEGORIES)],
+  );
+  prompt.record(prompt_versions);
+
+  let budget = ai
+    .token_budget(ChatTask::Consolidation)
+    .reserve(&prompt.text);
+  let section_tokens = budget.available() / SECTION_BUDGET_SHARE;
+  let header = format!(
+    "## Episode Title\n{}\n\n## Existing Active Facts\n{}\n\n## PREDICTED Content\n{}\n\n## ACTUAL Messages\n",
     episode.title,
-    existing_facts_text,
-    prediction,
-    format_messages(episode)
+    format_existing_facts_for_prompt(&action_candidates, &budget, section_tokens),
+    budget.truncate(&prediction, section_tokens)
   );
+  let chunks = message_chunks(episode, &budget.reserve(&header));
 
   tracing::debug!(episode_id = %episode.id, "Extracting semantic actions from gaps");
   let calibrate_start = Instant::now();
-  tracing::info!(episode_id = %episode.id, "Predict-Calibrate stage start: calibrate");
+  tracing::info!(
+    episode_id = %episode.id,
+    chunk_count = chunks.len(),
+    "Predict-Calibrate stage start: calibrate"
+  );
 
-  let output = generate_object::<SemanticActionOutput>(
-    vec![
-      ChatCompletionRequestMessage::System(EXTRACT_FROM_COMPARISON_PROMPT.into()),
-      ChatCompletionRequestMessage::User(user_content.into()),
-    ],
-    "pcl_calibrate".to_owned(),
-    Some("Generate semantic memory update actions from prediction-vs-actual comparison".to_owned()),
-  )
-  .await?;
+  let mut actions = Vec::new();
+  for messages in chunks {
+    let output = generate_object::<SemanticActionOutput>(
+      ChatTask::Consolidation,
+      vec![
+        ChatCompletionRequestMessage::System(prompt.text.clone().into()),
+        ChatCompletionRequestMessage::User(format!("{header}{messages}").into()),
+      ],
+      "pcl_calibrate".to_owned(),
+      Some(
+        "Generate semantic memory update actions from prediction-vs-actual comparison".to_owned(),
+      ),
+      ai,
+    )
+    .await?;
+    actions.extend(output.actions);
+  }
 
   tracing::info!(
     episode_id = %episode.id,
     elapsed_ms = calibrate_start.elapsed().as_millis(),
-    action_count = output.actions.len(),
+    action_count = actions.len(),
     "Predict-Calibrate stage done: calibrate"
   );
 
-  Ok(normalize_actions(output.actions))
+  Ok(normalize_actions(actions))
 }
 
-async fn predict_episode(title: &str, facts: &[&SemanticMemory]) -> Result<String, AppError> {
+async fn predict_episode(
+  title: &str,
+  facts: &[&SemanticMemory],
+  language: Language,
+  prompt_versions: &mut PromptVersions,
+  ai: &AiClient,
+) -> Result<String, AppError> {
   if facts.is_empty() {
     return Ok(format!("No knowledge available to predict '{title}'."));
   }
 
+  let prompt = ai
+    .prompts()
+    .render_in(&PREDICTION_SYSTEM_PROMPT, language, &[]);
+  prompt.record(prompt_versions);
+
   let facts_text = facts
     .iter()
     .map(|f| format!("- [{}] {}", f.category, f.fact))
@@ -360,10 +521,14 @@ async fn predict_episode(title: &str, facts: &[&SemanticMemory]) -> Result<Strin
     .join("\n");
   let user_content = format!("Episode Title: {title}\n\nExisting Knowledge:\n{facts_text}");
 
-  generate_text(vec![
-    ChatCompletionRequestMessage::System(PREDICTION_SYSTEM_PROMPT.into()),
-    ChatCompletionRequestMessage::User(user_content.into()),
-  ])
+  generate_text(
+    ChatTask::Prediction,
+    vec![
+      ChatCompletionRequestMessage::System(prompt.text.into()),
+      ChatCompletionRequestMessage::User(user_content.into()),
+    ],
+    ai,
+  )
   .await
 }
 
@@ -371,6 +536,8 @@ async fn consolidate_actions(
   actions: &[SemanticAction],
   source: &EpisodicMemory,
   existing_facts: &[(SemanticMemory, f64)],
+  prompt_versions: &PromptVersions,
+  ai: &AiClient,
   db: &DatabaseConnection,
 ) -> Result<(), AppError> {
   let normalized_actions = normalize_actions(actions.to_vec());
@@ -394,7 +561,7 @@ async fn consolidate_actions(
       statement_count = statements_to_embed.len(),
       "Predict-Calibrate stage start: embed_many"
     );
-    let embeddings = embed_many(&statements_to_embed).await?;
+    let embeddings = embed_many(EmbeddingKind::Document, &statements_to_embed, ai).await?;
     tracing::info!(
       episode_id = %source.id,
       elapsed_ms = embed_start.elapsed().as_millis(),
@@ -409,6 +576,7 @@ async fn consolidate_actions(
     .map(|(memory, _)| (memory.id.to_string(), memory.clone()))
     .collect::<HashMap<_, _>>();
 
+  let embedding_model = ai.embedding().model();
   let tx = db.begin().await?;
   let mut current_active_map = active_map.clone();
   let mut embedding_iter = embeddings.into_iter();
@@ -444,6 +612,7 @@ async fn consolidate_actions(
           .ok_or_else(|| AppError::new(anyhow!("Missing embedding for semantic action")))?;
         let duplicate = find_duplicate_in_scope(
           &embedding,
+          embedding_model,
           source.conversation_id,
           &current_active_map,
           &tx,
@@ -456,8 +625,16 @@ async fn consolidate_actions(
           reinforce_existing(&existing, source.id, &tx).await?;
           current_active_map.insert(existing.id.to_string(), existing);
         } else {
-          let inserted =
-            insert_new_fact(&action.fact, &action.category, embedding, source, &tx).await?;
+          let inserted = insert_new_fact(
+            &action.fact,
+            &action.category,
+            embedding,
+            embedding_model,
+            source,
+            prompt_versions,
+            &tx,
+          )
+          .await?;
           current_active_map.insert(inserted.id.to_string(), inserted);
         }
       }
@@ -469,6 +646,7 @@ async fn consolidate_actions(
         let Some(target) = resolve_active_target(&action, &current_active_map) else {
           let duplicate = find_duplicate_in_scope(
             &embedding,
+            embedding_model,
             source.conversation_id,
             &current_active_map,
             &tx,
@@ -480,8 +658,16 @@ async fn consolidate_actions(
             reinforce_existing(&existing, source.id, &tx).await?;
             current_active_map.insert(existing.id.to_string(), existing);
           } else {
-            let inserted =
-              insert_new_fact(&action.fact, &action.category, embedding, source, &tx).await?;
+            let inserted = insert_new_fact(
+              &action.fact,
+              &action.category,
+              embedding,
+              embedding_model,
+              source,
+              prompt_versions,
+              &tx,
+            )
+            .await?;
             current_active_map.insert(inserted.id.to_string(), inserted);
           }
           tracing::warn!(
@@ -494,6 +680,7 @@ async fn consolidate_actions(
 
         let duplicate = find_duplicate_in_scope(
           &embedding,
+          embedding_model,
           source.conversation_id,
           &current_active_map,
           &tx,
@@ -509,8 +696,16 @@ async fn consolidate_actions(
           reinforce_existing(&existing, source.id, &tx).await?;
           current_active_map.insert(existing.id.to_string(), existing);
         } else {
-          let inserted =
-            insert_new_fact(&action.fact, &action.category, embedding, source, &tx).await?;
+          let inserted = insert_new_fact(
+            &action.fact,
+            &action.category,
+            embedding,
+            embedding_model,
+            source,
+            prompt_versions,
+            &tx,
+          )
+          .await?;
           current_active_map.insert(inserted.id.to_string(), inserted);
         }
       }
@@ -633,12 +828,14 @@ fn select_action_candidates(facts: &[(SemanticMemory, f64)]) -> Vec<&SemanticMem
     .collect()
 }
 
-fn format_existing_facts_for_prompt(facts: &[&SemanticMemory]) -> String {
-  if facts.is_empty() {
-    return "None".to_owned();
-  }
-
-  facts
+/// Existing facts in ranking order, leaving out the lowest-ranked ones that do not fit `max_tokens`.
+fn format_existing_facts_for_prompt(
+  facts: &[&SemanticMemory],
+  budget: &TokenBudget,
+  max_tokens: usize,
+) -> String {
+  let mut used = 0;
+  let entries = facts
     .iter()
     .map(|fact| {
       format!(
@@ -650,23 +847,42 @@ fn format_existing_facts_for_prompt(facts: &[&SemanticMemory]) -> String {
         fact.source_episodic_ids.len()
       )
     })
-    .collect::<Vec<_>>()
-    .join("\n")
+    .take_while(|entry| {
+      used += budget.count(entry) + 1;
+      used <= max_tokens
+    })
+    .collect::<Vec<_>>();
+
+  if entries.len() < facts.len() {
+    tracing::debug!(
+      kept = entries.len(),
+      dropped = facts.len() - entries.len(),
+      "Existing facts exceed their prompt budget"
+    );
+  }
+  if entries.is_empty() {
+    return "None".to_owned();
+  }
+  entries.join("\n")
 }
 
 async fn load_related_facts(
   episode: &EpisodicMemory,
+  ai: &AiClient,
   db: &DatabaseConnection,
   limit: i64,
 ) -> Result<Vec<(SemanticMemory, f64)>, AppError> {
-  let content_embedding = embed(&episode.content).await?;
+  let content_embedding = embed(EmbeddingKind::Query, &episode.content, ai).await?;
   let results = SemanticMemory::retrieve_by_embedding(
     &episode.content,
-    content_embedding,
+    Some(content_embedding),
+    ai.embedding().model(),
     limit,
     episode.conversation_id,
     db,
     None,
+    // Consolidation keeps the built-in merge, whatever profile serves requests
+    &RetrievalProfile::default(),
   )
   .await?;
 
@@ -690,13 +906,21 @@ fn resolve_active_target(
 
 async fn find_duplicate_in_scope<C: ConnectionTrait>(
   embedding: &PgVector,
+  embedding_model: &str,
   conversation_id: Uuid,
   active_map: &HashMap<String, SemanticMemory>,
   db: &C,
   exclude_id: Option<Uuid>,
 ) -> Result<Option<semantic_memory::Model>, AppError> {
-  let mut similar =
-    find_similar_facts(embedding, DEDUPE_THRESHOLD, conversation_id, db, exclude_id).await?;
+  let mut similar = find_similar_facts(
+    embedding,
+    embedding_model,
+    DEDUPE_THRESHOLD,
+    conversation_id,
+    db,
+    exclude_id,
+  )
+  .await?;
   similar.sort_by(|a, b| {
     let a_current = active_map.contains_key(&a.id.to_string());
     let b_current = active_map.contains_key(&b.id.to_string());
@@ -752,7 +976,9 @@ async fn insert_new_fact<C: ConnectionTrait>(
   statement: &str,
   category: &str,
   embedding: PgVector,
+  embedding_model: &str,
   source: &EpisodicMemory,
+  prompt_versions: &PromptVersions,
   db: &C,
 ) -> Result<SemanticMemory, AppError> {
   let now = Utc::now();
@@ -767,6 +993,8 @@ async fn insert_new_fact<C: ConnectionTrait>(
     valid_at: valid_at.into(),
     invalid_at: None,
     embedding,
+    embedding_model: embedding_model.to_owned(),
+    prompt_versions: serde_json::to_value(prompt_versions)?,
     created_at: now.into(),
   };
 
@@ -784,6 +1012,7 @@ fn effective_valid_at(source: &EpisodicMemory) -> DateTime<Utc> {
 
 async fn find_similar_facts<C: ConnectionTrait>(
   embedding: &PgVector,
+  embedding_model: &str,
   threshold: f64,
   conversation_id: Uuid,
   db: &C,
@@ -791,10 +1020,12 @@ async fn find_similar_facts<C: ConnectionTrait>(
 ) -> Result<Vec<semantic_memory::Model>, AppError> {
   let sql = r"
   SELECT id, conversation_id, category, fact, source_episodic_ids,
-    valid_at, invalid_at, embedding, created_at, -(embedding <#> $1) AS similarity
+    valid_at, invalid_at, embedding, embedding_model, prompt_versions, created_at,
+    -(embedding <#> $1) AS similarity
   FROM semantic_memory
   WHERE conversation_id = $2
     AND invalid_at IS NULL
+    AND embedding_model = $5
     AND ($4::uuid IS NULL OR id <> $4)
     AND -(embedding <#> $1) > $3
   ORDER BY similarity DESC
@@ -808,6 +1039,7 @@ async fn find_similar_facts<C: ConnectionTrait>(
       conversation_id.into(),
       threshold.into(),
       exclude_id.into(),
+      embedding_model.into(),
     ],
   );
 
@@ -833,7 +1065,19 @@ async fn mark_consolidated<C: ConnectionTrait>(episode_id: Uuid, db: &C) -> Resu
 
 #[cfg(test)]
 mod tests {
-  use super::*;
+  use std::sync::Arc;
+
+  use chrono::TimeDelta;
+  use plastmem_ai::{EstimatedTokenCounter, ScriptedChatProvider};
+  use plastmem_core::ConversationMessage;
+  use plastmem_entities::EpisodeClassification;
+  use plastmem_event_segmentation::{
+    SegmentClassification, primitive_review_llm_segmenter, temporal_boundary_review_llm_segmenter,
+    temporal_rule_segmenter,
+  };
+  use plastmem_shared::{Message, fsrs::FLASHBULB_SURPRISE_THRESHOLD};
+
+  use super::{super::episode_creation::generate_episode_artifacts, *};
 
   fn action(
     kind: SemanticActionKind,
@@ -893,4 +1137,186 @@ mod tests {
     assert_eq!(normalize_category("unknown"), "identity");
     assert_eq!(normalize_category("guideline"), "guideline");
   }
+
+  fn episode(
+    conversation_id: Uuid,
+    messages: Vec<Message>,
+    title: String,
+    content: String,
+  ) -> EpisodicMemory {
+    let now = Utc::now();
+    EpisodicMemory {
+      id: Uuid::now_v7(),
+      conversation_id,
+      messages,
+      title,
+      content,
+      classification: Some(EpisodeClassification::Informative),
+      embedding: PgVector::from(vec![0.0; 4]),
+      embedding_model: String::new(),
+      prompt_versions: PromptVersions::new(),
+      stability: 1.0,
+      difficulty: 5.0,
+      surprise: 0.0,
+      is_flashbulb: false,
+      start_at: now,
+      end_at: now,
+      created_at: now,
+      last_reviewed_at: now,
+      consolidated_at: None,
+    }
+  }
+
+  #[test]
+  fn message_chunks_truncate_long_messages_and_fit_the_budget() {
+    let budget = TokenBudget::exact(200, Arc::new(EstimatedTokenCounter));
+    let messages = (0..7)
+      .map(|i| Message {
+        role: "user".into(),
+        // The last message alone is five times the budget
+        content: format!("message {i} ").repeat(if i == 6 { 100 } else { 30 }),
+        timestamp: Utc::now(),
+      })
+      .collect();
+    let long = episode(Uuid::now_v7(), messages, String::new(), String::new());
+
+    let chunks = message_chunks(&long, &budget);
+    assert!(chunks.len() > 1);
+    for chunk in &chunks {
+      assert!(budget.count(chunk) <= budget.available());
+    }
+    let lines = chunks
+      .iter()
+      .flat_map(|chunk| chunk.lines())
+      .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 7);
+    for (i, line) in lines.iter().enumerate() {
+      assert!(line.starts_with(&format!("Message {} [user]: message {i} ", i + 1)));
+      assert!(line.ends_with("[...]"));
+    }
+
+    let empty = episode(Uuid::now_v7(), Vec::new(), String::new(), String::new());
+    assert_eq!(message_chunks(&empty, &budget), [""]);
+  }
+
+  fn conversation(conversation_id: Uuid) -> Vec<ConversationMessage> {
+    let start = Utc::now() - TimeDelta::days(1);
+    [
+      ("user", "I'm moving to Tokyo next month for a new job."),
+      (
+        "assistant",
+        "Congratulations! Have you found an apartment yet?",
+      ),
+      ("user", "Not yet, I'm looking near Shibuya."),
+    ]
+    .into_iter()
+    .zip(0..)
+    .map(|((role, content), seq)| ConversationMessage {
+      conversation_id,
+      seq,
+      role: role.into(),
+      content: content.to_owned(),
+      timestamp: start + TimeDelta::minutes(seq),
+    })
+    .collect()
+  }
+
+  #[tokio::test]
+  async fn scripted_pipeline_runs_from_segmentation_to_calibration() {
+    let scripted = Arc::new(ScriptedChatProvider::new());
+    let ai = AiClient::scripted(scripted.clone());
+    let conversation_id = Uuid::now_v7();
+    let claimed = conversation(conversation_id);
+
+    // Segmentation: one short rule bucket, classified by the LLM
+    scripted.push_raw(
+      "primitive_segment_classification",
+      r#"{"classification": "informative"}"#,
+    );
+    let rule_output = temporal_rule_segmenter(&claimed).unwrap();
+    let (segments, boundaries) = primitive_review_llm_segmenter(&claimed, &rule_output, &ai)
+      .await
+      .unwrap();
+    let segments = temporal_boundary_review_llm_segmenter(&claimed, &segments, &boundaries, &ai)
+      .await
+      .unwrap();
+    assert_eq!(segments.len(), 1);
+    assert_eq!((segments[0].start_seq, segments[0].end_seq), (0, 2));
+    assert_eq!(
+      segments[0].classification,
+      SegmentClassification::Informative
+    );
+
+    // Episode creation
+    scripted.push_raw("episodic_time_anchoring", r#"{"insertions": []}"#);
+    scripted.push_raw(
+      "episodic_title_generation",
+      r#"{"title": "Moving to Tokyo", "surprise_level": "high"}"#,
+    );
+    let messages = claimed
+      .iter()
+      .map(ConversationMessage::to_message)
+      .collect::<Vec<Message>>();
+    let mut prompt_versions = PromptVersions::new();
+    let artifacts =
+      generate_episode_artifacts(&messages, Language::English, &mut prompt_versions, &ai)
+        .await
+        .unwrap();
+    assert_eq!(artifacts.title, "Moving to Tokyo");
+    assert!(artifacts.content.contains("looking near Shibuya"));
+    assert!(artifacts.surprise < FLASHBULB_SURPRISE_THRESHOLD);
+
+    let episode = episode(
+      conversation_id,
+      messages,
+      artifacts.title,
+      artifacts.content,
+    );
+
+    // Predict-calibrate, first without and then with existing knowledge
+    scripted.push_raw(
+      "pcl_cold_start",
+      r#"{"actions": [{"kind": "new", "fact": "User is moving to Tokyo", "category": "identity", "target_fact_id": "", "justification": "stated", "confidence": 0.9}]}"#,
+    );
+    let actions =
+      cold_start_extraction(&episode, Language::English, &mut PromptVersions::new(), &ai)
+        .await
+        .unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].kind, SemanticActionKind::New);
+
+    let fact = SemanticMemory {
+      id: Uuid::now_v7(),
+      conversation_id,
+      category: actions[0].category.clone(),
+      fact: actions[0].fact.clone(),
+      source_episodic_ids: vec![episode.id],
+      valid_at: episode.end_at,
+      invalid_at: None,
+      embedding: PgVector::from(vec![0.0; 4]),
+      embedding_model: String::new(),
+      prompt_versions: PromptVersions::new(),
+      created_at: episode.created_at,
+    };
+    scripted.push_text("The user talks about their move to Tokyo.");
+    scripted.push_raw(
+      "pcl_calibrate",
+      format!(
+        r#"{{"actions": [{{"kind": "update", "fact": "User is moving to Tokyo and looking for an apartment near Shibuya", "category": "identity", "target_fact_id": "{}", "justification": "new detail", "confidence": 0.8}}]}}"#,
+        fact.id
+      ),
+    );
+    let actions = predict_calibrate_extraction(
+      &episode,
+      &[(fact.clone(), 1.0)],
+      Language::English,
+      &mut PromptVersions::new(),
+      &ai,
+    )
+    .await
+    .unwrap();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].kind, SemanticActionKind::Update);
+    assert_eq!(actions[0].target_fact_id, fact.id.to_string());
+  }
 }
@@ -0,0 +1,209 @@
+use apalis::prelude::{Data, TaskSink};
+use apalis_postgres::PostgresStorage;
+use plastmem_ai::{AiClient, EmbeddingKind, embed_many};
+use plastmem_entities::{episodic_memory, semantic_memory};
+use plastmem_shared::AppError;
+use sea_orm::{
+  ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, PaginatorTrait,
+  QueryFilter, QuerySelect, Set, Statement, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Rows re-embedded per table in one job run.
+const REEMBED_BATCH_SIZE: u64 = 64;
+
+/// Job to move stored embeddings to the active embedding model.
+///
+/// Each run re-embeds one batch of rows whose `embedding_model` differs from the
+/// job's model and enqueues the next run while rows remain. Progress lives in the
+/// tables themselves, so an interrupted migration resumes where it stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReembedJob {
+  pub embedding_model: String,
+}
+
+/// Enqueue a [`ReembedJob`] if any memory was embedded by another model.
+pub async fn enqueue_reembed_if_needed(
+  db: &DatabaseConnection,
+  ai: &AiClient,
+  reembed_storage: &PostgresStorage<ReembedJob>,
+) -> Result<(), AppError> {
+  let embedding_model = ai.embedding().model();
+
+  let stale_episodic = episodic_memory::Entity::find()
+    .filter(episodic_memory::Column::EmbeddingModel.ne(embedding_model))
+    .count(db)
+    .await?;
+  let stale_semantic = semantic_memory::Entity::find()
+    .filter(semantic_memory::Column::EmbeddingModel.ne(embedding_model))
+    .count(db)
+    .await?;
+
+  if stale_episodic == 0 && stale_semantic == 0 {
+    return Ok(());
+  }
+
+  // A migration left pending or running by an earlier start resumes on its own
+  if has_active_reembed_job(embedding_model, db).await? {
+    tracing::debug!(
+      embedding_model,
+      "Re-embedding already queued, not enqueueing another job"
+    );
+    return Ok(());
+  }
+
+  tracing::info!(
+    embedding_model,
+    stale_episodic,
+    stale_semantic,
+    "Embedding model changed, enqueueing re-embedding"
+  );
+  let mut storage = reembed_storage.clone();
+  storage
+    .push(ReembedJob {
+      embedding_model: embedding_model.to_owned(),
+    })
+    .await?;
+
+  Ok(())
+}
+
+#[derive(Debug, FromQueryResult)]
+struct ActiveJobsRow {
+  active_jobs: i64,
+}
+
+async fn has_active_reembed_job(
+  embedding_model: &str,
+  db: &DatabaseConnection,
+) -> Result<bool, AppError> {
+  let sql = "SELECT COUNT(*)::bigint AS active_jobs FROM apalis.jobs \
+    WHERE status IN ('Pending', 'Running') AND job_type LIKE '%ReembedJob%' \
+    AND convert_from(job, 'UTF8')::jsonb->>'embedding_model' = $1";
+
+  let row = ActiveJobsRow::find_by_statement(Statement::from_sql_and_values(
+    DbBackend::Postgres,
+    sql,
+    [embedding_model.into()],
+  ))
+  .one(db)
+  .await?;
+
+  Ok(row.is_some_and(|row| row.active_jobs > 0))
+}
+
+pub async fn process_reembed(
+  job: ReembedJob,
+  db: Data<DatabaseConnection>,
+  ai: Data<AiClient>,
+  reembed_storage: Data<PostgresStorage<ReembedJob>>,
+) -> Result<(), AppError> {
+  let db = &*db;
+  let ai = &*ai;
+
+  // A restart with yet another model enqueues its own job
+  if job.embedding_model != ai.embedding().model() {
+    tracing::debug!(
+      embedding_model = %job.embedding_model,
+      "Skipping re-embed job for an inactive embedding model"
+    );
+    return Ok(());
+  }
+
+  let episodic = reembed_episodic_batch(&job.embedding_model, ai, db).await?;
+  let semantic = reembed_semantic_batch(&job.embedding_model, ai, db).await?;
+
+  if episodic == 0 && semantic == 0 {
+    tracing::info!(embedding_model = %job.embedding_model, "Re-embedding complete");
+    return Ok(());
+  }
+
+  tracing::info!(
+    embedding_model = %job.embedding_model,
+    episodic,
+    semantic,
+    "Re-embedded memory batch"
+  );
+  let mut storage = (*reembed_storage).clone();
+  storage.push(job).await?;
+
+  Ok(())
+}
+
+async fn reembed_episodic_batch(
+  embedding_model: &str,
+  ai: &AiClient,
+  db: &DatabaseConnection,
+) -> Result<usize, AppError> {
+  let rows: Vec<(Uuid, String)> = episodic_memory::Entity::find()
+    .select_only()
+    .columns([
+      episodic_memory::Column::Id,
+      episodic_memory::Column::Content,
+    ])
+    .filter(episodic_memory::Column::EmbeddingModel.ne(embedding_model))
+    .limit(REEMBED_BATCH_SIZE)
+    .into_tuple()
+    .all(db)
+    .await?;
+
+  if rows.is_empty() {
+    return Ok(0);
+  }
+
+  let (ids, contents): (Vec<Uuid>, Vec<String>) = rows.into_iter().unzip();
+  let embeddings = embed_many(EmbeddingKind::Document, &contents, ai).await?;
+
+  let tx = db.begin().await?;
+  for (id, embedding) in ids.iter().zip(embeddings) {
+    episodic_memory::Entity::update(episodic_memory::ActiveModel {
+      id: Set(*id),
+      embedding: Set(embedding),
+      embedding_model: Set(embedding_model.to_owned()),
+      ..Default::default()
+    })
+    .exec(&tx)
+    .await?;
+  }
+  tx.commit().await?;
+
+  Ok(ids.len())
+}
+
+async fn reembed_semantic_batch(
+  embedding_model: &str,
+  ai: &AiClient,
+  db: &DatabaseConnection,
+) -> Result<usize, AppError> {
+  let rows: Vec<(Uuid, String)> = semantic_memory::Entity::find()
+    .select_only()
+    .columns([semantic_memory::Column::Id, semantic_memory::Column::Fact])
+    .filter(semantic_memory::Column::EmbeddingModel.ne(embedding_model))
+    .limit(REEMBED_BATCH_SIZE)
+    .into_tuple()
+    .all(db)
+    .await?;
+
+  if rows.is_empty() {
+    return Ok(0);
+  }
+
+  let (ids, facts): (Vec<Uuid>, Vec<String>) = rows.into_iter().unzip();
+  let embeddings = embed_many(EmbeddingKind::Document, &facts, ai).await?;
+
+  let tx = db.begin().await?;
+  for (id, embedding) in ids.iter().zip(embeddings) {
+    semantic_memory::Entity::update(semantic_memory::ActiveModel {
+      id: Set(*id),
+      embedding: Set(embedding),
+      embedding_model: Set(embedding_model.to_owned()),
+      ..Default::default()
+    })
+    .exec(&tx)
+    .await?;
+  }
+  tx.commit().await?;
+
+  Ok(ids.len())
+}
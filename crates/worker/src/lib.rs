@@ -2,9 +2,10 @@ use std::time::Duration;
 
 use apalis::{
   layers::WorkerBuilderExt,
-  prelude::{Monitor, WorkerBuilder},
+  prelude::{Data, Monitor, WorkerBuilder},
 };
 use apalis_postgres::PostgresStorage;
+use plastmem_ai::AiClient;
 use plastmem_shared::APP_ENV;
 use plastmem_shared::AppError;
 use sea_orm::DatabaseConnection;
@@ -14,23 +15,30 @@ pub use jobs::EpisodeCreationJob;
 pub use jobs::EventSegmentationJob;
 pub use jobs::MemoryReviewJob;
 pub use jobs::PredictCalibrateJob;
+pub use jobs::ReembedJob;
 use jobs::{
-  WorkerError, process_episode_creation, process_event_segmentation, process_memory_review,
-  process_predict_calibrate,
+  WorkerError, enqueue_reembed_if_needed, process_episode_creation, process_event_segmentation,
+  process_memory_review, process_predict_calibrate, process_reembed, run_when_ai_available,
 };
 
 pub async fn worker(
   db: &DatabaseConnection,
+  ai: &AiClient,
   segmentation_backend: PostgresStorage<EventSegmentationJob>,
   episode_creation_backend: PostgresStorage<EpisodeCreationJob>,
   review_backend: PostgresStorage<MemoryReviewJob>,
   semantic_backend: PostgresStorage<PredictCalibrateJob>,
+  reembed_backend: PostgresStorage<ReembedJob>,
 ) -> Result<(), AppError> {
   let db = db.clone();
+  let ai = ai.clone();
+
+  enqueue_reembed_if_needed(&db, &ai, &reembed_backend).await?;
 
   Monitor::new()
     .register({
       let db = db.clone();
+      let ai = ai.clone();
       let segmentation_backend = segmentation_backend.clone();
       let episode_creation_backend = episode_creation_backend.clone();
       let review_backend = review_backend.clone();
@@ -40,17 +48,29 @@ pub async fn worker(
           .concurrency(1)
           .enable_tracing()
           .data(db.clone())
+          .data(ai.clone())
           .data(segmentation_backend.clone())
           .data(episode_creation_backend.clone())
           .data(review_backend.clone())
           .build(
-            move |job, data, segmentation_storage, episode_creation_storage, review_storage| async move {
-              process_event_segmentation(
-                job,
-                data,
-                segmentation_storage,
-                episode_creation_storage,
-                review_storage,
+            move |job: EventSegmentationJob,
+                  data: Data<DatabaseConnection>,
+                  ai: Data<AiClient>,
+                  segmentation_storage: Data<PostgresStorage<EventSegmentationJob>>,
+                  episode_creation_storage: Data<PostgresStorage<EpisodeCreationJob>>,
+                  review_storage: Data<PostgresStorage<MemoryReviewJob>>| async move {
+              run_when_ai_available(
+                || {
+                  process_event_segmentation(
+                    job.clone(),
+                    data.clone(),
+                    ai.clone(),
+                    segmentation_storage.clone(),
+                    episode_creation_storage.clone(),
+                    review_storage.clone(),
+                  )
+                },
+                &ai,
               )
               .await
               .map_err(WorkerError::from)
@@ -60,6 +80,7 @@ pub async fn worker(
     })
     .register({
       let db = db.clone();
+      let ai = ai.clone();
       let semantic_backend = semantic_backend.clone();
       move |_run_id| {
         WorkerBuilder::new("episode-creation")
@@ -67,38 +88,101 @@ pub async fn worker(
           .concurrency(APP_ENV.predict_calibrate_concurrency)
           .enable_tracing()
           .data(db.clone())
+          .data(ai.clone())
           .data(semantic_backend.clone())
-          .build(move |job, data, predict_storage| async move {
-            process_episode_creation(job, data, predict_storage)
+          .build(
+            move |job: EpisodeCreationJob,
+                  data: Data<DatabaseConnection>,
+                  ai: Data<AiClient>,
+                  predict_storage: Data<PostgresStorage<PredictCalibrateJob>>| async move {
+              run_when_ai_available(
+                || {
+                  process_episode_creation(
+                    job.clone(),
+                    data.clone(),
+                    ai.clone(),
+                    predict_storage.clone(),
+                  )
+                },
+                &ai,
+              )
               .await
               .map_err(WorkerError::from)
-          })
+            },
+          )
       }
     })
     .register({
       let db = db.clone();
+      let ai = ai.clone();
       move |_run_id| {
         WorkerBuilder::new("memory-review")
           .backend(review_backend.clone())
           .enable_tracing()
           .data(db.clone())
-          .build(move |job, data| async move {
-            process_memory_review(job, data)
+          .data(ai.clone())
+          .build(
+            move |job: MemoryReviewJob,
+                  data: Data<DatabaseConnection>,
+                  ai: Data<AiClient>| async move {
+              run_when_ai_available(
+                || {
+                  process_memory_review(job.clone(), data.clone(), ai.clone())
+                },
+                &ai,
+              )
               .await
               .map_err(WorkerError::from)
-          })
+            },
+          )
       }
     })
     .register({
       let db = db.clone();
+      let ai = ai.clone();
       move |_run_id| {
         WorkerBuilder::new("predict-calibrate")
           .backend(semantic_backend.clone())
           .concurrency(APP_ENV.predict_calibrate_concurrency)
           .enable_tracing()
           .data(db.clone())
-          .build(move |job, data| async move {
-            process_predict_calibrate(job, data)
+          .data(ai.clone())
+          .data(semantic_backend.clone())
+          .build(
+            move |job: PredictCalibrateJob,
+                  data: Data<DatabaseConnection>,
+                  ai: Data<AiClient>,
+                  predict_storage: Data<PostgresStorage<PredictCalibrateJob>>| async move {
+              run_when_ai_available(
+                || {
+                  process_predict_calibrate(
+                    job.clone(),
+                    data.clone(),
+                    ai.clone(),
+                    predict_storage.clone(),
+                  )
+                },
+                &ai,
+              )
+              .await
+              .map_err(WorkerError::from)
+            },
+          )
+      }
+    })
+    .register({
+      let db = db.clone();
+      let ai = ai.clone();
+      move |_run_id| {
+        WorkerBuilder::new("reembed")
+          .backend(reembed_backend.clone())
+          .concurrency(1)
+          .enable_tracing()
+          .data(db.clone())
+          .data(ai.clone())
+          .data(reembed_backend.clone())
+          .build(move |job, data, ai, reembed_storage| async move {
+            process_reembed(job, data, ai, reembed_storage)
               .await
               .map_err(WorkerError::from)
           })
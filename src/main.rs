@@ -1,11 +1,19 @@
+use std::sync::Arc;
+
 #[cfg(debug_assertions)]
 use apalis_board_api::sse::{TracingBroadcaster, TracingSubscriber};
 use apalis_postgres::PostgresStorage;
+use plastmem_ai::{AiClient, PromptRegistry};
+use plastmem_core::{
+  EmbeddingCache, LlmCallLog, ModelPrices, OutputTemplates, RetrievalProfiles, UsageLedger,
+  load_prompt_templates, verify_embedding_dim,
+};
 use plastmem_migration::{Migrator, MigratorTrait};
 use plastmem_server::server;
 use plastmem_shared::{APP_ENV, AppError};
 use plastmem_worker::{
-  EpisodeCreationJob, EventSegmentationJob, MemoryReviewJob, PredictCalibrateJob, worker,
+  EpisodeCreationJob, EventSegmentationJob, MemoryReviewJob, PredictCalibrateJob, ReembedJob,
+  worker,
 };
 use sea_orm::Database;
 use tracing_error::ErrorLayer;
@@ -43,27 +51,93 @@ async fn main() -> Result<(), AppError> {
   // Apply all pending migrations
   // https://www.sea-ql.org/SeaORM/docs/migration/running-migration/#migrating-programmatically
   Migrator::up(&db, None).await?;
+  verify_embedding_dim(&db, APP_ENV.embedding_dim).await?;
   let pool = db.get_postgres_connection_pool();
   PostgresStorage::setup(pool).await?;
   let segment_job_storage = PostgresStorage::<EventSegmentationJob>::new(pool);
   let episode_creation_job_storage = PostgresStorage::<EpisodeCreationJob>::new(pool);
   let review_job_storage = PostgresStorage::<MemoryReviewJob>::new(pool);
   let semantic_job_storage = PostgresStorage::<PredictCalibrateJob>::new(pool);
+  let reembed_job_storage = PostgresStorage::<ReembedJob>::new(pool);
+  let ai = AiClient::from_env()?;
+  let ai = if APP_ENV.embedding_cache_enabled {
+    let db = db.clone();
+    ai.map_embedding(|inner| {
+      Arc::new(EmbeddingCache::new(
+        inner,
+        db,
+        APP_ENV.embedding_dim,
+        APP_ENV.embedding_cache_max_entries,
+      ))
+    })
+  } else {
+    ai
+  };
+  let prices = APP_ENV
+    .ai_model_prices
+    .as_deref()
+    .map(ModelPrices::parse)
+    .transpose()?
+    .unwrap_or_default();
+  let ai = ai.with_usage_sink(Arc::new(UsageLedger::new(db.clone(), prices)));
+  let ai = if APP_ENV.llm_call_log_enabled {
+    ai.with_call_sink(Arc::new(LlmCallLog::new(db.clone())))
+  } else {
+    ai
+  };
+  // Table overrides take precedence over files in PROMPT_TEMPLATES_DIR
+  let mut prompts = PromptRegistry::default();
+  if let Some(dir) = APP_ENV.prompt_templates_dir.as_deref() {
+    prompts = prompts.load_dir(dir)?;
+  }
+  let prompts = load_prompt_templates(&db)
+    .await?
+    .into_iter()
+    .fold(prompts, PromptRegistry::with_template);
+  for template in prompts.templates() {
+    tracing::info!(prompt = %template.name, version = %template.version, "Prompt override loaded");
+  }
+  let ai = ai.with_prompts(prompts);
+  // Files in OUTPUT_TEMPLATES_DIR may replace the built-in presets
+  let mut output_templates = OutputTemplates::default();
+  if let Some(dir) = APP_ENV.output_templates_dir.as_deref() {
+    output_templates = output_templates.load_dir(dir)?;
+  }
+  tracing::info!(templates = ?output_templates.names(), "Output templates loaded");
+  let mut retrieval_profiles = APP_ENV
+    .retrieval_profiles
+    .as_deref()
+    .map(RetrievalProfiles::parse)
+    .transpose()?
+    .unwrap_or_default();
+  if let Some(name) = APP_ENV.retrieval_profile.as_deref() {
+    retrieval_profiles = retrieval_profiles.with_default(name)?;
+  }
+  tracing::info!(
+    profiles = ?retrieval_profiles.names(),
+    default = retrieval_profiles.default_name(),
+    "Retrieval profiles loaded"
+  );
 
   let _ = tokio::try_join!(
     worker(
       &db,
+      &ai,
       segment_job_storage.clone(),
       episode_creation_job_storage.clone(),
       review_job_storage.clone(),
-      semantic_job_storage.clone()
+      semantic_job_storage.clone(),
+      reembed_job_storage
     ),
     server(
       db.clone(),
+      ai,
       segment_job_storage,
       episode_creation_job_storage,
       review_job_storage,
       semantic_job_storage,
+      output_templates,
+      retrieval_profiles,
       #[cfg(debug_assertions)]
       board_broadcaster
     )